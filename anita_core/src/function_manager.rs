@@ -4,6 +4,22 @@ pub trait FunctionManager {
         identifier: &str,
         calling_conventrion: cranelift::prelude::isa::CallConv,
     ) -> Option<cranelift::prelude::Signature>;
+    /// Returns the partial derivatives of a function for [`differentiate`](crate::jit::frontend::derivative::differentiate).
+    ///
+    /// Functions without derivatives can not be differentiated.
+    fn function_derivatives(_identifier: &str) -> Option<Derivatives> {
+        None
+    }
+}
+
+/// Partial derivatives of a function with respect to each of its parameters.
+///
+/// The partials are expressions in the parameter names, e.g. `hypot(x, y)` has the parameters `["x", "y"]`
+/// and the partials `["x / hypot(x, y)", "y / hypot(x, y)"]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Derivatives {
+    pub parameters: &'static [&'static str],
+    pub partials: &'static [&'static str],
 }
 
 pub struct NoFunctions {}
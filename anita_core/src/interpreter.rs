@@ -0,0 +1,589 @@
+//! Evaluation of expressions without generating machine code.
+//!
+//! The [`Interpreter`] parses, checks and optimizes an expression like the [`JIT`] and resolves its variables and functions once.
+//! Every call then walks the resolved tree. Functions of the [`FunctionManager`] are called through their symbols.
+//! Interpreted functions are slower than compiled ones. They still work on hosts without a JIT, e.g. hosts that forbid memory that is both
+//! writable and executable or architectures that `cranelift_native` does not support.
+//!
+//! [`Backend`] selects the JIT or the interpreter at runtime.
+//! The [`interpret_expression!`](crate::interpret_expression) macro is the interpreted counterpart of untyped [`compile_expression!`](crate::compile_expression).
+
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    mem,
+    ops::{Add, Div, Mul, Neg, Sub},
+    sync::Arc,
+};
+
+use cranelift::prelude::isa::CallConv;
+
+use crate::{
+    function_manager::FunctionManager,
+    jit::{
+        compiled_function::DynamicFunction,
+        frontend::{
+            definitions,
+            optimizer::{optimize, optimize_assuming_finite, Operation},
+            Expr, LoopKind, Span, Syntax,
+        },
+        types::{pointer_type, Real},
+        JITError, TranslatorError, DEFAULT_MAX_ITERATIONS, JIT,
+    },
+};
+
+/// Prepares an expression for the [`Interpreter`] and wraps it like the untyped forms of [`compile_expression!`](crate::compile_expression).
+///
+/// The result is a [`CompiledFunction`](crate::jit::compiled_function::CompiledFunction) of a closure instead of a `fn` pointer,
+/// which works on hosts that can not run the JIT.
+#[macro_export]
+macro_rules! interpret_expression {
+    ($expression:expr, ($($parameter:ident),*) -> $target:ty) => {
+        $crate::interpret_expression!($expression, ($($parameter),*) -> $target, $crate::function_manager::NoFunctions)
+    };
+
+    ($expression:expr, ($($parameter:ident),*) -> $target:ty, $functions:ty) => {
+        {
+            use $crate::{interpreter::Interpreter, jit::compiled_function::CompiledFunction};
+
+            Interpreter::<$target, $functions>::default()
+                .compile_dynamic($expression, &[$( stringify!($parameter) ),*])
+                .map(|function| {
+                    CompiledFunction::interpreted(move |$($parameter: $target),*| -> $target {
+                        // SAFETY: the function was prepared for exactly these parameters.
+                        unsafe { function.call_unchecked(<[$target]>::as_ptr(&[$($parameter),*])) }
+                    })
+                })
+        }
+    };
+}
+
+/// Largest amount of parameters of a function the interpreter can call.
+pub const MAX_ARITY: usize = 6;
+
+/// Numbers expressions can be interpreted for.
+///
+/// The operations have the same results as the lowering of the [`AnitaType`](crate::jit::types::AnitaType),
+/// so an interpreted expression evaluates to exactly what the compiled one returns.
+pub trait Interpretable:
+    Real
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Send
+    + Sync
+{
+    /// Rounds towards zero, used by `%`.
+    fn trunc(self) -> Self;
+}
+
+impl Interpretable for f32 {
+    fn trunc(self) -> Self {
+        f32::trunc(self)
+    }
+}
+
+impl Interpretable for f64 {
+    fn trunc(self) -> Self {
+        f64::trunc(self)
+    }
+}
+
+/// Evaluates expressions by walking their tree, see the [module documentation](self).
+pub struct Interpreter<T: Interpretable, F: FunctionManager> {
+    optimize: bool,
+    assume_finite: bool,
+    syntax: Syntax,
+    max_iterations: u64,
+    _function_manager: PhantomData<F>,
+    _type: PhantomData<T>,
+}
+
+impl<T: Interpretable, F: FunctionManager> Default for Interpreter<T, F> {
+    fn default() -> Self {
+        Self {
+            optimize: true,
+            assume_finite: false,
+            syntax: Syntax::default(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            _function_manager: PhantomData,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T: Interpretable, F: FunctionManager> Interpreter<T, F> {
+    /// Enables or disables the optimization pass, see [`JIT::set_optimize`].
+    pub fn set_optimize(&mut self, optimize: bool) -> &mut Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Lets the optimization pass assume finite operands, see [`JIT::set_assume_finite`].
+    pub fn set_assume_finite(&mut self, assume_finite: bool) -> &mut Self {
+        self.assume_finite = assume_finite;
+        self
+    }
+
+    /// Sets the syntax expressions are parsed in, see [`JIT::set_syntax`].
+    pub fn set_syntax(&mut self, syntax: Syntax) -> &mut Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Sets the amount of loop iterations per evaluation, see [`JIT::set_max_iterations`].
+    pub fn set_max_iterations(&mut self, max_iterations: u64) -> &mut Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Prepares `expression` to be evaluated as a function of the `parameters`.
+    ///
+    /// Reports the same errors as [`JIT::compile_dynamic`] and returns a [`DynamicFunction`] with the same interface.
+    pub fn compile_dynamic<E: AsRef<str>>(
+        &self,
+        expression: E,
+        parameters: &[&str],
+    ) -> Result<DynamicFunction<T>, JITError> {
+        let ast = self.syntax.parse(expression.as_ref())?;
+        JIT::<T, F>::check_variables(&ast, parameters)?;
+        let ast = definitions::inline(ast)?;
+        let ast = match (self.optimize, self.assume_finite) {
+            (true, false) => optimize::<T>(ast),
+            (true, true) => optimize_assuming_finite::<T>(ast),
+            (false, _) => ast,
+        };
+        let mut resolver = Resolver::<T, F>::new(parameters);
+        let root = resolver.resolve(ast)?;
+        let program = Program {
+            root,
+            variables: resolver.count,
+            max_iterations: self.max_iterations,
+        };
+        Ok(DynamicFunction::interpreted(
+            Arc::new(move |arguments| program.evaluate(arguments)),
+            parameters,
+        ))
+    }
+}
+
+/// Whether expressions are compiled by the [`JIT`] or evaluated by the [`Interpreter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Jit,
+    Interpreter,
+}
+
+impl Backend {
+    /// Selects the interpreter if `cranelift_native` does not support the host, see [`JIT::try_default`], and the JIT otherwise.
+    ///
+    /// Hosts that forbid executable memory can not be detected and need [`Backend::Interpreter`].
+    pub fn detect() -> Self {
+        match cranelift_native::builder().is_err() {
+            true => Backend::Interpreter,
+            false => Backend::Jit,
+        }
+    }
+
+    /// Compiles or prepares `expression` as a function of the `parameters`, see [`JIT::compile_dynamic`] and [`Interpreter::compile_dynamic`].
+    pub fn compile_dynamic<T: Interpretable, F: FunctionManager, E: AsRef<str>>(
+        self,
+        expression: E,
+        parameters: &[&str],
+    ) -> Result<DynamicFunction<T>, JITError> {
+        match self {
+            Backend::Jit => JIT::<T, F>::try_default()?.compile_dynamic(expression, parameters),
+            Backend::Interpreter => {
+                Interpreter::<T, F>::default().compile_dynamic(expression, parameters)
+            }
+        }
+    }
+}
+
+/// A resolved expression and the amount of variables it uses, the parameters come first.
+struct Program<T> {
+    root: Node<T>,
+    variables: usize,
+    max_iterations: u64,
+}
+
+impl<T: Interpretable> Program<T> {
+    fn evaluate(&self, arguments: &[T]) -> T {
+        // Variables are only read after they are assigned, the initial value is never observed.
+        let mut variables = vec![T::from_f64(0.0); self.variables];
+        variables[..arguments.len()].copy_from_slice(arguments);
+        let mut state = State {
+            variables,
+            iterations: self.max_iterations,
+        };
+        self.root.evaluate(&mut state)
+    }
+}
+
+/// The variables of an evaluation and the iterations left to its loops, see [`JIT::set_max_iterations`].
+struct State<T> {
+    variables: Vec<T>,
+    iterations: u64,
+}
+
+/// An expression whose variables are resolved to indices and whose functions are resolved to their addresses.
+///
+/// `x ^ y` is a call of `inbuilt_pow` like in the compiled code.
+enum Node<T> {
+    Const(T),
+    Read(usize),
+    Assign(usize, Box<Node<T>>),
+    Chain(Box<Node<T>>, Box<Node<T>>),
+    Call(Function, Vec<Node<T>>),
+    Binary(Operation, Box<Node<T>>, Box<Node<T>>),
+    Neg(Box<Node<T>>),
+    Not(Box<Node<T>>),
+    If(Box<Node<T>>, Box<Node<T>>, Box<Node<T>>),
+    Loop {
+        kind: LoopKind,
+        variable: usize,
+        start: Box<Node<T>>,
+        end: Box<Node<T>>,
+        body: Box<Node<T>>,
+    },
+}
+
+impl<T: Interpretable> Node<T> {
+    /// Evaluates the node to a number, conditions are `1` if set and `0` otherwise.
+    fn evaluate(&self, state: &mut State<T>) -> T {
+        match self {
+            Node::Const(value) => *value,
+            Node::Read(index) => state.variables[*index],
+            Node::Assign(index, value) => {
+                let value = value.evaluate(state);
+                state.variables[*index] = value;
+                value
+            }
+            Node::Chain(side, ret) => {
+                side.evaluate(state);
+                ret.evaluate(state)
+            }
+            Node::Call(function, args) => {
+                let args: Vec<T> = args.iter().map(|arg| arg.evaluate(state)).collect();
+                // SAFETY: the signature of the function was checked when it was resolved.
+                unsafe { function.call(&args) }
+            }
+            Node::Binary(operation, lhs, rhs) => {
+                let arithmetic: fn(T, T) -> T = match operation {
+                    Operation::Add => |lhs, rhs| lhs + rhs,
+                    Operation::Sub => |lhs, rhs| lhs - rhs,
+                    Operation::Mul => |lhs, rhs| lhs * rhs,
+                    Operation::Div => |lhs, rhs| lhs / rhs,
+                    // `value - trunc(value / modulus) * modulus` like the lowering.
+                    Operation::Mod => |lhs, rhs| lhs - (lhs / rhs).trunc() * rhs,
+                    _ => return number(self.condition(state)),
+                };
+                let lhs = lhs.evaluate(state);
+                arithmetic(lhs, rhs.evaluate(state))
+            }
+            Node::Neg(value) => -value.evaluate(state),
+            Node::Not(_) => number(self.condition(state)),
+            Node::If(condition, then, otherwise) => match condition.condition(state) {
+                true => then.evaluate(state),
+                false => otherwise.evaluate(state),
+            },
+            Node::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+            } => {
+                let mut value = start.evaluate(state);
+                let end = end.evaluate(state);
+                let mut result = T::from_f64(kind.initial());
+                while state.iterations > 0 && (value < end || (kind.inclusive() && value == end)) {
+                    state.iterations -= 1;
+                    state.variables[*variable] = value;
+                    let iteration = body.evaluate(state);
+                    result = match kind {
+                        LoopKind::For => iteration,
+                        LoopKind::Sum => result + iteration,
+                        LoopKind::Product => result * iteration,
+                    };
+                    value = value + T::from_f64(1.0);
+                }
+                result
+            }
+        }
+    }
+
+    /// Evaluates the node to a condition, numbers other than `0` are true.
+    fn condition(&self, state: &mut State<T>) -> bool {
+        let Node::Binary(operation, lhs, rhs) = self else {
+            return match self {
+                Node::Not(value) => !value.condition(state),
+                Node::If(condition, then, otherwise) => match condition.condition(state) {
+                    true => then.condition(state),
+                    false => otherwise.condition(state),
+                },
+                _ => self.evaluate(state) != T::from_f64(0.0),
+            };
+        };
+        match operation {
+            Operation::And => lhs.condition(state) && rhs.condition(state),
+            Operation::Or => lhs.condition(state) || rhs.condition(state),
+            Operation::Eq
+            | Operation::Neq
+            | Operation::Gt
+            | Operation::Lt
+            | Operation::Geq
+            | Operation::Leq => {
+                let lhs = lhs.evaluate(state);
+                let rhs = rhs.evaluate(state);
+                match operation {
+                    Operation::Eq => lhs == rhs,
+                    Operation::Neq => lhs != rhs,
+                    Operation::Gt => lhs > rhs,
+                    Operation::Lt => lhs < rhs,
+                    Operation::Geq => lhs >= rhs,
+                    _ => lhs <= rhs,
+                }
+            }
+            _ => self.evaluate(state) != T::from_f64(0.0),
+        }
+    }
+}
+
+fn number<T: Interpretable>(condition: bool) -> T {
+    T::from_f64(f64::from(u8::from(condition)))
+}
+
+/// Address of an `extern "C"` function that takes `arity` numbers and returns one.
+#[derive(Clone, Copy)]
+struct Function {
+    address: usize,
+    arity: usize,
+}
+
+impl Function {
+    /// # Safety
+    ///
+    /// The function has to take `args.len()` numbers of type `T` and return one.
+    unsafe fn call<T: Interpretable>(self, args: &[T]) -> T {
+        let address = self.address;
+        // SAFETY: guaranteed by the caller.
+        unsafe {
+            match *args {
+                [] => mem::transmute::<usize, extern "C" fn() -> T>(address)(),
+                [a] => mem::transmute::<usize, extern "C" fn(T) -> T>(address)(a),
+                [a, b] => mem::transmute::<usize, extern "C" fn(T, T) -> T>(address)(a, b),
+                [a, b, c] => mem::transmute::<usize, extern "C" fn(T, T, T) -> T>(address)(a, b, c),
+                [a, b, c, d] => {
+                    mem::transmute::<usize, extern "C" fn(T, T, T, T) -> T>(address)(a, b, c, d)
+                }
+                [a, b, c, d, e] => mem::transmute::<usize, extern "C" fn(T, T, T, T, T) -> T>(
+                    address,
+                )(a, b, c, d, e),
+                [a, b, c, d, e, f] => {
+                    mem::transmute::<usize, extern "C" fn(T, T, T, T, T, T) -> T>(address)(
+                        a, b, c, d, e, f,
+                    )
+                }
+                _ => unreachable!("functions take at most {MAX_ARITY} arguments"),
+            }
+        }
+    }
+}
+
+/// Resolves variables and functions while converting an [`Expr`] to a [`Node`].
+///
+/// Reports the errors the translation of the [`JIT`] reports for the same expression.
+struct Resolver<T: Interpretable, F: FunctionManager> {
+    variables: HashMap<String, usize>,
+    /// Amount of indices, every parameter has one even if its name is repeated.
+    count: usize,
+    functions: HashMap<String, Function>,
+    symbols: HashMap<&'static str, usize>,
+    _function_manager: PhantomData<F>,
+    _type: PhantomData<T>,
+}
+
+impl<T: Interpretable, F: FunctionManager> Resolver<T, F> {
+    fn new(parameters: &[&str]) -> Self {
+        let mut variables = HashMap::new();
+        // A repeated parameter takes the last of its arguments, like in the compiled code.
+        for (index, parameter) in parameters.iter().enumerate() {
+            variables.insert(parameter.to_string(), index);
+        }
+        let functions = T::inbuilt_functions()
+            .iter()
+            .map(|(name, address, arity)| {
+                let function = Function {
+                    address: *address as usize,
+                    arity: *arity,
+                };
+                (name.to_string(), function)
+            })
+            .collect();
+        let symbols = F::function_symbols()
+            .iter()
+            .map(|(name, address)| (*name, *address as usize))
+            .collect();
+        Self {
+            variables,
+            count: parameters.len(),
+            functions,
+            symbols,
+            _function_manager: PhantomData,
+            _type: PhantomData,
+        }
+    }
+
+    fn resolve(&mut self, expr: Expr) -> Result<Node<T>, TranslatorError> {
+        Ok(match expr {
+            Expr::VariableRead { identifier, .. } => Node::Read(self.variable(identifier)),
+            Expr::Const { value } => Node::Const(T::from_f64(value)),
+            Expr::Integer { value } => Node::Const(T::from_f64(value as f64)),
+            Expr::Imaginary { span, .. } => {
+                return Err(TranslatorError::UnsupportedImaginary { span })
+            }
+            Expr::Chain { side, ret } => {
+                let side = self.resolve(*side)?;
+                Node::Chain(Box::new(side), Box::new(self.resolve(*ret)?))
+            }
+            Expr::Call {
+                identifier,
+                args,
+                span,
+            } => {
+                let function = self.function(&identifier, span)?;
+                if args.len() != function.arity {
+                    return Err(TranslatorError::ArityMismatch {
+                        name: identifier,
+                        expected: function.arity,
+                        found: args.len(),
+                        span,
+                    });
+                }
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.resolve(arg))
+                    .collect::<Result<Vec<Node<T>>, TranslatorError>>()?;
+                Node::Call(function, args)
+            }
+            Expr::Add { lhs, rhs } => self.binary(Operation::Add, *lhs, *rhs)?,
+            Expr::Sub { lhs, rhs } => self.binary(Operation::Sub, *lhs, *rhs)?,
+            Expr::Mul { lhs, rhs } => self.binary(Operation::Mul, *lhs, *rhs)?,
+            Expr::Div { lhs, rhs } => self.binary(Operation::Div, *lhs, *rhs)?,
+            Expr::Mod { lhs, rhs } => self.binary(Operation::Mod, *lhs, *rhs)?,
+            Expr::Exp { lhs, rhs } => {
+                // Provided by `AnitaType::inbuilt_functions` like for the JIT.
+                let pow = self.functions["inbuilt_pow"];
+                let lhs = self.resolve(*lhs)?;
+                Node::Call(pow, vec![lhs, self.resolve(*rhs)?])
+            }
+            Expr::Neg { value } => Node::Neg(Box::new(self.resolve(*value)?)),
+            Expr::Assign {
+                identifier, value, ..
+            } => {
+                let value = self.resolve(*value)?;
+                Node::Assign(self.variable(identifier), Box::new(value))
+            }
+            Expr::Eq { lhs, rhs } => self.binary(Operation::Eq, *lhs, *rhs)?,
+            Expr::Neq { lhs, rhs } => self.binary(Operation::Neq, *lhs, *rhs)?,
+            Expr::Gt { lhs, rhs } => self.binary(Operation::Gt, *lhs, *rhs)?,
+            Expr::Lt { lhs, rhs } => self.binary(Operation::Lt, *lhs, *rhs)?,
+            Expr::Geq { lhs, rhs } => self.binary(Operation::Geq, *lhs, *rhs)?,
+            Expr::Leq { lhs, rhs } => self.binary(Operation::Leq, *lhs, *rhs)?,
+            Expr::And { lhs, rhs } => self.binary(Operation::And, *lhs, *rhs)?,
+            Expr::Or { lhs, rhs } => self.binary(Operation::Or, *lhs, *rhs)?,
+            Expr::Not { value } => Node::Not(Box::new(self.resolve(*value)?)),
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.resolve(*condition)?;
+                let then = self.resolve(*then)?;
+                let otherwise = self.resolve(*otherwise)?;
+                Node::If(Box::new(condition), Box::new(then), Box::new(otherwise))
+            }
+            Expr::Cast { value, to, span } => match T::value_type() == Some(to) {
+                true => self.resolve(*value)?,
+                false => return Err(TranslatorError::UnsupportedCast { to, span }),
+            },
+            Expr::Swizzle { span, .. } => return Err(TranslatorError::UnsupportedSwizzle { span }),
+            Expr::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                let start = self.resolve(*start)?;
+                let end = self.resolve(*end)?;
+                Node::Loop {
+                    kind,
+                    variable: self.variable(variable),
+                    start: Box::new(start),
+                    end: Box::new(end),
+                    body: Box::new(self.resolve(*body)?),
+                }
+            }
+            Expr::Function { .. } => unreachable!("definitions are inlined before resolution"),
+        })
+    }
+
+    fn binary(
+        &mut self,
+        operation: Operation,
+        lhs: Expr,
+        rhs: Expr,
+    ) -> Result<Node<T>, TranslatorError> {
+        let lhs = self.resolve(lhs)?;
+        let rhs = self.resolve(rhs)?;
+        Ok(Node::Binary(operation, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn variable(&mut self, identifier: String) -> usize {
+        *self.variables.entry(identifier).or_insert_with(|| {
+            self.count += 1;
+            self.count - 1
+        })
+    }
+
+    /// Looks up a function of the [`FunctionManager`] and checks its signature like [`JIT`] does.
+    fn function(&mut self, identifier: &str, span: Span) -> Result<Function, TranslatorError> {
+        if let Some(function) = self.functions.get(identifier) {
+            return Ok(*function);
+        }
+        let not_found = || TranslatorError::FunctionNotFound {
+            name: identifier.to_owned(),
+            span,
+        };
+        // Only the types of the signature are compared, so any calling convention works.
+        let signature =
+            F::function_signature(identifier, CallConv::SystemV).ok_or_else(not_found)?;
+        let arity = T::function_arity(&signature);
+        let expected = T::function_signature(arity, signature.call_conv, pointer_type());
+        if signature != expected {
+            return Err(TranslatorError::SignatureMismatch {
+                name: identifier.to_owned(),
+                expected: Box::new(expected),
+                found: Box::new(signature),
+                span,
+            });
+        }
+        if arity > MAX_ARITY {
+            return Err(TranslatorError::UnsupportedArity {
+                name: identifier.to_owned(),
+                arity,
+                span,
+            });
+        }
+        let address = *self.symbols.get(identifier).ok_or_else(not_found)?;
+        let function = Function { address, arity };
+        self.functions.insert(identifier.to_owned(), function);
+        Ok(function)
+    }
+}
@@ -1,8 +1,13 @@
-use std::{mem::ManuallyDrop, ops::Deref};
+use std::{
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::Deref,
+    slice,
+    sync::Arc,
+};
 
 use cranelift_jit::JITModule;
 
-struct FrozenJITModule {
+pub(super) struct FrozenJITModule {
     _module: ManuallyDrop<Box<JITModule>>,
 }
 
@@ -29,14 +34,23 @@ impl Drop for FrozenJITModule {
 
 pub struct CompiledFunction<F: Send + Sync> {
     function_pointer: F,
-    _memory_region: FrozenJITModule,
+    /// `None` for functions evaluated by the [`Interpreter`](crate::interpreter::Interpreter), which have no compiled code.
+    _memory_region: Option<FrozenJITModule>,
 }
 
 impl<F: Send + Sync> CompiledFunction<F> {
     pub fn new(module: Box<JITModule>, function_pointer: F) -> CompiledFunction<F> {
         CompiledFunction {
             function_pointer,
-            _memory_region: module.into(),
+            _memory_region: Some(module.into()),
+        }
+    }
+
+    /// Wraps a function that evaluates the expression without compiled code, e.g. with the [`Interpreter`](crate::interpreter::Interpreter).
+    pub fn interpreted(function: F) -> CompiledFunction<F> {
+        CompiledFunction {
+            function_pointer: function,
+            _memory_region: None,
         }
     }
 }
@@ -48,3 +62,180 @@ impl<F: Send + Sync> Deref for CompiledFunction<F> {
         &self.function_pointer
     }
 }
+
+/// Error returned when a compiled function is called with arguments that do not match its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallError {
+    /// The number of arguments differs from the number of parameters the expression was compiled with.
+    ArgumentCountMismatch { expected: usize, found: usize },
+    /// An argument slice is shorter than the output slice.
+    LengthMismatch { expected: usize, found: usize },
+}
+
+type BatchFunctionPointer<T> = extern "C" fn(*const *const T, *mut T, usize);
+
+/// Reads the arguments from the first pointer and writes the result to the second.
+pub(super) type DynamicFunctionPointer<T> = extern "C" fn(*const T, *mut T);
+
+/// Evaluates an expression for the given arguments without compiled code.
+pub(crate) type InterpretedFunction<T> = Arc<dyn Fn(&[T]) -> T + Send + Sync>;
+
+/// An expression compiled to evaluate whole slices of parameters at once.
+///
+/// Created by the [`compile_batch_expression!`](crate::compile_batch_expression) macro.
+pub struct CompiledBatchFunction<T> {
+    function_pointer: BatchFunctionPointer<T>,
+    parameter_count: usize,
+    _memory_region: FrozenJITModule,
+}
+
+impl<T> CompiledBatchFunction<T> {
+    pub fn new(
+        module: Box<JITModule>,
+        function_pointer: BatchFunctionPointer<T>,
+        parameter_count: usize,
+    ) -> CompiledBatchFunction<T> {
+        CompiledBatchFunction {
+            function_pointer,
+            parameter_count,
+            _memory_region: module.into(),
+        }
+    }
+
+    /// Evaluates the expression for every index of `output`.
+    ///
+    /// `parameters` holds one slice per parameter in the order they were declared in.
+    /// Every slice must contain at least `output.len()` elements.
+    pub fn call(&self, parameters: &[&[T]], output: &mut [T]) -> Result<(), CallError> {
+        if parameters.len() != self.parameter_count {
+            return Err(CallError::ArgumentCountMismatch {
+                expected: self.parameter_count,
+                found: parameters.len(),
+            });
+        }
+        if let Some(parameter) = parameters.iter().find(|p| p.len() < output.len()) {
+            return Err(CallError::LengthMismatch {
+                expected: output.len(),
+                found: parameter.len(),
+            });
+        }
+        let base_pointers: Vec<*const T> = parameters.iter().map(|p| p.as_ptr()).collect();
+        (self.function_pointer)(base_pointers.as_ptr(), output.as_mut_ptr(), output.len());
+        Ok(())
+    }
+}
+
+/// An expression compiled for a list of parameters that is only known at runtime.
+///
+/// Created by [`JIT::compile_dynamic`](super::JIT::compile_dynamic) or taken from a [`CompiledModule`](super::session::CompiledModule).
+/// Functions taken from the same module share its memory region, which is freed once the last of them is dropped.
+/// [`Interpreter::compile_dynamic`](crate::interpreter::Interpreter::compile_dynamic) creates functions with the same interface that evaluate the expression without compiled code.
+pub struct DynamicFunction<T> {
+    evaluator: Evaluator<T>,
+    parameters: Arc<[String]>,
+}
+
+enum Evaluator<T> {
+    Compiled {
+        function_pointer: DynamicFunctionPointer<T>,
+        _memory_region: Arc<FrozenJITModule>,
+    },
+    Interpreted(InterpretedFunction<T>),
+}
+
+impl<T> DynamicFunction<T> {
+    pub fn new(
+        module: Box<JITModule>,
+        function_pointer: DynamicFunctionPointer<T>,
+        parameters: &[&str],
+    ) -> DynamicFunction<T> {
+        Self::shared(
+            Arc::new(module.into()),
+            function_pointer,
+            parameters.iter().map(|p| p.to_string()).collect(),
+        )
+    }
+
+    pub(super) fn shared(
+        memory_region: Arc<FrozenJITModule>,
+        function_pointer: DynamicFunctionPointer<T>,
+        parameters: Arc<[String]>,
+    ) -> DynamicFunction<T> {
+        DynamicFunction {
+            evaluator: Evaluator::Compiled {
+                function_pointer,
+                _memory_region: memory_region,
+            },
+            parameters,
+        }
+    }
+
+    pub(crate) fn interpreted(
+        function: InterpretedFunction<T>,
+        parameters: &[&str],
+    ) -> DynamicFunction<T> {
+        DynamicFunction {
+            evaluator: Evaluator::Interpreted(function),
+            parameters: parameters.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    /// The names of the parameters in the order the arguments are expected in.
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    /// Evaluates the expression with one argument per parameter.
+    pub fn call(&self, arguments: &[T]) -> Result<T, CallError> {
+        if arguments.len() != self.parameters.len() {
+            return Err(CallError::ArgumentCountMismatch {
+                expected: self.parameters.len(),
+                found: arguments.len(),
+            });
+        }
+        // SAFETY: the amount of arguments matches the parameters.
+        Ok(unsafe { self.call_unchecked(arguments.as_ptr()) })
+    }
+
+    /// Evaluates the expression with the arguments read from `arguments`.
+    ///
+    /// # Safety
+    ///
+    /// `arguments` must point to at least as many initialized values as there are [`DynamicFunction::parameters`].
+    pub unsafe fn call_unchecked(&self, arguments: *const T) -> T {
+        match &self.evaluator {
+            Evaluator::Compiled {
+                function_pointer, ..
+            } => {
+                let mut result = MaybeUninit::uninit();
+                function_pointer(arguments, result.as_mut_ptr());
+                // SAFETY: the compiled function always writes its result.
+                unsafe { result.assume_init() }
+            }
+            Evaluator::Interpreted(function) => {
+                // SAFETY: the caller guarantees one initialized argument per parameter.
+                let arguments = unsafe { slice::from_raw_parts(arguments, self.parameters.len()) };
+                function(arguments)
+            }
+        }
+    }
+}
+
+impl<T> Clone for DynamicFunction<T> {
+    fn clone(&self) -> Self {
+        let evaluator = match &self.evaluator {
+            Evaluator::Compiled {
+                function_pointer,
+                _memory_region,
+            } => Evaluator::Compiled {
+                function_pointer: *function_pointer,
+                _memory_region: _memory_region.clone(),
+            },
+            Evaluator::Interpreted(function) => Evaluator::Interpreted(function.clone()),
+        };
+        Self {
+            evaluator,
+            parameters: self.parameters.clone(),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+};
+
+use itertools::Itertools;
+
+use super::{Expr, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionError {
+    /// A function calls itself, which can not be inlined.
+    Recursion { name: String, span: Span },
+    /// A definition is not followed by the rest of a chain, e.g. `f(x) = x` on its own or as an operand.
+    MisplacedDefinition { name: String, span: Span },
+    /// A definition lists the same parameter more than once.
+    DuplicateParameter { name: String, span: Span },
+    /// A defined function is called with a different amount of arguments than it has parameters.
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+}
+
+impl DefinitionError {
+    /// Location of the error in the expression.
+    pub fn span(&self) -> Span {
+        match self {
+            DefinitionError::Recursion { span, .. }
+            | DefinitionError::MisplacedDefinition { span, .. }
+            | DefinitionError::DuplicateParameter { span, .. }
+            | DefinitionError::ArityMismatch { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::Recursion { name, .. } => {
+                write!(f, "function `{name}` can not call itself")
+            }
+            DefinitionError::MisplacedDefinition { name, .. } => {
+                write!(
+                    f,
+                    "the definition of `{name}` has to be followed by `;` and the expression using it"
+                )
+            }
+            DefinitionError::DuplicateParameter { name, .. } => {
+                write!(f, "the parameter `{name}` is declared more than once")
+            }
+            DefinitionError::ArityMismatch {
+                name,
+                expected,
+                found,
+                ..
+            } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(
+                    f,
+                    "function `{name}` takes {expected} argument{plural} but {found} {verb} given"
+                )
+            }
+        }
+    }
+}
+
+impl Error for DefinitionError {}
+
+/// Replaces every call of a function defined with `identifier(parameters) = body` by its body and removes the definitions.
+///
+/// A definition is visible in the rest of the chain it is the side of and shadows the functions of the
+/// [`FunctionManager`](crate::function_manager::FunctionManager) as well as earlier definitions with the same name.
+/// Calls to the function inside of its own body are rejected, there is no recursion.
+///
+/// The variables of an inlined body are renamed to `{function}_{variable}_{n}` with a number `n` per call,
+/// so they never clash with the variables around the call. Arguments are assigned to the renamed parameters in order.
+/// Arguments without side effects are substituted for parameters the body does not assign instead,
+/// as long as no argument of the call assigns a variable, which keeps calls like `sq(x + 1)` differentiable.
+///
+/// The body of a definition only sees its parameters, see [`Expr::uninitialized_reads`], which has to be checked beforehand.
+pub fn inline(expr: Expr) -> Result<Expr, DefinitionError> {
+    let mut inliner = Inliner {
+        identifiers: expr.variables_all().into_iter().collect(),
+        scope: HashMap::new(),
+        calls: 0,
+    };
+    inliner.inline(expr)
+}
+
+/// A defined function with its calls already inlined into the body.
+struct Definition {
+    parameters: Vec<String>,
+    body: Expr,
+}
+
+struct Inliner {
+    /// Variables that renamed variables may not be named like.
+    identifiers: HashSet<String>,
+    /// Visible definitions, `None` for a function whose body is being inlined.
+    scope: HashMap<String, Option<Definition>>,
+    /// Amount of inlined calls so far.
+    calls: usize,
+}
+
+impl Inliner {
+    fn inline(&mut self, expr: Expr) -> Result<Expr, DefinitionError> {
+        match expr {
+            Expr::Chain { side, ret } => match *side {
+                Expr::Function {
+                    identifier,
+                    parameters,
+                    body,
+                    span,
+                } => {
+                    if let Some(name) = parameters
+                        .iter()
+                        .enumerate()
+                        .find_map(|(i, name)| parameters[..i].contains(name).then_some(name))
+                    {
+                        return Err(DefinitionError::DuplicateParameter {
+                            name: name.clone(),
+                            span,
+                        });
+                    }
+                    let shadowed = self.scope.insert(identifier.clone(), None);
+                    let body = self.inline(*body)?;
+                    self.scope
+                        .insert(identifier.clone(), Some(Definition { parameters, body }));
+                    let ret = self.inline(*ret)?;
+                    match shadowed {
+                        Some(shadowed) => self.scope.insert(identifier, shadowed),
+                        None => self.scope.remove(&identifier),
+                    };
+                    Ok(ret)
+                }
+                side => Ok(Expr::Chain {
+                    side: Box::new(self.inline(side)?),
+                    ret: Box::new(self.inline(*ret)?),
+                }),
+            },
+            Expr::Function {
+                identifier, span, ..
+            } => Err(DefinitionError::MisplacedDefinition {
+                name: identifier,
+                span,
+            }),
+            Expr::Call {
+                identifier,
+                args,
+                span,
+            } => {
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.inline(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match self.scope.get(&identifier) {
+                    None => Ok(Expr::Call {
+                        identifier,
+                        args,
+                        span,
+                    }),
+                    Some(None) => Err(DefinitionError::Recursion {
+                        name: identifier,
+                        span,
+                    }),
+                    Some(Some(definition)) if definition.parameters.len() != args.len() => {
+                        Err(DefinitionError::ArityMismatch {
+                            name: identifier,
+                            expected: definition.parameters.len(),
+                            found: args.len(),
+                            span,
+                        })
+                    }
+                    Some(Some(_)) => Ok(self.call(&identifier, args, span)),
+                }
+            }
+            expr => {
+                let mut error = None;
+                let expr = expr.map_children(|child| match self.inline(child) {
+                    Ok(child) => child,
+                    Err(e) => {
+                        error.get_or_insert(e);
+                        // Discarded with the error.
+                        Expr::Const { value: 0.0 }
+                    }
+                });
+                match error {
+                    Some(error) => Err(error),
+                    None => Ok(expr),
+                }
+            }
+        }
+    }
+
+    /// Returns the body of the defined function `identifier` evaluated for `args`.
+    fn call(&mut self, identifier: &str, args: Vec<Expr>, span: Span) -> Expr {
+        let Some(Some(Definition { parameters, body })) = self.scope.get(identifier) else {
+            unreachable!("only called for visible definitions");
+        };
+        let (parameters, body) = (parameters.clone(), body.clone());
+        self.calls += 1;
+
+        let mut names = HashMap::new();
+        for variable in parameters
+            .iter()
+            .cloned()
+            .chain(body.variables_all())
+            .unique()
+        {
+            let mut name = format!("{identifier}_{variable}_{}", self.calls);
+            while self.identifiers.contains(&name) {
+                name.push('_');
+            }
+            self.identifiers.insert(name.clone());
+            names.insert(variable, name);
+        }
+
+        // An argument assigning a variable could change what a substituted argument reads.
+        let substitute = args.iter().all(|arg| arg.variables_write().is_empty());
+        let writes = body.variables_write();
+        let mut substitutions = HashMap::new();
+        let mut assignments = Vec::new();
+        for (parameter, arg) in parameters.iter().zip(args) {
+            if substitute && arg.is_pure() && !writes.contains(parameter) {
+                substitutions.insert(parameter.clone(), arg);
+            } else {
+                assignments.push(Expr::Assign {
+                    identifier: names[parameter].clone(),
+                    value: Box::new(arg),
+                    span,
+                });
+            }
+        }
+
+        let body = rename(body, &names, &substitutions);
+        assignments
+            .into_iter()
+            .rev()
+            .fold(body, |ret, assignment| Expr::Chain {
+                side: Box::new(assignment),
+                ret: Box::new(ret),
+            })
+    }
+}
+
+/// Renames every variable of `expr` according to `names` and replaces the reads of the `substitutions`.
+fn rename(
+    expr: Expr,
+    names: &HashMap<String, String>,
+    substitutions: &HashMap<String, Expr>,
+) -> Expr {
+    if let Expr::VariableRead { identifier, span } = expr {
+        return match substitutions.get(&identifier) {
+            Some(value) => value.clone(),
+            None => Expr::VariableRead {
+                identifier: names[&identifier].clone(),
+                span,
+            },
+        };
+    }
+    let mut expr = expr.map_children(|child| rename(child, names, substitutions));
+    if let Expr::Assign { identifier, .. }
+    | Expr::Loop {
+        variable: identifier,
+        ..
+    } = &mut expr
+    {
+        *identifier = names[identifier.as_str()].clone();
+    }
+    expr
+}
@@ -0,0 +1,525 @@
+use std::{collections::HashMap, error::Error, fmt, marker::PhantomData};
+
+use itertools::Itertools;
+
+use super::{
+    definitions::{inline, DefinitionError},
+    optimizer::optimize,
+    parser, Expr, LoopKind, Span,
+};
+use crate::{function_manager::FunctionManager, jit::types::AnitaType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DifferentiationError {
+    /// The [`FunctionManager`] has no derivative for a function whose arguments depend on the variable.
+    MissingDerivative { name: String, span: Span },
+    /// A derivative of the [`FunctionManager`] can not be parsed or does not match the function.
+    InvalidDerivative { name: String, span: Span },
+    /// A function required by a differentiation rule is missing, e.g. `ln` for `a ^ b` with a variable exponent.
+    MissingFunction { name: String },
+    /// Assignments can only be differentiated as parts of a chain or branches of a conditional,
+    /// not inside of operands, arguments or conditions.
+    UnsupportedAssignment { name: String, span: Span },
+    /// A function defined in the expression can not be inlined, see [`inline`].
+    InvalidDefinition(DefinitionError),
+}
+
+impl DifferentiationError {
+    /// Location of the error in the expression, if it was caused by a part of it.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            DifferentiationError::MissingDerivative { span, .. }
+            | DifferentiationError::InvalidDerivative { span, .. }
+            | DifferentiationError::UnsupportedAssignment { span, .. } => Some(*span),
+            DifferentiationError::InvalidDefinition(error) => Some(error.span()),
+            DifferentiationError::MissingFunction { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for DifferentiationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DifferentiationError::MissingDerivative { name, .. } => {
+                write!(f, "function `{name}` has no derivative")
+            }
+            DifferentiationError::InvalidDerivative { name, .. } => {
+                write!(f, "the derivative of function `{name}` is invalid")
+            }
+            DifferentiationError::MissingFunction { name } => {
+                write!(f, "differentiation requires the function `{name}`")
+            }
+            DifferentiationError::UnsupportedAssignment { name, .. } => {
+                write!(
+                    f,
+                    "can not differentiate the assignment to `{name}` inside of an operand"
+                )
+            }
+            DifferentiationError::InvalidDefinition(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for DifferentiationError {}
+
+/// Returns the derivative of `expr` with respect to `variable`.
+///
+/// Functions are differentiated with the chain rule and the partial derivatives of [`FunctionManager::function_derivatives`].
+/// Every assigned variable `y` gets a companion variable (`dy_dx` for the variable `x`) that holds its derivative,
+/// so chains like `y = x ^ 2; y * y` are supported as long as assignments are not nested inside of operands.
+/// Comparisons and logical operators are piecewise constant and have the derivative `0`.
+/// Loops are differentiated per iteration, the bounds only shift the loop variable and do not contribute otherwise.
+/// The derivative of a product needs a second loop inside of every iteration.
+/// Functions defined in the expression are inlined first, so their calls are differentiated like their bodies.
+///
+/// The result is simplified with [`optimize`] for `T` and, formatted with [`Display`](fmt::Display),
+/// compiles like any other expression with the same parameters and `F`.
+pub fn differentiate<T: AnitaType, F: FunctionManager>(
+    expr: &Expr,
+    variable: &str,
+) -> Result<Expr, DifferentiationError> {
+    let expr = &inline(expr.clone()).map_err(DifferentiationError::InvalidDefinition)?;
+    let identifiers = expr.variables_all();
+    let mut derivatives = HashMap::new();
+    for identifier in expr.variables_write().into_iter().unique() {
+        let mut name = format!("d{identifier}_d{variable}");
+        while identifiers.contains(&name) || derivatives.values().contains(&name) {
+            name.push('_');
+        }
+        derivatives.insert(identifier, name);
+    }
+    let differentiator = Differentiator::<F> {
+        variable,
+        identifiers,
+        derivatives,
+        _function_manager: PhantomData,
+    };
+    let mut derivative = differentiator.derivative(expr)?;
+
+    // Parameters that are reassigned need the derivative of their initial value before the first assignment.
+    let parameters = expr.uninitialized_reads(&[]);
+    for (identifier, _) in parameters.iter().unique_by(|(identifier, _)| identifier) {
+        if let Some(name) = differentiator.derivatives.get(identifier) {
+            let initial = constant((identifier == variable) as u8 as f64);
+            derivative = chain(assign(name, initial, Span::default()), derivative);
+        }
+    }
+    Ok(optimize::<T>(derivative))
+}
+
+struct Differentiator<'a, F: FunctionManager> {
+    variable: &'a str,
+    /// Variables of the differentiated expression, which may not be used as loop variables of the derivative.
+    identifiers: Vec<String>,
+    /// Names of the variables that hold the derivatives of assigned variables.
+    derivatives: HashMap<String, String>,
+    _function_manager: PhantomData<F>,
+}
+
+impl<F: FunctionManager> Differentiator<'_, F> {
+    /// Returns an expression that evaluates to the derivative of `expr` and performs its assignments.
+    fn derivative(&self, expr: &Expr) -> Result<Expr, DifferentiationError> {
+        Ok(match expr {
+            Expr::VariableRead { identifier, span } => match self.derivatives.get(identifier) {
+                Some(name) => Expr::VariableRead {
+                    identifier: name.clone(),
+                    span: *span,
+                },
+                None => constant((identifier == self.variable) as u8 as f64),
+            },
+            Expr::Const { .. } | Expr::Integer { .. } | Expr::Imaginary { .. } => constant(0.0),
+            Expr::Chain { side, ret } => match self.statement(side)? {
+                Some(side) => chain(side, self.derivative(ret)?),
+                None => self.derivative(ret)?,
+            },
+            Expr::Assign {
+                identifier, span, ..
+            } => {
+                let assignments = self.assignment(expr)?;
+                let read = Expr::VariableRead {
+                    identifier: self.derivatives[identifier].clone(),
+                    span: *span,
+                };
+                chain(assignments, read)
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                Self::reject_assignments(condition)?;
+                let (then, otherwise) = (self.derivative(then)?, self.derivative(otherwise)?);
+                match then.same_structure(&otherwise) && condition.is_pure() {
+                    true => then,
+                    false => Expr::If {
+                        condition: condition.clone(),
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    },
+                }
+            }
+            Expr::Call {
+                identifier,
+                args,
+                span,
+            } => self.call(identifier, args, *span)?,
+            Expr::Add { lhs, rhs } => add(self.operand(lhs)?, self.operand(rhs)?),
+            Expr::Sub { lhs, rhs } => sub(self.operand(lhs)?, self.operand(rhs)?),
+            Expr::Mul { lhs, rhs } => {
+                let (dl, dr) = (self.operand(lhs)?, self.operand(rhs)?);
+                add(mul(dl, *rhs.clone()), mul(*lhs.clone(), dr))
+            }
+            Expr::Div { lhs, rhs } => {
+                let (dl, dr) = (self.operand(lhs)?, self.operand(rhs)?);
+                let square = Expr::Exp {
+                    lhs: rhs.clone(),
+                    rhs: Box::new(constant(2.0)),
+                };
+                sub(div(dl, *rhs.clone()), div(mul(*lhs.clone(), dr), square))
+            }
+            Expr::Mod { lhs, rhs } => {
+                // a % b = a - trunc(a / b) * b, where trunc(a / b) = (a - a % b) / b is piecewise constant.
+                let (dl, dr) = (self.operand(lhs)?, self.operand(rhs)?);
+                let quotient = div(sub(*lhs.clone(), expr.clone()), *rhs.clone());
+                sub(dl, mul(quotient, dr))
+            }
+            Expr::Exp { lhs, rhs } => {
+                let (dl, dr) = (self.operand(lhs)?, self.operand(rhs)?);
+                if is_zero(&dr) {
+                    let power = Expr::Exp {
+                        lhs: lhs.clone(),
+                        rhs: Box::new(sub(*rhs.clone(), constant(1.0))),
+                    };
+                    return Ok(mul(mul(*rhs.clone(), power), dl));
+                }
+                if !F::function_symbols().iter().any(|(name, _)| *name == "ln") {
+                    return Err(DifferentiationError::MissingFunction {
+                        name: "ln".to_owned(),
+                    });
+                }
+                let ln = Expr::Call {
+                    identifier: "ln".to_owned(),
+                    args: vec![*lhs.clone()],
+                    span: Span::default(),
+                };
+                let factor = add(mul(dr, ln), div(mul(*rhs.clone(), dl), *lhs.clone()));
+                mul(expr.clone(), factor)
+            }
+            Expr::Neg { value } => neg(self.operand(value)?),
+            Expr::Eq { lhs, rhs }
+            | Expr::Neq { lhs, rhs }
+            | Expr::Gt { lhs, rhs }
+            | Expr::Lt { lhs, rhs }
+            | Expr::Geq { lhs, rhs }
+            | Expr::Leq { lhs, rhs }
+            | Expr::And { lhs, rhs }
+            | Expr::Or { lhs, rhs } => {
+                Self::reject_assignments(lhs)?;
+                Self::reject_assignments(rhs)?;
+                constant(0.0)
+            }
+            Expr::Not { value } => {
+                Self::reject_assignments(value)?;
+                constant(0.0)
+            }
+            // Conversions between floats keep the derivative, integers and booleans are piecewise constant.
+            Expr::Cast { value, to, .. } => match to.is_float() {
+                true => self.operand(value)?,
+                false => {
+                    Self::reject_assignments(value)?;
+                    constant(0.0)
+                }
+            },
+            // Swizzles are linear, so the derivative has the same components.
+            Expr::Swizzle {
+                value,
+                components,
+                span,
+            } => Expr::Swizzle {
+                value: Box::new(self.operand(value)?),
+                components: components.clone(),
+                span: *span,
+            },
+            Expr::Loop { .. } => self.iterations(expr)?,
+            Expr::Function { .. } => unreachable!("definitions are inlined before differentiation"),
+        })
+    }
+
+    /// Differentiates a loop, whose variable has the derivative of `start` in every iteration.
+    fn iterations(&self, expr: &Expr) -> Result<Expr, DifferentiationError> {
+        let Expr::Loop {
+            kind,
+            variable,
+            start,
+            end,
+            body,
+            span,
+        } = expr
+        else {
+            unreachable!("only called for loops");
+        };
+        Self::reject_assignments(end)?;
+        let shift = assign(&self.derivatives[variable], self.operand(start)?, *span);
+        let body = match kind {
+            LoopKind::For | LoopKind::Sum => self.derivative(body)?,
+            // The product rule, the sum over every factor's derivative times the other factors.
+            LoopKind::Product => {
+                let derivative = self.operand(body)?;
+                let mut other = format!("{variable}_");
+                while self.identifiers.contains(&other)
+                    || self.derivatives.values().contains(&other)
+                {
+                    other.push('_');
+                }
+                let read = |identifier: &str| Expr::VariableRead {
+                    identifier: identifier.to_owned(),
+                    span: *span,
+                };
+                let factor = Expr::If {
+                    condition: Box::new(Expr::Eq {
+                        lhs: Box::new(read(&other)),
+                        rhs: Box::new(read(variable)),
+                    }),
+                    then: Box::new(constant(1.0)),
+                    otherwise: Box::new(substitute(
+                        *body.clone(),
+                        &[variable.as_str()],
+                        &[read(&other)],
+                    )),
+                };
+                let others = Expr::Loop {
+                    kind: LoopKind::Product,
+                    variable: other,
+                    start: start.clone(),
+                    end: end.clone(),
+                    body: Box::new(factor),
+                    span: *span,
+                };
+                mul(derivative, others)
+            }
+        };
+        Ok(Expr::Loop {
+            kind: match kind {
+                LoopKind::For => LoopKind::For,
+                LoopKind::Sum | LoopKind::Product => LoopKind::Sum,
+            },
+            variable: variable.clone(),
+            start: start.clone(),
+            end: end.clone(),
+            body: Box::new(chain(shift, body)),
+            span: *span,
+        })
+    }
+
+    /// Differentiates an expression whose value is not used, `None` if it has no effect on the derivative.
+    fn statement(&self, expr: &Expr) -> Result<Option<Expr>, DifferentiationError> {
+        if expr.variables_write().is_empty() {
+            return Ok(None);
+        }
+        match expr {
+            Expr::Assign { .. } => self.assignment(expr).map(Some),
+            Expr::Chain { side, ret } => match (self.statement(side)?, self.statement(ret)?) {
+                (Some(side), Some(ret)) => Ok(Some(chain(side, ret))),
+                (side, ret) => Ok(side.or(ret)),
+            },
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                Self::reject_assignments(condition)?;
+                let then = self.statement(then)?.unwrap_or(constant(0.0));
+                let otherwise = self.statement(otherwise)?.unwrap_or(constant(0.0));
+                Ok(Some(Expr::If {
+                    condition: condition.clone(),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                }))
+            }
+            _ => self.derivative(expr).map(Some),
+        }
+    }
+
+    /// Assigns the derivative of an assignment `y = value` to its companion variable, followed by the assignment itself.
+    fn assignment(&self, expr: &Expr) -> Result<Expr, DifferentiationError> {
+        let Expr::Assign {
+            identifier,
+            value,
+            span,
+        } = expr
+        else {
+            unreachable!("only called for assignments");
+        };
+        let derivative = self.operand(value)?;
+        let name = &self.derivatives[identifier];
+        Ok(chain(assign(name, derivative, *span), expr.clone()))
+    }
+
+    /// Differentiates an operand, which is evaluated again by the derivative and may not assign variables.
+    fn operand(&self, expr: &Expr) -> Result<Expr, DifferentiationError> {
+        Self::reject_assignments(expr)?;
+        self.derivative(expr)
+    }
+
+    fn call(
+        &self,
+        identifier: &str,
+        args: &[Expr],
+        span: Span,
+    ) -> Result<Expr, DifferentiationError> {
+        let derivatives = args
+            .iter()
+            .map(|arg| self.operand(arg))
+            .collect::<Result<Vec<Expr>, DifferentiationError>>()?;
+        if derivatives.iter().all(is_zero) {
+            return Ok(constant(0.0));
+        }
+
+        let invalid = || DifferentiationError::InvalidDerivative {
+            name: identifier.to_owned(),
+            span,
+        };
+        let Some(rule) = F::function_derivatives(identifier) else {
+            return Err(DifferentiationError::MissingDerivative {
+                name: identifier.to_owned(),
+                span,
+            });
+        };
+        if rule.parameters.len() != args.len() || rule.partials.len() != args.len() {
+            return Err(invalid());
+        }
+
+        let mut derivative = constant(0.0);
+        for (partial, inner) in rule.partials.iter().zip(derivatives) {
+            if is_zero(&inner) {
+                continue;
+            }
+            let partial = parser::expression(partial).map_err(|_| invalid())?;
+            let unknown = partial
+                .variables_all()
+                .iter()
+                .any(|variable| !rule.parameters.contains(&variable.as_str()));
+            if unknown || !partial.variables_write().is_empty() {
+                return Err(invalid());
+            }
+            let partial = substitute(partial, rule.parameters, args);
+            derivative = add(derivative, mul(partial, inner));
+        }
+        Ok(derivative)
+    }
+
+    fn reject_assignments(expr: &Expr) -> Result<(), DifferentiationError> {
+        if let Expr::Assign {
+            identifier, span, ..
+        } = expr
+        {
+            return Err(DifferentiationError::UnsupportedAssignment {
+                name: identifier.clone(),
+                span: *span,
+            });
+        }
+        expr.children()
+            .into_iter()
+            .try_for_each(Self::reject_assignments)
+    }
+}
+
+/// Replaces the reads of `parameters` with the corresponding `args`.
+fn substitute(expr: Expr, parameters: &[&str], args: &[Expr]) -> Expr {
+    if let Expr::VariableRead { identifier, .. } = &expr {
+        if let Some(index) = parameters.iter().position(|p| p == identifier) {
+            return args[index].clone();
+        }
+    }
+    expr.map_children(|child| substitute(child, parameters, args))
+}
+
+fn constant(value: f64) -> Expr {
+    Expr::Const { value }
+}
+
+fn is_zero(expr: &Expr) -> bool {
+    matches!(expr, Expr::Const { value } if *value == 0.0)
+}
+
+fn is_one(expr: &Expr) -> bool {
+    matches!(expr, Expr::Const { value } if *value == 1.0)
+}
+
+/// Chains `side` and `ret`, nesting to the right like the parser does.
+fn chain(side: Expr, ret: Expr) -> Expr {
+    match side {
+        Expr::Chain { side, ret: middle } => chain(*side, chain(*middle, ret)),
+        side => Expr::Chain {
+            side: Box::new(side),
+            ret: Box::new(ret),
+        },
+    }
+}
+
+fn assign(identifier: &str, value: Expr, span: Span) -> Expr {
+    Expr::Assign {
+        identifier: identifier.to_owned(),
+        value: Box::new(value),
+        span,
+    }
+}
+
+// The constructors below drop terms that are known to be zero while building the derivative,
+// which keeps the tree small before it is simplified.
+
+fn add(lhs: Expr, rhs: Expr) -> Expr {
+    match (is_zero(&lhs), is_zero(&rhs)) {
+        (true, _) => rhs,
+        (_, true) => lhs,
+        _ => Expr::Add {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+fn sub(lhs: Expr, rhs: Expr) -> Expr {
+    match (is_zero(&lhs), is_zero(&rhs)) {
+        (_, true) => lhs,
+        (true, _) => neg(rhs),
+        _ => Expr::Sub {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+fn mul(lhs: Expr, rhs: Expr) -> Expr {
+    if is_zero(&lhs) || is_zero(&rhs) {
+        return constant(0.0);
+    }
+    match (is_one(&lhs), is_one(&rhs)) {
+        (true, _) => rhs,
+        (_, true) => lhs,
+        _ => Expr::Mul {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+fn div(lhs: Expr, rhs: Expr) -> Expr {
+    match (is_zero(&lhs), is_one(&rhs)) {
+        (true, _) => constant(0.0),
+        (_, true) => lhs,
+        _ => Expr::Div {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+fn neg(value: Expr) -> Expr {
+    match value {
+        Expr::Const { value } => constant(-value),
+        value => Expr::Neg {
+            value: Box::new(value),
+        },
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use super::Span;
+
+/// Renders `message` followed by the lines of `source` that contain `spans`, with the spans underlined by carets.
+///
+/// ```text
+/// error: unknown function `foo`
+///  --> 1:5
+///   |
+/// 1 | x + foo(2)
+///   |     ^^^^^^
+/// ```
+///
+/// Empty spans, e.g. the position of a parse error, are marked with a single caret.
+pub fn render(source: &str, message: &str, spans: &[Span]) -> String {
+    let mut spans: Vec<Span> = spans
+        .iter()
+        .map(|span| {
+            let start = floor_char_boundary(source, span.start);
+            Span::new(start, floor_char_boundary(source, span.end).max(start))
+        })
+        .collect();
+    spans.sort_by_key(|span| span.start);
+
+    let mut rendered = format!("error: {message}");
+    let Some(first) = spans.first() else {
+        return rendered;
+    };
+
+    let lines: Vec<(usize, &str)> = line_starts(source)
+        .zip(source.split('\n'))
+        .map(|(start, line)| (start, line.strip_suffix('\r').unwrap_or(line)))
+        .collect();
+    let line_of = |offset: usize| lines.partition_point(|(start, _)| *start <= offset) - 1;
+
+    let gutter = (line_of(spans[spans.len() - 1].start) + 1)
+        .to_string()
+        .len();
+    let (first_line, first_column) = position(&lines, line_of(first.start), first.start);
+    let _ = write!(
+        rendered,
+        "\n{:gutter$}--> {first_line}:{first_column}\n{:gutter$} |",
+        "", ""
+    );
+
+    let mut spans = spans.into_iter().peekable();
+    while let Some(span) = spans.next() {
+        let line = line_of(span.start);
+        let (start, text) = lines[line];
+        let mut marked = vec![span];
+        while let Some(next) = spans.next_if(|next| line_of(next.start) == line) {
+            marked.push(next);
+        }
+
+        let mut carets = String::new();
+        let mut offset = start;
+        for span in marked {
+            // Tabs are kept so the carets line up with the source line.
+            for character in source[offset.max(start)..span.start.max(offset)].chars() {
+                carets.push(if character == '\t' { '\t' } else { ' ' });
+            }
+            let line_end = start + text.len();
+            let end = span.end.min(line_end);
+            let width = source[span.start.min(end)..end].chars().count();
+            carets.extend(std::iter::repeat_n('^', width.max(1)));
+            // An empty span is marked on the character it points at.
+            let end = match width {
+                0 => ceil_char_boundary(source, (span.start + 1).min(line_end)),
+                _ => end,
+            };
+            offset = offset.max(end);
+        }
+        let _ = write!(
+            rendered,
+            "\n{:>gutter$} | {text}\n{:gutter$} | {carets}",
+            line + 1,
+            ""
+        );
+    }
+    rendered
+}
+
+/// Returns the 1-based line and column (in characters) of `offset`.
+fn position(lines: &[(usize, &str)], line: usize, offset: usize) -> (usize, usize) {
+    let (start, text) = lines[line];
+    let column = text
+        .get(..offset - start)
+        .map_or(text.chars().count(), |prefix| prefix.chars().count());
+    (line + 1, column + 1)
+}
+
+fn line_starts(source: &str) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(0).chain(source.match_indices('\n').map(|(index, _)| index + 1))
+}
+
+fn ceil_char_boundary(source: &str, mut index: usize) -> usize {
+    index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+fn floor_char_boundary(source: &str, mut index: usize) -> usize {
+    index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -1,12 +1,52 @@
-use std::{collections::HashSet, vec};
+use std::{collections::HashSet, fmt, mem, vec};
 
-#[derive(Debug, Clone)]
+use super::types::ValueType;
+
+pub mod definitions;
+pub mod derivative;
+pub mod diagnostic;
+pub mod optimizer;
+
+/// Byte range `start..end` of a part of the parsed expression.
+///
+/// Expressions parsed from different source text differ in their spans, see [`Expr::same_structure`] to compare them without.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span enclosing both spans.
+    pub fn join(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     VariableRead {
         identifier: String,
+        span: Span,
     },
     Const {
-        value: f32,
+        value: f64,
+    },
+    /// An integer literal that an `f64` can not hold exactly, like `18446744073709551615`.
+    ///
+    /// Integer types lower it without rounding, see [`AnitaType::integer_constant`](super::types::AnitaType::integer_constant).
+    Integer {
+        value: i128,
+    },
+    /// `value i`, a multiple of the imaginary unit like `2.5i`, see [`AnitaType::imaginary`](super::types::AnitaType::imaginary).
+    Imaginary {
+        value: f64,
+        /// Covers the literal.
+        span: Span,
     },
     Chain {
         side: Box<Expr>,
@@ -15,6 +55,8 @@ pub enum Expr {
     Call {
         identifier: String,
         args: Vec<Expr>,
+        /// Covers the identifier and the argument list.
+        span: Span,
     },
     Add {
         lhs: Box<Expr>,
@@ -46,6 +88,8 @@ pub enum Expr {
     Assign {
         identifier: String,
         value: Box<Expr>,
+        /// Covers the assigned identifier.
+        span: Span,
     },
     Eq {
         lhs: Box<Expr>,
@@ -82,66 +126,519 @@ pub enum Expr {
     Not {
         value: Box<Expr>,
     },
+    If {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// `value as to`, converts like the `as` operator of Rust.
+    Cast {
+        value: Box<Expr>,
+        to: ValueType,
+        /// Covers the type name.
+        span: Span,
+    },
+    /// `value.xy`, the components of a vector in the given order, each one of `x`, `y`, `z` and `w`.
+    Swizzle {
+        value: Box<Expr>,
+        components: String,
+        /// Covers the components.
+        span: Span,
+    },
+    /// `identifier(parameters) = body`, defines a function for the rest of the chain it is the side of.
+    ///
+    /// Calls are inlined by [`definitions::inline`] before translation, see there.
+    /// The body only sees the parameters, the variables it assigns and the functions defined before.
+    Function {
+        identifier: String,
+        parameters: Vec<String>,
+        body: Box<Expr>,
+        /// Covers the identifier and the parameter list.
+        span: Span,
+    },
+    /// Evaluates `body` with `variable` counting up by `1` from `start` to `end`, see [`LoopKind`].
+    ///
+    /// `start` and `end` are evaluated once before the first iteration.
+    /// `variable` is assigned before every iteration, so it keeps its last value after the loop.
+    Loop {
+        kind: LoopKind,
+        variable: String,
+        start: Box<Expr>,
+        end: Box<Expr>,
+        body: Box<Expr>,
+        /// Covers the loop variable.
+        span: Span,
+    },
 }
 
-pub struct Variables {
-    pub read: Vec<String>,
-    pub write: Vec<String>,
-    pub uninitialized: Vec<String>,
+/// How an [`Expr::Loop`] combines the values of its iterations.
+///
+/// The loops of an evaluation share a budget of iterations, see [`JIT::set_max_iterations`](super::JIT::set_max_iterations).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopKind {
+    /// `for i in start..end { body }`, runs while `i < end` and evaluates to the value of the last iteration, or `0` if there is none.
+    For,
+    /// `sum(i, start, end, body)`, runs while `i <= end` and adds the values of all iterations to `0`.
+    Sum,
+    /// `prod(i, start, end, body)`, runs while `i <= end` and multiplies the values of all iterations with `1`.
+    Product,
 }
 
-impl Variables {
-    pub fn set_defined(&mut self, identifier: &String) {
-        self.uninitialized = self
-            .uninitialized
-            .clone()
-            .into_iter()
-            .filter(|e| e != identifier)
-            .collect();
+impl LoopKind {
+    /// The value of a loop without iterations, which the values of the iterations are combined with.
+    pub fn initial(self) -> f64 {
+        match self {
+            LoopKind::For | LoopKind::Sum => 0.0,
+            LoopKind::Product => 1.0,
+        }
     }
 
-    pub fn initialized_identifiers(mut self) -> Result<Box<[String]>, Box<[String]>> {
-        if !self.uninitialized.is_empty() {
-            return Err(self.uninitialized.into());
-        }
-        let mut identifiers = Vec::new();
-        identifiers.append(&mut self.read);
-        identifiers.append(&mut self.write);
-        let unique_identifiers = identifiers
-            .into_iter()
-            .collect::<HashSet<String>>()
-            .into_iter()
-            .collect();
-        Ok(unique_identifiers)
+    /// Whether the loop also runs for `end` itself.
+    pub fn inclusive(self) -> bool {
+        self != LoopKind::For
     }
 }
 
 impl Expr {
-    pub fn variables(&self) -> Variables {
-        let read = self.variables_read();
-        let write = self.variables_write();
-        let undefined: Vec<String> = self
-            .variables_all()
+    /// Returns `true` if evaluating the expression has no side effects.
+    ///
+    /// Function calls are considered impure, since the functions of a [`FunctionManager`](crate::function_manager::FunctionManager) are opaque.
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Expr::VariableRead { .. }
+            | Expr::Const { .. }
+            | Expr::Integer { .. }
+            | Expr::Imaginary { .. } => true,
+            Expr::Assign { .. } | Expr::Call { .. } | Expr::Loop { .. } | Expr::Function { .. } => {
+                false
+            }
+            Expr::Neg { value }
+            | Expr::Not { value }
+            | Expr::Cast { value, .. }
+            | Expr::Swizzle { value, .. } => value.is_pure(),
+            Expr::Chain {
+                side: lhs,
+                ret: rhs,
+            }
+            | Expr::Add { lhs, rhs }
+            | Expr::Sub { lhs, rhs }
+            | Expr::Mul { lhs, rhs }
+            | Expr::Div { lhs, rhs }
+            | Expr::Mod { lhs, rhs }
+            | Expr::Exp { lhs, rhs }
+            | Expr::Eq { lhs, rhs }
+            | Expr::Neq { lhs, rhs }
+            | Expr::Gt { lhs, rhs }
+            | Expr::Lt { lhs, rhs }
+            | Expr::Geq { lhs, rhs }
+            | Expr::Leq { lhs, rhs }
+            | Expr::And { lhs, rhs }
+            | Expr::Or { lhs, rhs } => lhs.is_pure() && rhs.is_pure(),
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => condition.is_pure() && then.is_pure() && otherwise.is_pure(),
+        }
+    }
+
+    /// Returns the direct subexpressions in evaluation order.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::VariableRead { .. }
+            | Expr::Const { .. }
+            | Expr::Integer { .. }
+            | Expr::Imaginary { .. } => Vec::new(),
+            Expr::Call { args, .. } => args.iter().collect(),
+            Expr::Neg { value }
+            | Expr::Not { value }
+            | Expr::Assign { value, .. }
+            | Expr::Cast { value, .. }
+            | Expr::Swizzle { value, .. } => vec![value],
+            Expr::Function { body, .. } => vec![body],
+            Expr::Chain {
+                side: lhs,
+                ret: rhs,
+            }
+            | Expr::Add { lhs, rhs }
+            | Expr::Sub { lhs, rhs }
+            | Expr::Mul { lhs, rhs }
+            | Expr::Div { lhs, rhs }
+            | Expr::Mod { lhs, rhs }
+            | Expr::Exp { lhs, rhs }
+            | Expr::Eq { lhs, rhs }
+            | Expr::Neq { lhs, rhs }
+            | Expr::Gt { lhs, rhs }
+            | Expr::Lt { lhs, rhs }
+            | Expr::Geq { lhs, rhs }
+            | Expr::Leq { lhs, rhs }
+            | Expr::And { lhs, rhs }
+            | Expr::Or { lhs, rhs } => vec![lhs, rhs],
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => vec![condition, then, otherwise],
+            Expr::Loop {
+                start, end, body, ..
+            } => vec![start, end, body],
+        }
+    }
+
+    /// The smallest span enclosing the spans of the expression and its subexpressions.
+    ///
+    /// Literals and operators have no span of their own, so expressions of only those return `None`.
+    pub fn extent(&self) -> Option<Span> {
+        let own = match self {
+            Expr::VariableRead { span, .. }
+            | Expr::Imaginary { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Assign { span, .. }
+            | Expr::Cast { span, .. }
+            | Expr::Swizzle { span, .. }
+            | Expr::Function { span, .. }
+            | Expr::Loop { span, .. } => Some(*span),
+            _ => None,
+        };
+        self.children()
+            .into_iter()
+            .filter_map(Expr::extent)
+            .chain(own)
+            .reduce(Span::join)
+    }
+
+    /// Whether both expressions are equal apart from their spans, e.g. if they were parsed from differently formatted text.
+    pub fn same_structure(&self, other: &Expr) -> bool {
+        let (children, other_children) = (self.children(), other.children());
+        self.same_node(other)
+            && children.len() == other_children.len()
+            && children
+                .into_iter()
+                .zip(other_children)
+                .all(|(child, other)| child.same_structure(other))
+    }
+
+    /// Whether both expressions are the same kind of node with the same fields apart from their spans and children.
+    fn same_node(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (
+                Expr::VariableRead { identifier, .. },
+                Expr::VariableRead {
+                    identifier: other, ..
+                },
+            )
+            | (
+                Expr::Call { identifier, .. },
+                Expr::Call {
+                    identifier: other, ..
+                },
+            )
+            | (
+                Expr::Assign { identifier, .. },
+                Expr::Assign {
+                    identifier: other, ..
+                },
+            )
+            | (
+                Expr::Swizzle {
+                    components: identifier,
+                    ..
+                },
+                Expr::Swizzle {
+                    components: other, ..
+                },
+            ) => identifier == other,
+            (Expr::Const { value }, Expr::Const { value: other })
+            | (Expr::Imaginary { value, .. }, Expr::Imaginary { value: other, .. }) => {
+                value == other
+            }
+            (Expr::Integer { value }, Expr::Integer { value: other }) => value == other,
+            (Expr::Cast { to, .. }, Expr::Cast { to: other, .. }) => to == other,
+            (
+                Expr::Function {
+                    identifier,
+                    parameters,
+                    ..
+                },
+                Expr::Function {
+                    identifier: other_identifier,
+                    parameters: other_parameters,
+                    ..
+                },
+            ) => identifier == other_identifier && parameters == other_parameters,
+            (
+                Expr::Loop { kind, variable, .. },
+                Expr::Loop {
+                    kind: other_kind,
+                    variable: other_variable,
+                    ..
+                },
+            ) => kind == other_kind && variable == other_variable,
+            // Nodes with fields are compared above, the remaining ones only have children.
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+
+    /// Replaces every direct subexpression with the result of `f`, in evaluation order.
+    pub fn map_children(self, mut f: impl FnMut(Expr) -> Expr) -> Expr {
+        match self {
+            Expr::VariableRead { .. }
+            | Expr::Const { .. }
+            | Expr::Integer { .. }
+            | Expr::Imaginary { .. } => self,
+            Expr::Chain { side, ret } => Expr::Chain {
+                side: Box::new(f(*side)),
+                ret: Box::new(f(*ret)),
+            },
+            Expr::Call {
+                identifier,
+                args,
+                span,
+            } => Expr::Call {
+                identifier,
+                args: args.into_iter().map(f).collect(),
+                span,
+            },
+            Expr::Add { lhs, rhs } => Expr::Add {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Sub { lhs, rhs } => Expr::Sub {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Mul { lhs, rhs } => Expr::Mul {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Div { lhs, rhs } => Expr::Div {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Mod { lhs, rhs } => Expr::Mod {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Exp { lhs, rhs } => Expr::Exp {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Eq { lhs, rhs } => Expr::Eq {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Neq { lhs, rhs } => Expr::Neq {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Gt { lhs, rhs } => Expr::Gt {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Lt { lhs, rhs } => Expr::Lt {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Geq { lhs, rhs } => Expr::Geq {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Leq { lhs, rhs } => Expr::Leq {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::And { lhs, rhs } => Expr::And {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Or { lhs, rhs } => Expr::Or {
+                lhs: Box::new(f(*lhs)),
+                rhs: Box::new(f(*rhs)),
+            },
+            Expr::Neg { value } => Expr::Neg {
+                value: Box::new(f(*value)),
+            },
+            Expr::Not { value } => Expr::Not {
+                value: Box::new(f(*value)),
+            },
+            Expr::Assign {
+                identifier,
+                value,
+                span,
+            } => Expr::Assign {
+                identifier,
+                value: Box::new(f(*value)),
+                span,
+            },
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => Expr::If {
+                condition: Box::new(f(*condition)),
+                then: Box::new(f(*then)),
+                otherwise: Box::new(f(*otherwise)),
+            },
+            Expr::Cast { value, to, span } => Expr::Cast {
+                value: Box::new(f(*value)),
+                to,
+                span,
+            },
+            Expr::Swizzle {
+                value,
+                components,
+                span,
+            } => Expr::Swizzle {
+                value: Box::new(f(*value)),
+                components,
+                span,
+            },
+            Expr::Function {
+                identifier,
+                parameters,
+                body,
+                span,
+            } => Expr::Function {
+                identifier,
+                parameters,
+                body: Box::new(f(*body)),
+                span,
+            },
+            Expr::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+                span,
+            } => Expr::Loop {
+                kind,
+                variable,
+                start: Box::new(f(*start)),
+                end: Box::new(f(*end)),
+                body: Box::new(f(*body)),
+                span,
+            },
+        }
+    }
+
+    /// Returns every read of a variable that is not assigned on all paths leading to it, in evaluation order.
+    ///
+    /// `defined` are the variables that are initialized before the expression is evaluated, e.g. its parameters.
+    /// Assignments inside the right side of `&&`/`||`, in only one branch of a conditional or in the body of a loop do not count after it.
+    /// The body of a function definition only sees its parameters.
+    pub fn uninitialized_reads(&self, defined: &[&str]) -> Vec<(String, Span)> {
+        let mut defined = defined.iter().map(|name| name.to_string()).collect();
+        let mut reads = Vec::new();
+        self.collect_uninitialized_reads(&mut defined, &mut reads);
+        reads
+    }
+
+    fn collect_uninitialized_reads(
+        &self,
+        defined: &mut HashSet<String>,
+        reads: &mut Vec<(String, Span)>,
+    ) {
+        match self {
+            Expr::VariableRead { identifier, span } => {
+                if !defined.contains(identifier) {
+                    reads.push((identifier.clone(), *span));
+                }
+            }
+            Expr::Assign {
+                identifier, value, ..
+            } => {
+                value.collect_uninitialized_reads(defined, reads);
+                defined.insert(identifier.clone());
+            }
+            Expr::And { lhs, rhs } | Expr::Or { lhs, rhs } => {
+                lhs.collect_uninitialized_reads(defined, reads);
+                rhs.collect_uninitialized_reads(&mut defined.clone(), reads);
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                condition.collect_uninitialized_reads(defined, reads);
+                let mut then_defined = defined.clone();
+                then.collect_uninitialized_reads(&mut then_defined, reads);
+                otherwise.collect_uninitialized_reads(defined, reads);
+                defined.retain(|identifier| then_defined.contains(identifier));
+            }
+            Expr::Loop {
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                start.collect_uninitialized_reads(defined, reads);
+                end.collect_uninitialized_reads(defined, reads);
+                // The body may run zero times.
+                let mut body_defined = defined.clone();
+                body_defined.insert(variable.clone());
+                body.collect_uninitialized_reads(&mut body_defined, reads);
+            }
+            Expr::Function {
+                parameters, body, ..
+            } => {
+                // The body is evaluated where the function is called, so it can not rely on any assignment around it.
+                let mut body_defined = parameters.iter().cloned().collect();
+                body.collect_uninitialized_reads(&mut body_defined, reads);
+            }
+            _ => {
+                for child in self.children() {
+                    child.collect_uninitialized_reads(defined, reads);
+                }
+            }
+        }
+    }
+
+    /// Replaces every read of `i` with the imaginary unit `1i`, unless `i` is a variable.
+    ///
+    /// `i` is a variable where it is one of the `defined` variables or a function parameter, or is assigned anywhere in its scope.
+    /// A conditional assignment therefore keeps `i` a variable, and reading it where it may be unassigned is an error.
+    pub fn resolve_imaginary_unit(self, defined: &[&str]) -> Expr {
+        let variable = defined.contains(&"i") || self.assigns_i();
+        self.replace_unit(variable)
+    }
+
+    fn assigns_i(&self) -> bool {
+        self.variables_write()
             .iter()
-            .filter_map(|e| {
-                if !write.contains(e) {
-                    Some(e.to_string())
-                } else {
-                    None
+            .any(|identifier| identifier == "i")
+    }
+
+    /// Replaces the reads of `i` with `1i` if it is not a `variable` in the current scope.
+    fn replace_unit(self, variable: bool) -> Expr {
+        match self {
+            Expr::VariableRead { identifier, span } if identifier == "i" && !variable => {
+                Expr::Imaginary { value: 1.0, span }
+            }
+            Expr::Function {
+                identifier,
+                parameters,
+                body,
+                span,
+            } => {
+                let variable =
+                    parameters.iter().any(|parameter| parameter == "i") || body.assigns_i();
+                Expr::Function {
+                    identifier,
+                    parameters,
+                    body: Box::new(body.replace_unit(variable)),
+                    span,
                 }
-            })
-            .collect();
-        Variables {
-            read,
-            write,
-            uninitialized: undefined,
+            }
+            expr => expr.map_children(|child| child.replace_unit(variable)),
         }
     }
 
-    fn variables_write(&self) -> Vec<String> {
+    pub(crate) fn variables_write(&self) -> Vec<String> {
         match self {
-            Expr::VariableRead { identifier: _ } => Vec::new(),
-            Expr::Const { value: _ } => Vec::new(),
+            Expr::VariableRead { .. } | Expr::Function { .. } => Vec::new(),
+            Expr::Const { value: _ } | Expr::Integer { .. } | Expr::Imaginary { .. } => Vec::new(),
             Expr::Chain { side, ret } => {
                 let mut v = side.variables_write();
                 v.extend(ret.variables_write());
@@ -150,6 +647,7 @@ impl Expr {
             Expr::Call {
                 identifier: _,
                 args,
+                ..
             } => {
                 let mut v = Vec::new();
                 for arg in args {
@@ -188,7 +686,9 @@ impl Expr {
                 v
             }
             Expr::Neg { value } => value.variables_write(),
-            Expr::Assign { identifier, value } => {
+            Expr::Assign {
+                identifier, value, ..
+            } => {
                 let mut v = value.variables_write();
                 v.insert(0, identifier.to_string());
                 v
@@ -233,112 +733,42 @@ impl Expr {
                 v.extend(rhs.variables_write());
                 v
             }
-            Expr::Not { value } => value.variables_write(),
-        }
-    }
-
-    fn variables_read(&self) -> Vec<String> {
-        match self {
-            Expr::VariableRead { identifier } => vec![identifier.to_string()],
-            Expr::Const { value: _ } => Vec::new(),
-            Expr::Chain { side, ret } => {
-                let mut v = side.variables_read();
-                v.extend(ret.variables_read());
-                v
+            Expr::Not { value } | Expr::Cast { value, .. } | Expr::Swizzle { value, .. } => {
+                value.variables_write()
             }
-            Expr::Call {
-                identifier: _,
-                args,
+            Expr::If {
+                condition,
+                then,
+                otherwise,
             } => {
-                let mut v = Vec::new();
-                for arg in args {
-                    v.extend(arg.variables_read());
-                }
-                v
-            }
-            Expr::Add { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Sub { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Mul { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Div { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Mod { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Exp { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Neg { value } => value.variables_read(),
-            Expr::Assign {
-                identifier: _,
-                value,
-            } => value.variables_read(),
-            Expr::Eq { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Neq { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Gt { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Lt { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Geq { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::Leq { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
-                v
-            }
-            Expr::And { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
+                let mut v = condition.variables_write();
+                v.extend(then.variables_write());
+                v.extend(otherwise.variables_write());
                 v
             }
-            Expr::Or { lhs, rhs } => {
-                let mut v = lhs.variables_read();
-                v.extend(rhs.variables_read());
+            Expr::Loop {
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                let mut v = vec![variable.to_string()];
+                v.extend(start.variables_write());
+                v.extend(end.variables_write());
+                v.extend(body.variables_write());
                 v
             }
-            Expr::Not { value } => value.variables_read(),
         }
     }
 
-    fn variables_all(&self) -> Vec<String> {
+    pub(crate) fn variables_all(&self) -> Vec<String> {
         match self {
-            Expr::VariableRead { identifier } => vec![identifier.to_string()],
-            Expr::Const { value: _ } => Vec::new(),
+            Expr::VariableRead { identifier, .. } => vec![identifier.to_string()],
+            Expr::Const { value: _ }
+            | Expr::Integer { .. }
+            | Expr::Imaginary { .. }
+            | Expr::Function { .. } => Vec::new(),
             Expr::Chain { side, ret } => {
                 let mut v = side.variables_all();
                 v.extend(ret.variables_all());
@@ -347,6 +777,7 @@ impl Expr {
             Expr::Call {
                 identifier: _,
                 args,
+                ..
             } => {
                 let mut v = Vec::new();
                 for arg in args {
@@ -385,7 +816,9 @@ impl Expr {
                 v
             }
             Expr::Neg { value } => value.variables_all(),
-            Expr::Assign { identifier, value } => {
+            Expr::Assign {
+                identifier, value, ..
+            } => {
                 let mut v = value.variables_all();
                 v.insert(0, identifier.to_string());
                 v
@@ -430,44 +863,347 @@ impl Expr {
                 v.extend(rhs.variables_all());
                 v
             }
-            Expr::Not { value } => value.variables_all(),
+            Expr::Not { value } | Expr::Cast { value, .. } | Expr::Swizzle { value, .. } => {
+                value.variables_all()
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let mut v = condition.variables_all();
+                v.extend(then.variables_all());
+                v.extend(otherwise.variables_all());
+                v
+            }
+            Expr::Loop {
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                let mut v = vec![variable.to_string()];
+                v.extend(start.variables_all());
+                v.extend(end.variables_all());
+                v.extend(body.variables_all());
+                v
+            }
+        }
+    }
+
+    /// Binding strength of the grammar level the expression is parsed at, higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Chain { .. } => 0,
+            Expr::Assign { .. } | Expr::Function { .. } => 1,
+            Expr::If { .. } => 2,
+            Expr::And { .. } | Expr::Or { .. } => 3,
+            Expr::Eq { .. }
+            | Expr::Neq { .. }
+            | Expr::Gt { .. }
+            | Expr::Lt { .. }
+            | Expr::Geq { .. }
+            | Expr::Leq { .. } => 4,
+            Expr::Add { .. } | Expr::Sub { .. } => 5,
+            Expr::Mul { .. } | Expr::Div { .. } | Expr::Mod { .. } => 6,
+            Expr::Cast { .. } => 7,
+            Expr::Exp { .. } | Expr::Neg { .. } | Expr::Not { .. } => 8,
+            Expr::Const { value } | Expr::Imaginary { value, .. } if value.is_sign_negative() => 8,
+            Expr::Integer { value } if *value < 0 => 8,
+            Expr::VariableRead { .. }
+            | Expr::Const { .. }
+            | Expr::Integer { .. }
+            | Expr::Imaginary { .. }
+            | Expr::Call { .. }
+            | Expr::Swizzle { .. }
+            | Expr::Loop { .. } => 9,
+        }
+    }
+
+    /// Writes the expression as source text, in parentheses if it binds weaker than `precedence`.
+    fn write(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "(")?;
+            self.write(f, 0)?;
+            return write!(f, ")");
+        }
+        // All binary operators are left associative.
+        let mut binary = |lhs: &Expr, operator: &str, rhs: &Expr| {
+            lhs.write(f, self.precedence())?;
+            write!(f, " {operator} ")?;
+            rhs.write(f, self.precedence() + 1)
+        };
+        match self {
+            Expr::VariableRead { identifier, .. } => write!(f, "{identifier}"),
+            Expr::Const { value } if value.is_nan() => write!(f, "(0 / 0)"),
+            Expr::Const { value } if value.is_infinite() => match value.is_sign_negative() {
+                true => write!(f, "(-1 / 0)"),
+                false => write!(f, "(1 / 0)"),
+            },
+            Expr::Integer { value } => write!(f, "{value}"),
+            Expr::Const { value } => {
+                // Debug formatting is the shortest representation that parses back to the same value.
+                let literal = format!("{value:?}");
+                write!(f, "{}", literal.strip_suffix(".0").unwrap_or(&literal))
+            }
+            Expr::Imaginary { value, .. } if !value.is_finite() => {
+                write!(f, "(")?;
+                Expr::Const { value: *value }.write(f, 6)?;
+                write!(f, " * 1i)")
+            }
+            Expr::Imaginary { value, .. } => {
+                Expr::Const { value: *value }.write(f, 0)?;
+                write!(f, "i")
+            }
+            Expr::Chain { side, ret } => {
+                side.write(f, 1)?;
+                write!(f, "; ")?;
+                ret.write(f, 0)
+            }
+            Expr::Call {
+                identifier, args, ..
+            } => {
+                write!(f, "{identifier}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    arg.write(f, 0)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Add { lhs, rhs } => binary(lhs, "+", rhs),
+            Expr::Sub { lhs, rhs } => binary(lhs, "-", rhs),
+            Expr::Mul { lhs, rhs } => binary(lhs, "*", rhs),
+            Expr::Div { lhs, rhs } => binary(lhs, "/", rhs),
+            Expr::Mod { lhs, rhs } => binary(lhs, "%", rhs),
+            Expr::Exp { lhs, rhs } => binary(lhs, "^", rhs),
+            Expr::Eq { lhs, rhs } => binary(lhs, "==", rhs),
+            Expr::Neq { lhs, rhs } => binary(lhs, "!=", rhs),
+            Expr::Gt { lhs, rhs } => binary(lhs, ">", rhs),
+            Expr::Lt { lhs, rhs } => binary(lhs, "<", rhs),
+            Expr::Geq { lhs, rhs } => binary(lhs, ">=", rhs),
+            Expr::Leq { lhs, rhs } => binary(lhs, "<=", rhs),
+            Expr::And { lhs, rhs } => binary(lhs, "&&", rhs),
+            Expr::Or { lhs, rhs } => binary(lhs, "||", rhs),
+            Expr::Neg { value } => match value.as_ref() {
+                // `-2` would be read as the literal `-2`.
+                Expr::Const { .. } | Expr::Imaginary { .. } => {
+                    write!(f, "-(")?;
+                    value.write(f, 0)?;
+                    write!(f, ")")
+                }
+                value => {
+                    write!(f, "-")?;
+                    value.write(f, 9)
+                }
+            },
+            Expr::Not { value } => {
+                write!(f, "!")?;
+                value.write(f, 9)
+            }
+            Expr::Cast { value, to, .. } => {
+                value.write(f, 7)?;
+                write!(f, " as {to}")
+            }
+            Expr::Swizzle {
+                value, components, ..
+            } => {
+                match value.as_ref() {
+                    // `2.x` would be read as the literal `2.` followed by `x`.
+                    Expr::Const { .. } | Expr::Imaginary { .. } => {
+                        write!(f, "(")?;
+                        value.write(f, 0)?;
+                        write!(f, ")")?;
+                    }
+                    value => value.write(f, 9)?,
+                }
+                write!(f, ".{components}")
+            }
+            Expr::Assign {
+                identifier, value, ..
+            } => {
+                write!(f, "{identifier} = ")?;
+                value.write(f, 1)
+            }
+            Expr::Function {
+                identifier,
+                parameters,
+                body,
+                ..
+            } => {
+                write!(f, "{identifier}({}) = ", parameters.join(", "))?;
+                body.write(f, 1)
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                condition.write(f, 3)?;
+                write!(f, " ? ")?;
+                then.write(f, 3)?;
+                write!(f, " : ")?;
+                otherwise.write(f, 2)
+            }
+            Expr::Loop {
+                kind: LoopKind::For,
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                write!(f, "for {variable} in ")?;
+                start.write(f, 0)?;
+                write!(f, "..")?;
+                end.write(f, 0)?;
+                write!(f, " {{ ")?;
+                body.write(f, 0)?;
+                write!(f, " }}")
+            }
+            Expr::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => {
+                let name = match kind {
+                    LoopKind::Product => "prod",
+                    _ => "sum",
+                };
+                write!(f, "{name}({variable}, ")?;
+                start.write(f, 0)?;
+                write!(f, ", ")?;
+                end.write(f, 0)?;
+                write!(f, ", ")?;
+                body.write(f, 0)?;
+                write!(f, ")")
+            }
         }
     }
+}
+
+impl fmt::Display for Expr {
+    /// Formats the expression as source text that parses back into an equal expression.
+    ///
+    /// Parentheses are only added where the precedence of the operators requires them.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Converts the text matched by the `literal` rule into its value.
+///
+/// Underscores are ignored. Hexadecimal literals may have a fraction and a binary exponent, e.g. `0x1.8p3` is `12`.
+fn parse_literal(text: &str) -> Option<f64> {
+    let text = text.replace('_', "");
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, text.as_str()),
+    };
+    let Some(hexadecimal) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    else {
+        return text.parse().ok();
+    };
+
+    let (mantissa, exponent) = match hexadecimal.split_once(['p', 'P']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+        None => (hexadecimal, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut value = 0_f64;
+    for digit in integer.chars().chain(fraction.chars()) {
+        value = value * 16.0 + f64::from(digit.to_digit(16)?);
+    }
+    let exponent = exponent.saturating_sub(4 * fraction.len() as i32);
+    let value = value * 2_f64.powi(exponent);
+    Some(if negative { -value } else { value })
+}
+
+/// Converts the text matched by the `literal` rule into a constant.
+///
+/// Integer literals that an `f64` can not hold exactly become an [`Expr::Integer`] instead, so integer types see their exact value.
+fn parse_constant(text: &str) -> Option<Expr> {
+    let value = parse_literal(text)?;
+    let digits = text.replace('_', "");
+    let (negative, unsigned) = match digits.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, digits.as_str()),
+    };
+    let integer = match unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        Some(hexadecimal) => i128::from_str_radix(hexadecimal, 16).ok(),
+        None => unsigned.parse::<i128>().ok(),
+    };
+    Some(
+        match integer.map(|integer| if negative { -integer } else { integer }) {
+            Some(integer) if integer as f64 as i128 != integer => Expr::Integer { value: integer },
+            _ => Expr::Const { value },
+        },
+    )
+}
+
+/// Desugars `identifier operator= value`, e.g. `a += 1` into `a = a + 1`.
+///
+/// The assignment reads the variable first, so it is reported as uninitialized if it was not assigned before.
+fn compound_assignment(identifier: String, span: Span, operator: &str, value: Expr) -> Expr {
+    let lhs = Box::new(Expr::VariableRead {
+        identifier: identifier.clone(),
+        span,
+    });
+    let rhs = Box::new(value);
+    let value = match operator {
+        "+" => Expr::Add { lhs, rhs },
+        "-" => Expr::Sub { lhs, rhs },
+        "*" => Expr::Mul { lhs, rhs },
+        "/" => Expr::Div { lhs, rhs },
+        "%" => Expr::Mod { lhs, rhs },
+        "^" => Expr::Exp { lhs, rhs },
+        "&&" => Expr::And { lhs, rhs },
+        "||" => Expr::Or { lhs, rhs },
+        _ => unreachable!("unknown compound assignment `{operator}=`"),
+    };
+    Expr::Assign {
+        identifier,
+        value: Box::new(value),
+        span,
+    }
+}
 
-    #[allow(unused)]
-    #[allow(clippy::inherent_to_string)]
-    fn to_string(&self) -> String {
+/// Desugars the statements `x++`, `++x` and `x--` into `x = x + 1` and `x = x - 1`.
+fn increment(identifier: String, span: Span, operator: &str) -> Expr {
+    compound_assignment(identifier, span, &operator[..1], Expr::Const { value: 1.0 })
+}
+
+/// The syntax expressions are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// The syntax described in the README, parsed by [`parser::expression`].
+    #[default]
+    Anita,
+    /// The numeric subset of [evalexpr](https://docs.rs/evalexpr), parsed by [`parser::evalexpr`].
+    ///
+    /// Functions may be namespaced like `math::sin`, `math::` is removed from their names.
+    /// `true` and `false` are `1` and `0`, compound assignments like `a += 1` are desugared to assignments.
+    /// Operators have the precedence of evalexpr, e.g. `-2 ^ 2` is `-4` and `&&` binds stronger than `||`.
+    /// Newlines and comments are allowed between tokens.
+    /// Strings, tuples and the string functions are reported as parse errors.
+    Evalexpr,
+}
+
+impl Syntax {
+    pub fn parse(self, source: &str) -> Result<Expr, peg::error::ParseError<peg::str::LineCol>> {
         match self {
-            Expr::VariableRead { identifier } => identifier.to_string(),
-            Expr::Const { value } => format!("{value}"),
-            Expr::Chain { side, ret } => format!("({}); ({})", side.to_string(), ret.to_string()),
-            Expr::Call { identifier, args } => {
-                format!(
-                    "{identifier}({})",
-                    args.iter().fold(String::new(), |acc, expr| acc
-                        + "("
-                        + &expr.to_string()
-                        + ")"
-                        + ", ")
-                )
-            }
-            Expr::Add { lhs, rhs } => format!("({}) + ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Sub { lhs, rhs } => format!("({}) - ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Mul { lhs, rhs } => format!("({}) * ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Div { lhs, rhs } => format!("({}) / ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Mod { lhs, rhs } => format!("({}) % ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Exp { lhs, rhs } => format!("({}) ^ ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Neg { value } => format!("-{}", value.to_string()),
-            Expr::Assign { identifier, value } => format!("{identifier} = ({})", value.to_string()),
-            Expr::Eq { lhs, rhs } => format!("({}) == ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Neq { lhs, rhs } => format!("({}) != ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Gt { lhs, rhs } => format!("({}) > ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Lt { lhs, rhs } => format!("({}) < ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Geq { lhs, rhs } => format!("({}) >= ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Leq { lhs, rhs } => format!("({}) <= ({})", lhs.to_string(), rhs.to_string()),
-            Expr::And { lhs, rhs } => format!("({}) && ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Or { lhs, rhs } => format!("({}) || ({})", lhs.to_string(), rhs.to_string()),
-            Expr::Not { value } => format!("!({})", value.to_string()),
+            Syntax::Anita => parser::expression(source),
+            Syntax::Evalexpr => parser::evalexpr(source),
         }
     }
 }
@@ -480,8 +1216,15 @@ peg::parser!(pub grammar parser() for str {
     = precedence!{
         s:@ _ ";" _ r:(@) { Expr::Chain { side: Box::new(s), ret: Box::new(r) } }
         --
-        l:literal() { Expr::Const { value: l } }
-        i:identifier() _ "=" _ e:(@) { Expr::Assign { identifier: i, value: Box::new(e) }}
+        s:position!() l:literal() "i" !identifier_char() t:position!() { Expr::Imaginary { value: l, span: Span::new(s, t) } }
+        c:constant() { c }
+        s:position!() i:identifier() _ "(" _ p:(identifier() ** (_ "," _)) _ ")" t:position!() _ "=" _ e:(@) { Expr::Function { identifier: i, parameters: p, body: Box::new(e), span: Span::new(s, t) } }
+        i:spanned_identifier() _ "=" _ e:(@) { Expr::Assign { identifier: i.0, value: Box::new(e), span: i.1 }}
+        i:spanned_identifier() _ o:$("+" / "-" / "*" / "/" / "%" / "^") "=" _ e:(@) { compound_assignment(i.0, i.1, o, e) }
+        i:spanned_identifier() _ o:$("++" / "--") &statement_end() { increment(i.0, i.1, o) }
+        "++" _ i:spanned_identifier() &statement_end() { increment(i.0, i.1, "++") }
+        --
+        c:@ _ "?" _ a:operations() _ ":" _ b:(@) { Expr::If { condition: Box::new(c), then: Box::new(a), otherwise: Box::new(b) } }
         --
         a:(@) _ "&&" _ b:@ { Expr::And{ lhs: Box::new(a), rhs: Box::new(b) } }
         a:(@) _ "||" _ b:@ { Expr::Or{ lhs: Box::new(a), rhs: Box::new(b) } }
@@ -500,23 +1243,157 @@ peg::parser!(pub grammar parser() for str {
         a:(@) _ "/" _ b:@ { Expr::Div{ lhs: Box::new(a), rhs: Box::new(b) } }
         a:(@) _ "%" _ b:@ { Expr::Mod{ lhs: Box::new(a), rhs: Box::new(b) } }
         --
+        a:(@) _ keyword("as") _ t:spanned_value_type() { Expr::Cast { value: Box::new(a), to: t.0, span: t.1 } }
+        --
         a:(@) _ "^" _ b:@ { Expr::Exp{ lhs: Box::new(a), rhs: Box::new(b) } }
         "!" a:@ { Expr::Not{ value: Box::new(a) } }
         "-" a:@  { Expr::Neg { value: Box::new(a) } }
         "(" _ e:operations() _ ")" { e }
         --
-        i:identifier() _ "(" args:((_ e:operations() _ {e}) ** ",") ")" { Expr::Call { identifier: i, args } }
-        i:identifier() { Expr::VariableRead { identifier: i }}
+        a:@ "." s:position!() c:$(['x' | 'y' | 'z' | 'w']*<1,4>) !identifier_char() t:position!() { Expr::Swizzle { value: Box::new(a), components: c.to_owned(), span: Span::new(s, t) } }
+        --
+        keyword("if") _ "(" _ c:operations() _ "," _ a:operations() _ "," _ b:operations() _ ")" { Expr::If { condition: Box::new(c), then: Box::new(a), otherwise: Box::new(b) } }
+        e:if_else() { e }
+        e:for_loop() { e }
+        e:big_operator() { e }
+        c:call() { c }
+        i:spanned_identifier() { Expr::VariableRead { identifier: i.0, span: i.1 }}
+    }
+
+    rule if_else() -> Expr
+    = keyword("if") _ c:operations() _ a:block() _ keyword("else") _ b:(if_else() / block()) {
+        Expr::If { condition: Box::new(c), then: Box::new(a), otherwise: Box::new(b) }
     }
 
+    rule for_loop() -> Expr
+    = keyword("for") _ i:spanned_identifier() _ keyword("in") _ a:operations() _ ".." _ b:operations() _ e:block() {
+        Expr::Loop { kind: LoopKind::For, variable: i.0, start: Box::new(a), end: Box::new(b), body: Box::new(e), span: i.1 }
+    }
+
+    rule big_operator() -> Expr
+    = k:(keyword("sum") { LoopKind::Sum } / keyword("prod") { LoopKind::Product }) _ "(" _ i:spanned_identifier() _ "," _ a:operations() _ "," _ b:operations() _ "," _ e:operations() _ ")" {
+        Expr::Loop { kind: k, variable: i.0, start: Box::new(a), end: Box::new(b), body: Box::new(e), span: i.1 }
+    }
+
+    rule block() -> Expr
+    = "{" _ e:operations() _ "}" { e }
+
+    /// The end of a statement, which `x++` and `x--` must be followed by so that e.g. `x--1` stays `x - -1`.
+    rule statement_end()
+    = _ (";" / "}" / ![_])
+
+    rule keyword(k: &'static str)
+    = quiet!{ i:$(['a'..='z']+) !['A'..='Z' | '0'..='9' | '_'] {? if i == k { Ok(()) } else { Err(k) } } }
+    / expected!(k)
+
+    rule call() -> Expr
+    = s:position!() i:identifier() _ "(" args:((_ e:operations() _ {e}) ** ",") ")" t:position!() {
+        Expr::Call { identifier: i, args, span: Span::new(s, t) }
+    }
+
+    rule spanned_identifier() -> (String, Span)
+    = s:position!() i:identifier() t:position!() { (i, Span::new(s, t)) }
+
+    rule spanned_value_type() -> (ValueType, Span)
+    = s:position!() i:identifier() t:position!() {? ValueType::from_name(&i).map(|ty| (ty, Span::new(s, t))).ok_or("type") }
+
     rule identifier() -> String
     = quiet!{ n:$(['a'..='z' | 'A'..='Z' | '_']['a'..='z' | 'A'..='Z' | '0'..='9' | '_']*) { n.to_owned() } }
     / expected!("identifier")
 
-    rule literal() -> f32
-    = n:$("-"?['0'..='9']+("."['0'..='9']*)?) {? n.parse().or(Err("f32"))}
+    rule identifier_char()
+    = ['a'..='z' | 'A'..='Z' | '0'..='9' | '_']
+
+    rule constant() -> Expr
+    = quiet!{ n:$("-"? (hexadecimal() / decimal())) {? parse_constant(n).ok_or("f64") } }
+    / expected!("number")
+
+    rule literal() -> f64
+    = quiet!{ n:$("-"? (hexadecimal() / decimal())) {? parse_literal(n).ok_or("f64") } }
+    / expected!("number")
+
+    rule decimal()
+    = (digits() ("." !"." digits()?)? / "." digits()) (['e' | 'E'] ['+' | '-']? digits())?
+
+    rule hexadecimal()
+    = "0" ['x' | 'X'] (hex_digits() ("." !"." hex_digits()?)? / "." hex_digits()) (['p' | 'P'] ['+' | '-']? digits())?
+
+    rule digits()
+    = ['0'..='9'] ['0'..='9' | '_']*
+
+    rule hex_digits()
+    = ['0'..='9' | 'a'..='f' | 'A'..='F'] ['0'..='9' | 'a'..='f' | 'A'..='F' | '_']*
 
     rule _() =  quiet!{[' ' | '\t']*}
+
+    /// An expression in the numeric subset of evalexpr, see [`Syntax::Evalexpr`](super::Syntax::Evalexpr).
+    pub rule evalexpr() -> Expr
+    = __ e:evalexpr_operations() __ {e}
+
+    rule evalexpr_operations() -> Expr
+    = precedence!{
+        s:@ __ ";" __ r:(@) { Expr::Chain { side: Box::new(s), ret: Box::new(r) } }
+        --
+        i:spanned_identifier() __ "=" !"=" __ e:(@) { Expr::Assign { identifier: i.0, value: Box::new(e), span: i.1 } }
+        i:spanned_identifier() __ o:$("+" / "-" / "*" / "/" / "%" / "^" / "&&" / "||") "=" __ e:(@) { compound_assignment(i.0, i.1, o, e) }
+        --
+        a:(@) __ "||" __ b:@ { Expr::Or{ lhs: Box::new(a), rhs: Box::new(b) } }
+        --
+        a:(@) __ "&&" __ b:@ { Expr::And{ lhs: Box::new(a), rhs: Box::new(b) } }
+        --
+        a:(@) __ "==" __ b:@ { Expr::Eq{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ "!=" __ b:@ { Expr::Neq{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ ">"  __ b:@ { Expr::Gt{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ ">=" __ b:@ { Expr::Geq{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ "<"  __ b:@ { Expr::Lt{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ "<=" __ b:@ { Expr::Leq{ lhs: Box::new(a), rhs: Box::new(b) } }
+        --
+        a:(@) __ "+" __ b:@ { Expr::Add{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ "-" __ b:@ { Expr::Sub{ lhs: Box::new(a), rhs: Box::new(b) } }
+        --
+        a:(@) __ "*" __ b:@ { Expr::Mul{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ "/" __ b:@ { Expr::Div{ lhs: Box::new(a), rhs: Box::new(b) } }
+        a:(@) __ "%" __ b:@ { Expr::Mod{ lhs: Box::new(a), rhs: Box::new(b) } }
+        --
+        "-" __ a:(@) { Expr::Neg { value: Box::new(a) } }
+        "!" __ a:(@) { Expr::Not { value: Box::new(a) } }
+        --
+        a:(@) __ "^" __ b:evalexpr_exponent() { Expr::Exp{ lhs: Box::new(a), rhs: Box::new(b) } }
+        --
+        a:evalexpr_atom() { a }
+    }
+
+    /// Exponents may be negated without parentheses, e.g. `2 ^ -2`.
+    rule evalexpr_exponent() -> Expr
+    = "-" __ e:evalexpr_exponent() { Expr::Neg { value: Box::new(e) } }
+    / "!" __ e:evalexpr_exponent() { Expr::Not { value: Box::new(e) } }
+    / evalexpr_atom()
+
+    rule evalexpr_atom() -> Expr
+    = !"-" c:constant() !identifier_char() { c }
+    / keyword("true") { Expr::Const { value: 1.0 } }
+    / keyword("false") { Expr::Const { value: 0.0 } }
+    / "\"" {? Err("a number (strings are not supported)") }
+    / "(" __ ")" {? Err("an expression (empty tuples are not supported)") }
+    / "(" __ evalexpr_operations() __ "," {? Err("`)` (tuples are not supported)") }
+    / "(" __ e:evalexpr_operations() __ ")" { e }
+    / keyword("if") __ "(" __ c:evalexpr_operations() __ "," __ a:evalexpr_operations() __ "," __ b:evalexpr_operations() __ ")" {
+        Expr::If { condition: Box::new(c), then: Box::new(a), otherwise: Box::new(b) }
+    }
+    / evalexpr_call()
+    / i:spanned_identifier() { Expr::VariableRead { identifier: i.0, span: i.1 } }
+
+    rule evalexpr_call() -> Expr
+    = s:position!() i:$(identifier() ("::" identifier())*) __ "(" args:((__ e:evalexpr_operations() __ {e}) ** ",") ")" t:position!() {?
+        match i.split_once("::") {
+            Some(("str", _)) => Err("a numeric function (string functions are not supported)"),
+            Some(("math", name)) => Ok(Expr::Call { identifier: name.to_owned(), args, span: Span::new(s, t) }),
+            _ => Ok(Expr::Call { identifier: i.to_owned(), args, span: Span::new(s, t) }),
+        }
+    }
+
+    /// Whitespace including newlines and the comments of evalexpr.
+    rule __() = quiet!{([' ' | '\t' | '\n' | '\r'] / "//" [^'\n']* / "/*" (!"*/" [_])* "*/")*}
 });
 
 #[cfg(test)]
@@ -530,7 +1407,7 @@ mod tests {
         match parser::expression(expr) {
             Ok(parsed) => {
                 println!("{}", expr);
-                println!("{}", parsed.to_string());
+                println!("{}", parsed);
                 println!("{:#?}", parsed)
             }
             Err(err) => println!("{}", err),
@@ -0,0 +1,308 @@
+use super::Expr;
+use crate::jit::types::AnitaType;
+
+/// Binary operation on two literals that is evaluated at compile time by [`AnitaType::fold`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+    Eq,
+    Neq,
+    Gt,
+    Lt,
+    Geq,
+    Leq,
+    And,
+    Or,
+}
+
+/// Largest absolute integer exponent that is replaced by repeated multiplication.
+pub(crate) const MAX_EXPANDED_EXPONENT: f64 = 4.0;
+
+/// Folds constant subexpressions and removes operations that do not change the result.
+///
+/// Constants are folded with the arithmetic of `T`, see [`AnitaType::fold`].
+/// Algebraic identities (`x * 1`, `x + 0`, `--x`, ...) hold for all IEEE values apart from the sign of zero,
+/// so `x * 0` is kept since it is NaN for infinite `x`, see [`optimize_assuming_finite`] for identities that do not.
+/// Small integer powers of variables are expanded into multiplications, which may differ from `powf` in the last bit.
+pub fn optimize<T: AnitaType>(expr: Expr) -> Expr {
+    optimize_with::<T, false>(expr)
+}
+
+/// Like [`optimize`], but also applies identities that only hold for finite operands, like `x - x = 0`.
+pub fn optimize_assuming_finite<T: AnitaType>(expr: Expr) -> Expr {
+    optimize_with::<T, true>(expr)
+}
+
+/// Optimizes `expr`, identities that only hold for finite operands are applied if `FINITE` is set.
+fn optimize_with<T: AnitaType, const FINITE: bool>(expr: Expr) -> Expr {
+    match expr {
+        Expr::VariableRead { .. }
+        | Expr::Const { .. }
+        | Expr::Integer { .. }
+        | Expr::Imaginary { .. } => expr,
+        Expr::Chain { side, ret } => {
+            let side = optimize_with::<T, FINITE>(*side);
+            let ret = optimize_with::<T, FINITE>(*ret);
+            if side.is_pure() {
+                ret
+            } else {
+                Expr::Chain {
+                    side: Box::new(side),
+                    ret: Box::new(ret),
+                }
+            }
+        }
+        Expr::Call {
+            identifier,
+            args,
+            span,
+        } => Expr::Call {
+            identifier,
+            args: args.into_iter().map(optimize_with::<T, FINITE>).collect(),
+            span,
+        },
+        Expr::Add { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Add, *lhs, *rhs, |lhs, rhs| match (lhs, rhs) {
+                (x, Expr::Const { value: 0.0 }) | (Expr::Const { value: 0.0 }, x) => x,
+                (lhs, Expr::Neg { value }) => Expr::Sub {
+                    lhs: Box::new(lhs),
+                    rhs: value,
+                },
+                (lhs, rhs) => Expr::Add {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            })
+        }
+        Expr::Sub { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Sub, *lhs, *rhs, |lhs, rhs| match (lhs, rhs) {
+                (x, Expr::Const { value: 0.0 }) => x,
+                (Expr::Const { value: 0.0 }, x) => {
+                    optimize_with::<T, FINITE>(Expr::Neg { value: Box::new(x) })
+                }
+                (lhs, rhs) if FINITE && lhs.same_structure(&rhs) && lhs.is_pure() => {
+                    Expr::Const { value: 0.0 }
+                }
+                (lhs, Expr::Neg { value }) => Expr::Add {
+                    lhs: Box::new(lhs),
+                    rhs: value,
+                },
+                (lhs, rhs) => Expr::Sub {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            })
+        }
+        Expr::Mul { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Mul, *lhs, *rhs, |lhs, rhs| match (lhs, rhs) {
+                (x, Expr::Const { value: 1.0 }) | (Expr::Const { value: 1.0 }, x) => x,
+                (x, Expr::Const { value: -1.0 }) | (Expr::Const { value: -1.0 }, x) => {
+                    optimize_with::<T, FINITE>(Expr::Neg { value: Box::new(x) })
+                }
+                (lhs, rhs) => Expr::Mul {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            })
+        }
+        Expr::Div { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Div, *lhs, *rhs, |lhs, rhs| match (lhs, rhs) {
+                (x, Expr::Const { value: 1.0 }) => x,
+                // `0 / 0` is NaN for floats, only types that divide it to `0` may drop the divisor.
+                (Expr::Const { value: 0.0 }, x)
+                    if x.is_pure() && T::fold(Operation::Div, 0.0, 0.0) == Some(0.0) =>
+                {
+                    Expr::Const { value: 0.0 }
+                }
+                (lhs, rhs) => Expr::Div {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            })
+        }
+        Expr::Mod { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Mod, *lhs, *rhs, |lhs, rhs| Expr::Mod {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Exp { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Exp, *lhs, *rhs, expand_power::<T>)
+        }
+        Expr::Neg { value } => match optimize_with::<T, FINITE>(*value) {
+            Expr::Neg { value } => *value,
+            Expr::Const { value } => Expr::Const { value: -value },
+            Expr::Imaginary { value, span } => Expr::Imaginary {
+                value: -value,
+                span,
+            },
+            value => Expr::Neg {
+                value: Box::new(value),
+            },
+        },
+        Expr::Assign {
+            identifier,
+            value,
+            span,
+        } => Expr::Assign {
+            identifier,
+            value: Box::new(optimize_with::<T, FINITE>(*value)),
+            span,
+        },
+        Expr::Eq { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Eq, *lhs, *rhs, |lhs, rhs| Expr::Eq {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Neq { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Neq, *lhs, *rhs, |lhs, rhs| Expr::Neq {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Gt { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Gt, *lhs, *rhs, |lhs, rhs| Expr::Gt {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Lt { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Lt, *lhs, *rhs, |lhs, rhs| Expr::Lt {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Geq { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Geq, *lhs, *rhs, |lhs, rhs| Expr::Geq {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Leq { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Leq, *lhs, *rhs, |lhs, rhs| Expr::Leq {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::And { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::And, *lhs, *rhs, |lhs, rhs| Expr::And {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Or { lhs, rhs } => {
+            binary::<T, FINITE>(Operation::Or, *lhs, *rhs, |lhs, rhs| Expr::Or {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            })
+        }
+        Expr::Not { value } => match optimize_with::<T, FINITE>(*value) {
+            Expr::Const { value } => match T::fold(Operation::Eq, value, 0.0) {
+                Some(value) => Expr::Const { value },
+                None => Expr::Not {
+                    value: Box::new(Expr::Const { value }),
+                },
+            },
+            value => Expr::Not {
+                value: Box::new(value),
+            },
+        },
+        Expr::Cast { value, to, span } => Expr::Cast {
+            value: Box::new(optimize_with::<T, FINITE>(*value)),
+            to,
+            span,
+        },
+        Expr::Swizzle {
+            value,
+            components,
+            span,
+        } => Expr::Swizzle {
+            value: Box::new(optimize_with::<T, FINITE>(*value)),
+            components,
+            span,
+        },
+        Expr::If {
+            condition,
+            then,
+            otherwise,
+        } => match optimize_with::<T, FINITE>(*condition) {
+            // Truthiness is decided in `T`, e.g. `1e-50` is zero as an f32.
+            Expr::Const { value } => match T::fold(Operation::Neq, value, 0.0) {
+                Some(1.0) => optimize_with::<T, FINITE>(*then),
+                Some(_) => optimize_with::<T, FINITE>(*otherwise),
+                None => Expr::If {
+                    condition: Box::new(Expr::Const { value }),
+                    then: Box::new(optimize_with::<T, FINITE>(*then)),
+                    otherwise: Box::new(optimize_with::<T, FINITE>(*otherwise)),
+                },
+            },
+            condition => Expr::If {
+                condition: Box::new(condition),
+                then: Box::new(optimize_with::<T, FINITE>(*then)),
+                otherwise: Box::new(optimize_with::<T, FINITE>(*otherwise)),
+            },
+        },
+        Expr::Loop { .. } | Expr::Function { .. } => expr.map_children(optimize_with::<T, FINITE>),
+    }
+}
+
+/// Optimizes both operands and folds them if they are constant, otherwise `simplify` is applied to the optimized operands.
+fn binary<T: AnitaType, const FINITE: bool>(
+    operation: Operation,
+    lhs: Expr,
+    rhs: Expr,
+    simplify: impl FnOnce(Expr, Expr) -> Expr,
+) -> Expr {
+    let (lhs, rhs) = (
+        optimize_with::<T, FINITE>(lhs),
+        optimize_with::<T, FINITE>(rhs),
+    );
+    if let (Expr::Const { value: l }, Expr::Const { value: r }) = (&lhs, &rhs) {
+        if let Some(value) = T::fold(operation, *l, *r) {
+            return Expr::Const { value };
+        }
+    }
+    simplify(lhs, rhs)
+}
+
+fn expand_power<T: AnitaType>(base: Expr, exponent: Expr) -> Expr {
+    let power = match (&base, &exponent) {
+        (Expr::VariableRead { .. }, Expr::Const { value })
+            if T::expand_powers()
+                && value.fract() == 0.0
+                && value.abs() <= MAX_EXPANDED_EXPONENT =>
+        {
+            *value as i32
+        }
+        _ => {
+            return Expr::Exp {
+                lhs: Box::new(base),
+                rhs: Box::new(exponent),
+            }
+        }
+    };
+    let mut product = Expr::Const { value: 1.0 };
+    for _ in 0..power.unsigned_abs() {
+        product = match product {
+            Expr::Const { value: 1.0 } => base.clone(),
+            product => Expr::Mul {
+                lhs: Box::new(product),
+                rhs: Box::new(base.clone()),
+            },
+        };
+    }
+    if power < 0 {
+        Expr::Div {
+            lhs: Box::new(Expr::Const { value: 1.0 }),
+            rhs: Box::new(product),
+        }
+    } else {
+        product
+    }
+}
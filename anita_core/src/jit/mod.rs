@@ -1,37 +1,77 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, error::Error, fmt, mem};
 
 use super::function_manager::FunctionManager;
 use codegen::ir::FuncRef;
+use compiled_function::{DynamicFunction, DynamicFunctionPointer};
 use cranelift::{
     codegen,
     prelude::{
-        settings, AbiParam, Block, Configurable, EntityRef, FunctionBuilder,
-        FunctionBuilderContext, InstBuilder, Signature, Variable,
+        settings, types::F32, AbiParam, Configurable, EntityRef, FunctionBuilder,
+        FunctionBuilderContext, InstBuilder, IntCC, MemFlags, Type, Value, Variable,
     },
 };
 use cranelift_jit::{JITBuilder, JITModule};
-use cranelift_module::{Module, ModuleError};
-use frontend::{parser, Expr};
+use cranelift_module::{FuncId, Module, ModuleError};
+use frontend::{
+    definitions::{self, DefinitionError},
+    diagnostic,
+    optimizer::{optimize, optimize_assuming_finite},
+    Expr, Span, Syntax,
+};
+use itertools::Itertools;
 use peg::{error::ParseError, str::LineCol};
-use translator::{ExprTranslator, TranslatorError};
-use types::AnitaType;
+use translator::{declare_iterations, ExprTranslator};
+use typed::translator::{build_vector, vector_lanes, TypedTranslator};
+use types::{AnitaType, Lowered, ValueType};
 
 pub mod compiled_function;
 pub mod frontend;
+pub mod session;
 mod translator;
+mod typed;
 pub mod types;
 
+pub use translator::TranslatorError;
+
 #[macro_export]
 macro_rules! compile_expression {
     (@to_f32 $_:ident, $target:ty) => {$target};
 
+    (@dual $expression:expr, $parameter:ident, $target:ty, $derivative:ty, $functions:ty) => {
+        {
+            use $crate::jit::{types::Dual, JIT};
+
+            JIT::<Dual<$target>, $functions>::default()
+                .compile_dynamic($expression, &[stringify!($parameter)])
+                .map(|function| {
+                    move |$parameter: $target| -> ($target, $derivative) {
+                        let argument = Dual::variable($parameter);
+                        // SAFETY: the function was compiled for exactly one parameter.
+                        let result = unsafe { function.call_unchecked(&argument) };
+                        (result.value, result.derivative)
+                    }
+                })
+        }
+    };
+
+    ($expression:expr, ($parameter:ident) -> ($target:ty, $derivative:ty)) => {
+        compile_expression!(@dual $expression, $parameter, $target, $derivative, $crate::function_manager::NoFunctions)
+    };
+
+    ($expression:expr, ($parameter:ident) -> ($target:ty, $derivative:ty), $functions:ty) => {
+        compile_expression!(@dual $expression, $parameter, $target, $derivative, $functions)
+    };
+
     ($expression:expr, ($($parameter:ident),*) -> $target:ty) => {
+        compile_expression!($expression, ($($parameter),*) -> $target, $crate::function_manager::NoFunctions)
+    };
+
+    ($expression:expr, ($($parameter:ident),*) -> $target:ty, $functions:ty) => {
         {
             use std::mem;
             use $crate::jit::{compiled_function::CompiledFunction, JIT};
-            use $crate::function_manager::NoFunctions;
 
-            let mut jit = JIT::<$target, NoFunctions>::default();
+            let mut jit = JIT::<$target, $functions>::default();
             match jit.compile($expression, &[$( stringify!($parameter) ),*]) {
                 Ok(code_ptr) => {
                     let function_pointer = unsafe { mem::transmute::<*const u8, fn($(compile_expression!(@to_f32 $parameter, $target)),*) -> $target>(code_ptr) };
@@ -45,17 +85,80 @@ macro_rules! compile_expression {
         }
     };
 
+    ($expression:expr, ($($parameter:ident : $type:ty),*) -> $target:ty) => {
+        compile_expression!($expression, ($($parameter: $type),*) -> $target, $crate::function_manager::NoFunctions)
+    };
+
+    ($expression:expr, ($($parameter:ident : $type:ty),*) -> $target:ty, $functions:ty) => {
+        {
+            use std::mem;
+            use $crate::jit::{compiled_function::CompiledFunction, types::Primitive, JIT};
+
+            let mut jit = JIT::<f64, $functions>::default();
+            let parameters = [$( (stringify!($parameter), <$type as Primitive>::VALUE_TYPE) ),*];
+            match jit.compile_typed($expression, &parameters, <$target as Primitive>::VALUE_TYPE) {
+                Ok(code_ptr) => {
+                    let address = code_ptr as usize;
+                    let function = move |$($parameter: $type),*| -> $target {
+                        // SAFETY: the code was compiled for exactly these parameters and result, see `Primitive`.
+                        unsafe {
+                            if <$target as Primitive>::VALUE_TYPE.is_vector() {
+                                let function_pointer = mem::transmute::<usize, fn($(<$type as Primitive>::Argument,)* *mut $target)>(address);
+                                let mut result = mem::MaybeUninit::<$target>::uninit();
+                                function_pointer($(Primitive::argument(&$parameter),)* result.as_mut_ptr());
+                                result.assume_init()
+                            } else {
+                                let function_pointer = mem::transmute::<usize, fn($(<$type as Primitive>::Argument),*) -> $target>(address);
+                                function_pointer($(Primitive::argument(&$parameter)),*)
+                            }
+                        }
+                    };
+                    let memory_region = jit.dissolve();
+                    Ok(CompiledFunction::new(memory_region, function))
+                },
+                Err(e) => {
+                    Err(e)
+                }
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! compile_batch_expression {
+    ($expression:expr, ($($parameter:ident),*) -> $target:ty) => {
+        {
+            use std::mem;
+            use $crate::jit::{compiled_function::CompiledBatchFunction, JIT};
+            use $crate::function_manager::NoFunctions;
+
+            let parameters = [$( stringify!($parameter) ),*];
+            let mut jit = JIT::<$target, NoFunctions>::default();
+            match jit.compile_batch($expression, &parameters) {
+                Ok(code_ptr) => {
+                    let function_pointer = unsafe { mem::transmute::<*const u8, extern "C" fn(*const *const $target, *mut $target, usize)>(code_ptr) };
+                    let memory_region = jit.dissolve();
+                    Ok(CompiledBatchFunction::new(memory_region, function_pointer, parameters.len()))
+                },
+                Err(e) => {
+                    Err(e)
+                }
+            }
+        }
+    };
+
     ($expression:expr, ($($parameter:ident),*) -> $target:ty, $functions:ty) => {
         {
             use std::mem;
-            use $crate::jit::{compiled_function::CompiledFunction, JIT};
+            use $crate::jit::{compiled_function::CompiledBatchFunction, JIT};
 
+            let parameters = [$( stringify!($parameter) ),*];
             let mut jit = JIT::<$target, $functions>::default();
-            match jit.compile($expression, &[$( stringify!($parameter) ),*]) {
+            match jit.compile_batch($expression, &parameters) {
                 Ok(code_ptr) => {
-                    let function_pointer = unsafe { mem::transmute::<*const u8, fn($(compile_expression!(@to_f32 $parameter, $target)),*) -> $target>(code_ptr) };
+                    let function_pointer = unsafe { mem::transmute::<*const u8, extern "C" fn(*const *const $target, *mut $target, usize)>(code_ptr) };
                     let memory_region = jit.dissolve();
-                    Ok(CompiledFunction::new(memory_region, function_pointer))
+                    Ok(CompiledBatchFunction::new(memory_region, function_pointer, parameters.len()))
                 },
                 Err(e) => {
                     Err(e)
@@ -68,9 +171,86 @@ macro_rules! compile_expression {
 #[derive(Debug)]
 pub enum JITError {
     TranslatorError(TranslatorError),
-    ModuleError(ModuleError),
-    ParseError(ParseError<LineCol>),
-    UseOfUninitializedVariables(Box<[String]>),
+    ModuleError(Box<ModuleError>),
+    ParseError(Box<ParseError<LineCol>>),
+    /// Variables that are read without being a parameter or being assigned, with the span of their first read.
+    UseOfUninitializedVariables(Box<[(String, Span)]>),
+    /// A function defined in the expression can not be inlined, see [`definitions::inline`].
+    DefinitionError(DefinitionError),
+    DuplicateFunction(String),
+    /// `cranelift_native` does not support the host, see [`JIT::try_default`].
+    UnsupportedHost(String),
+}
+
+impl JITError {
+    /// Location of the error in the expression, if it was caused by a part of it.
+    ///
+    /// For [`JITError::UseOfUninitializedVariables`] this is the first of the reported reads.
+    pub fn span(&self) -> Option<Span> {
+        self.spans().into_iter().min_by_key(|span| span.start)
+    }
+
+    /// Renders the error with the offending parts of `source` underlined, see [`diagnostic::render`].
+    ///
+    /// `source` has to be the expression that failed to compile.
+    pub fn render(&self, source: &str) -> String {
+        diagnostic::render(source, &self.to_string(), &self.spans())
+    }
+
+    fn spans(&self) -> Vec<Span> {
+        match self {
+            JITError::TranslatorError(error) => error.span().into_iter().collect(),
+            JITError::ParseError(error) => {
+                vec![Span::new(error.location.offset, error.location.offset)]
+            }
+            JITError::UseOfUninitializedVariables(variables) => {
+                variables.iter().map(|(_, span)| *span).collect()
+            }
+            JITError::DefinitionError(error) => vec![error.span()],
+            JITError::ModuleError(_)
+            | JITError::DuplicateFunction(_)
+            | JITError::UnsupportedHost(_) => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for JITError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JITError::TranslatorError(error) => error.fmt(f),
+            JITError::ModuleError(error) => write!(f, "code generation failed: {error}"),
+            JITError::ParseError(error) => write!(f, "invalid syntax, expected {}", error.expected),
+            JITError::UseOfUninitializedVariables(variables) => {
+                let names = variables.iter().map(|(name, _)| format!("`{name}`"));
+                match variables.len() {
+                    1 => write!(f, "use of uninitialized variable "),
+                    _ => write!(f, "use of uninitialized variables "),
+                }?;
+                write!(f, "{}", names.collect::<Vec<_>>().join(", "))
+            }
+            JITError::DefinitionError(error) => error.fmt(f),
+            JITError::DuplicateFunction(name) => {
+                write!(f, "a function named `{name}` was already added")
+            }
+            JITError::UnsupportedHost(message) => {
+                write!(f, "host machine is not supported: {message}")
+            }
+        }
+    }
+}
+
+impl Error for JITError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JITError::TranslatorError(error) => Some(error),
+            JITError::ModuleError(error) => Some(error.as_ref()),
+            JITError::ParseError(error) => Some(error.as_ref()),
+            JITError::DefinitionError(error) => Some(error),
+            JITError::UseOfUninitializedVariables(_)
+            | JITError::DuplicateFunction(_)
+            | JITError::UnsupportedHost(_) => None,
+        }
+    }
 }
 
 impl From<TranslatorError> for JITError {
@@ -79,28 +259,66 @@ impl From<TranslatorError> for JITError {
     }
 }
 
+impl From<DefinitionError> for JITError {
+    fn from(value: DefinitionError) -> Self {
+        Self::DefinitionError(value)
+    }
+}
+
 impl From<ModuleError> for JITError {
     fn from(value: ModuleError) -> Self {
-        Self::ModuleError(value)
+        Self::ModuleError(Box::new(value))
     }
 }
 
 impl From<ParseError<LineCol>> for JITError {
     fn from(value: ParseError<LineCol>) -> Self {
-        Self::ParseError(value)
+        Self::ParseError(Box::new(value))
     }
 }
 
+/// Calling convention of the function a compiled expression is exposed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    /// `fn(T, ..) -> T` with one argument per parameter.
+    Scalar,
+    /// `extern "C" fn(*const T, *mut T)` reading the parameters from an array and writing the result to the second pointer.
+    ParameterArray,
+    /// `extern "C" fn(*const *const T, *mut T, usize)` evaluating slices of parameters.
+    Batch,
+}
+
+/// Default of [`JIT::set_max_iterations`].
+pub const DEFAULT_MAX_ITERATIONS: u64 = 1_000_000;
+
 pub struct JIT<T: AnitaType, F: FunctionManager> {
     builder_context: FunctionBuilderContext,
     ctx: codegen::Context,
     module: Box<JITModule>,
+    optimize: bool,
+    assume_finite: bool,
+    syntax: Syntax,
+    max_iterations: u64,
     _function_manager: std::marker::PhantomData<F>,
     _type: std::marker::PhantomData<T>,
 }
 
 impl<T: AnitaType, F: FunctionManager> Default for JIT<T, F> {
+    /// Sets up a JIT for the host, see [`JIT::try_default`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the host is not supported.
     fn default() -> Self {
+        Self::try_default().unwrap_or_else(|error| panic!("{error}"))
+    }
+}
+
+impl<T: AnitaType, F: FunctionManager> JIT<T, F> {
+    /// Sets up a JIT for the host or returns [`JITError::UnsupportedHost`] if `cranelift_native` does not support it.
+    ///
+    /// Expressions can still be evaluated on such hosts with the [`Interpreter`](crate::interpreter::Interpreter).
+    pub fn try_default() -> Result<Self, JITError> {
         let mut flag_builder = settings::builder();
         flag_builder
             .set("use_colocated_libcalls", "false")
@@ -108,30 +326,36 @@ impl<T: AnitaType, F: FunctionManager> Default for JIT<T, F> {
         flag_builder
             .set("is_pic", "false")
             .expect("Failed to set JIT flags");
-        let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
-            panic!("host machine is not supported: {}", msg);
-        });
+        let isa_builder =
+            cranelift_native::builder().map_err(|msg| JITError::UnsupportedHost(msg.to_owned()))?;
         let isa = isa_builder
             .finish(settings::Flags::new(flag_builder))
             .expect("Failed to finish ISA builder");
         let mut builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
 
-        builder.symbol("inbuilt_pow", T::inbuilt_pow as *const u8);
+        for (ident, addr, _) in T::inbuilt_functions() {
+            builder.symbol(ident, addr);
+        }
+        for (ident, addr) in typed::translator::inbuilt_symbols() {
+            builder.symbol(ident, addr);
+        }
         for (ident, addr) in F::function_symbols() {
             builder.symbol(ident, addr);
         }
         let module = Box::new(JITModule::new(builder));
-        Self {
+        Ok(Self {
             builder_context: FunctionBuilderContext::new(),
             ctx: module.make_context(),
             module,
+            optimize: true,
+            assume_finite: false,
+            syntax: Syntax::default(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
             _function_manager: std::marker::PhantomData,
             _type: std::marker::PhantomData,
-        }
+        })
     }
-}
 
-impl<T: AnitaType, F: FunctionManager> JIT<T, F> {
     /// Drops self and returns an owned pointer to the memory region containing the compiled code.
     ///
     /// Can be used to manually manage the memory the validatity of the compiled function relies on.
@@ -141,6 +365,41 @@ impl<T: AnitaType, F: FunctionManager> JIT<T, F> {
         self.module
     }
 
+    /// Enables or disables the optimization pass, see [`frontend::optimizer::optimize`].
+    ///
+    /// Optimization is enabled by default. Disabling it lowers the expression exactly as written, which can help when debugging.
+    /// Results may differ in the last bits, since small integer powers like `x ^ 3` become multiplications instead of calls to `powf`.
+    pub fn set_optimize(&mut self, optimize: bool) -> &mut Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Lets the optimization pass apply identities that only hold for finite operands, like `x - x = 0`.
+    ///
+    /// Disabled by default, so optimized expressions evaluate like written ones, see [`frontend::optimizer::optimize_assuming_finite`].
+    pub fn set_assume_finite(&mut self, assume_finite: bool) -> &mut Self {
+        self.assume_finite = assume_finite;
+        self
+    }
+
+    /// Sets the syntax expressions are parsed in.
+    ///
+    /// [`Syntax::Evalexpr`] accepts the numeric subset of evalexpr expressions.
+    pub fn set_syntax(&mut self, syntax: Syntax) -> &mut Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Sets the amount of loop iterations per evaluation, [`DEFAULT_MAX_ITERATIONS`] by default.
+    ///
+    /// All loops of an evaluation, including nested ones, take their iterations from the same budget,
+    /// so expressions from untrusted sources terminate after a bounded amount of work.
+    /// The result is truncated silently: once the budget is used up every loop stops and evaluates to the result of its iterations so far.
+    pub fn set_max_iterations(&mut self, max_iterations: u64) -> &mut Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
     /// Compiles `expression` to a function of the `parameters` and returns the a pointer to the compiled code.
     ///
     /// The pointer remains valid until the module field of the JIT is deallocated.
@@ -153,64 +412,147 @@ impl<T: AnitaType, F: FunctionManager> JIT<T, F> {
         expression: E,
         parameters: &[&str],
     ) -> Result<*const u8, JITError> {
-        let ast = parser::expression(expression.as_ref())?;
-
-        self.translate(ast, parameters)?;
+        self.compile_entry(expression, parameters, Entry::Scalar)
+    }
 
-        let id = self.module.declare_function(
-            "expression",
-            cranelift_module::Linkage::Export,
-            &self.ctx.func.signature,
-        )?;
+    /// Compiles `expression` to a function evaluating it over slices of the `parameters` and returns a pointer to the compiled code.
+    ///
+    /// The compiled code has the signature `extern "C" fn(*const *const T, *mut T, usize)`, see [`CompiledBatchFunction`](compiled_function::CompiledBatchFunction).
+    /// The pointer remains valid until the module field of the JIT is deallocated.
+    ///
+    /// It is advised to use the provided [`compile_batch_expression!`] macro instead.
+    pub fn compile_batch<E: AsRef<str>>(
+        &mut self,
+        expression: E,
+        parameters: &[&str],
+    ) -> Result<*const u8, JITError> {
+        self.compile_entry(expression, parameters, Entry::Batch)
+    }
 
-        self.module.define_function(id, &mut self.ctx)?;
+    /// Compiles `expression` to a function of the `parameters` whose amount is only known at runtime.
+    ///
+    /// The returned [`DynamicFunction`] owns the compiled code and checks the amount of arguments on every call.
+    pub fn compile_dynamic<E: AsRef<str>>(
+        mut self,
+        expression: E,
+        parameters: &[&str],
+    ) -> Result<DynamicFunction<T>, JITError> {
+        let code_ptr = self.compile_entry(expression, parameters, Entry::ParameterArray)?;
+        // SAFETY: Entry::ParameterArray functions are translated with exactly this signature.
+        let function_pointer =
+            unsafe { mem::transmute::<*const u8, DynamicFunctionPointer<T>>(code_ptr) };
+        Ok(DynamicFunction::new(
+            self.dissolve(),
+            function_pointer,
+            parameters,
+        ))
+    }
 
-        self.module.clear_context(&mut self.ctx);
+    fn compile_entry<E: AsRef<str>>(
+        &mut self,
+        expression: E,
+        parameters: &[&str],
+        entry: Entry,
+    ) -> Result<*const u8, JITError> {
+        let id = self.define(expression.as_ref(), parameters, entry)?;
+        Ok(self.finalize(id))
+    }
 
+    fn finalize(&mut self, id: FuncId) -> *const u8 {
         self.module
             .finalize_definitions()
             .expect("Failed to compile expression");
-        Ok(self.module.get_finalized_function(id))
+        self.module.get_finalized_function(id)
+    }
+
+    /// Translates `expression` and defines it as an anonymous function in the module without finalizing it.
+    fn define(
+        &mut self,
+        expression: &str,
+        parameters: &[&str],
+        entry: Entry,
+    ) -> Result<FuncId, JITError> {
+        let translated = self
+            .syntax
+            .parse(expression)
+            .map_err(JITError::from)
+            .map(|ast| match T::has_imaginary_unit() {
+                true => ast.resolve_imaginary_unit(parameters),
+                false => ast,
+            })
+            .and_then(|ast| {
+                // Optimization may remove variable reads, so uninitialized variables are reported beforehand.
+                Self::check_variables(&ast, parameters)?;
+                let ast = definitions::inline(ast)?;
+                Ok(match (self.optimize, self.assume_finite) {
+                    (true, false) => optimize::<T>(ast),
+                    (true, true) => optimize_assuming_finite::<T>(ast),
+                    (false, _) => ast,
+                })
+            })
+            .and_then(|ast| match entry {
+                Entry::Batch => self.translate_batch(ast, parameters),
+                _ => self.translate(ast, parameters, entry),
+            });
+        self.declare_translated(translated)
+    }
+
+    /// Defines the function translated into the context as an anonymous function, or resets the context if translation failed.
+    fn declare_translated(&mut self, translated: Result<(), JITError>) -> Result<FuncId, JITError> {
+        if let Err(e) = translated {
+            // A translation error leaves the function half built, so the state is reset for the next expression.
+            self.builder_context = FunctionBuilderContext::new();
+            self.module.clear_context(&mut self.ctx);
+            return Err(e);
+        }
+
+        let defined = self
+            .module
+            .declare_anonymous_function(&self.ctx.func.signature)
+            .map_err(JITError::from)
+            .and_then(|id| {
+                self.module.define_function(id, &mut self.ctx)?;
+                Ok(id)
+            });
+
+        self.module.clear_context(&mut self.ctx);
+
+        defined
     }
 
     fn declare_inbuilt_functions(
         functions: &mut HashMap<String, (FuncRef, usize)>,
         builder: &mut FunctionBuilder,
         module: &mut JITModule,
-    ) -> Result<(), ModuleError> {
-        let inbuilt_pow_signature = Signature {
-            params: vec![
-                AbiParam::new(T::cranelift_repr()),
-                AbiParam::new(T::cranelift_repr()),
-            ],
-            returns: vec![AbiParam::new(T::cranelift_repr())],
-            call_conv: module.isa().default_call_conv(),
-        };
-        let func_id = module.declare_function(
-            "inbuilt_pow",
-            cranelift_module::Linkage::Import,
-            &inbuilt_pow_signature,
-        )?;
-        let func = (module.declare_func_in_func(func_id, builder.func), 2);
-        functions.insert("inbuilt_pow".to_owned(), func);
+    ) -> Result<(), JITError> {
+        let call_conv = module.isa().default_call_conv();
+        let pointer_type = module.target_config().pointer_type();
+        for (ident, _, arity) in T::inbuilt_functions() {
+            let signature = T::function_signature(arity, call_conv, pointer_type);
+            let func_id =
+                module.declare_function(ident, cranelift_module::Linkage::Import, &signature)?;
+            let func = (module.declare_func_in_func(func_id, builder.func), arity);
+            functions.insert(ident.to_owned(), func);
+        }
         Ok(())
     }
 
-    fn translate(&mut self, root: Expr, params: &[&str]) -> Result<(), JITError> {
-        for _name in params {
-            self.ctx
-                .func
-                .signature
-                .params
-                .push(AbiParam::new(T::cranelift_repr()));
+    fn translate(&mut self, root: Expr, params: &[&str], entry: Entry) -> Result<(), JITError> {
+        let pointer_type = self.module.target_config().pointer_type();
+        let components = T::Lowered::COMPONENTS;
+        let signature = &mut self.ctx.func.signature;
+        match entry {
+            Entry::ParameterArray => {
+                signature.params.push(AbiParam::new(pointer_type));
+                signature.params.push(AbiParam::new(pointer_type));
+            }
+            _ => {
+                let repr = AbiParam::new(T::cranelift_repr());
+                signature.params = vec![repr; params.len() * components];
+                signature.returns = vec![repr; components];
+            }
         }
 
-        self.ctx
-            .func
-            .signature
-            .returns
-            .push(AbiParam::new(T::cranelift_repr())); // Always returns f32
-
         let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_context);
 
         let entry_block = builder.create_block();
@@ -218,7 +560,38 @@ impl<T: AnitaType, F: FunctionManager> JIT<T, F> {
         builder.switch_to_block(entry_block);
         builder.seal_block(entry_block);
 
-        let variables = Self::declare_variables(&mut builder, &root, params, entry_block)?;
+        let element_size = (T::cranelift_repr().bytes() as usize * components) as i32;
+        let param_values: Vec<T::Lowered> = match entry {
+            Entry::ParameterArray => {
+                let parameters = builder.block_params(entry_block)[0];
+                (0..params.len() as i32)
+                    .map(|i| {
+                        Self::load(
+                            &mut builder,
+                            T::cranelift_repr(),
+                            MemFlags::trusted(),
+                            parameters,
+                            i * element_size,
+                        )
+                    })
+                    .collect()
+            }
+            _ => builder
+                .block_params(entry_block)
+                .chunks(components)
+                .map(T::Lowered::from_values)
+                .collect(),
+        };
+        let mut index = 0;
+        let variables = Self::declare_variables(
+            &mut builder,
+            &root,
+            params,
+            &param_values,
+            T::cranelift_repr(),
+            &mut index,
+        );
+        let iterations = declare_iterations(&mut builder, &mut index, self.max_iterations);
         let mut functions = HashMap::default();
 
         Self::declare_inbuilt_functions(&mut functions, &mut builder, &mut self.module)?;
@@ -228,58 +601,417 @@ impl<T: AnitaType, F: FunctionManager> JIT<T, F> {
             variables,
             functions,
             module: &mut self.module,
+            vector_type: None,
+            variable_index: index,
+            iterations,
             _function_manager: std::marker::PhantomData,
             _type: std::marker::PhantomData,
         };
 
         let return_value = translator.translate(root)?;
 
-        builder.ins().return_(&[return_value]);
+        match entry {
+            Entry::ParameterArray => {
+                let output = builder.block_params(entry_block)[1];
+                Self::store(&mut builder, MemFlags::trusted(), return_value, output, 0);
+                builder.ins().return_(&[]);
+            }
+            _ => {
+                builder.ins().return_(&return_value.values());
+            }
+        }
+        builder.finalize();
+
+        Ok(())
+    }
+
+    fn translate_typed(
+        &mut self,
+        root: typed::Typed,
+        params: &[(&str, ValueType)],
+        variables: &HashMap<String, ValueType>,
+        returns: ValueType,
+    ) -> Result<(), JITError> {
+        let pointer_type = self.module.target_config().pointer_type();
+        let abi_type = |ty: ValueType| match ty.is_vector() {
+            true => pointer_type,
+            false => ty.cranelift_repr(),
+        };
+        let signature = &mut self.ctx.func.signature;
+        signature.params = params
+            .iter()
+            .map(|(_, ty)| AbiParam::new(abi_type(*ty)))
+            .collect();
+        match returns.is_vector() {
+            true => signature.params.push(AbiParam::new(pointer_type)),
+            false => signature.returns = vec![AbiParam::new(returns.cranelift_repr())],
+        }
+
+        let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_context);
+
+        let entry_block = builder.create_block();
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        builder.seal_block(entry_block);
+
+        let declared: HashMap<String, Variable> = variables
+            .iter()
+            .enumerate()
+            .map(|(index, (name, ty))| {
+                let variable = Variable::new(index);
+                builder.declare_var(variable, ty.cranelift_repr());
+                (name.clone(), variable)
+            })
+            .collect();
+        let arguments = builder.block_params(entry_block).to_vec();
+        for ((name, ty), value) in params.iter().zip(&arguments) {
+            let value = match ty.is_vector() {
+                true => {
+                    let lanes: Vec<Value> = (0..ty.components() as i32)
+                        .map(|lane| {
+                            builder
+                                .ins()
+                                .load(F32, MemFlags::trusted(), *value, lane * 4)
+                        })
+                        .collect();
+                    build_vector(&mut builder, &lanes)
+                }
+                false => *value,
+            };
+            builder.def_var(declared[*name], value);
+        }
+        let mut index = declared.len();
+        let iterations = declare_iterations(&mut builder, &mut index, self.max_iterations);
+
+        let mut translator = TypedTranslator::<F> {
+            builder: &mut builder,
+            variables: declared,
+            functions: HashMap::default(),
+            module: &mut self.module,
+            iterations,
+            _function_manager: std::marker::PhantomData,
+        };
+        let return_value = translator.translate(root)?;
+        match returns.is_vector() {
+            true => {
+                let output = arguments[params.len()];
+                for (lane, value) in vector_lanes(&mut builder, return_value, returns)
+                    .into_iter()
+                    .enumerate()
+                {
+                    builder
+                        .ins()
+                        .store(MemFlags::trusted(), value, output, lane as i32 * 4);
+                }
+                builder.ins().return_(&[]);
+            }
+            false => {
+                builder.ins().return_(&[return_value]);
+            }
+        }
+        builder.finalize();
+
+        Ok(())
+    }
+
+    /// Lowers `root` into a loop over parameter slices.
+    ///
+    /// The generated function has the signature `extern "C" fn(*const *const T, *mut T, usize)`.
+    /// The first argument points to one base pointer per parameter, the second to the output and the third is the number of elements.
+    /// Elements are processed in chunks of [`AnitaType::cranelift_vector_repr`] followed by a scalar loop over the remainder.
+    fn translate_batch(&mut self, root: Expr, params: &[&str]) -> Result<(), JITError> {
+        let pointer_type = self.module.target_config().pointer_type();
+        let pointer_size = i64::from(self.module.target_config().pointer_bytes());
+        let element_size = i64::from(T::cranelift_repr().bytes()) * T::Lowered::COMPONENTS as i64;
+        let signature = &mut self.ctx.func.signature;
+        signature.params.push(AbiParam::new(pointer_type));
+        signature.params.push(AbiParam::new(pointer_type));
+        signature.params.push(AbiParam::new(pointer_type));
+
+        let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_context);
+        let flags = MemFlags::new().with_notrap();
+
+        let entry_block = builder.create_block();
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        builder.seal_block(entry_block);
+        let (parameters, output, length) = match builder.block_params(entry_block) {
+            &[parameters, output, length] => (parameters, output, length),
+            _ => unreachable!("batch signature has exactly three parameters"),
+        };
+        let base_pointers: Vec<Value> = (0..params.len() as i64)
+            .map(|i| {
+                builder
+                    .ins()
+                    .load(pointer_type, flags, parameters, (i * pointer_size) as i32)
+            })
+            .collect();
+
+        let mut functions = HashMap::default();
+        Self::declare_inbuilt_functions(&mut functions, &mut builder, &mut self.module)?;
+        let mut index = 0;
+
+        let zero = builder.ins().iconst(pointer_type, 0);
+        let mut scalar_start = zero;
+        let mut vector_loop = None;
+        // Numbers with several components are interleaved in memory, so only single components are loaded as vectors.
+        let vector_type = T::cranelift_vector_repr().filter(|_| T::Lowered::COMPONENTS == 1);
+        if let Some(vector_type) = vector_type {
+            let lanes = i64::from(vector_type.lane_count());
+            let remainder = builder.ins().urem_imm(length, lanes);
+            let vector_length = builder.ins().isub(length, remainder);
+            vector_loop = Some((vector_type, lanes, vector_length));
+            scalar_start = vector_length;
+        }
+
+        let scalar_header = builder.create_block();
+        builder.append_block_param(scalar_header, pointer_type);
+
+        if let Some((vector_type, lanes, vector_length)) = vector_loop {
+            let vector_header = builder.create_block();
+            let vector_body = builder.create_block();
+            builder.append_block_param(vector_header, pointer_type);
+            builder.ins().jump(vector_header, &[zero]);
+
+            builder.switch_to_block(vector_header);
+            let position = builder.block_params(vector_header)[0];
+            let in_bounds = builder
+                .ins()
+                .icmp(IntCC::UnsignedLessThan, position, vector_length);
+            builder
+                .ins()
+                .brif(in_bounds, vector_body, &[], scalar_header, &[position]);
+
+            builder.switch_to_block(vector_body);
+            builder.seal_block(vector_body);
+            let offset = builder.ins().imul_imm(position, element_size);
+            let param_values: Vec<T::Lowered> = base_pointers
+                .iter()
+                .map(|base| {
+                    let address = builder.ins().iadd(*base, offset);
+                    Self::load(&mut builder, vector_type, flags, address, 0)
+                })
+                .collect();
+            let variables = Self::declare_variables(
+                &mut builder,
+                &root,
+                params,
+                &param_values,
+                vector_type,
+                &mut index,
+            );
+            let iterations = declare_iterations(&mut builder, &mut index, self.max_iterations);
+            let mut translator = ExprTranslator::<T, F> {
+                builder: &mut builder,
+                variables,
+                functions,
+                module: &mut self.module,
+                vector_type: Some(vector_type),
+                variable_index: index,
+                iterations,
+                _function_manager: std::marker::PhantomData,
+                _type: std::marker::PhantomData,
+            };
+            let result = translator.translate(root.clone())?;
+            functions = translator.functions;
+            index = translator.variable_index;
+            let address = builder.ins().iadd(output, offset);
+            Self::store(&mut builder, flags, result, address, 0);
+            let next = builder.ins().iadd_imm(position, lanes);
+            builder.ins().jump(vector_header, &[next]);
+            builder.seal_block(vector_header);
+        } else {
+            builder.ins().jump(scalar_header, &[scalar_start]);
+        }
+
+        let scalar_body = builder.create_block();
+        let exit_block = builder.create_block();
+
+        builder.switch_to_block(scalar_header);
+        let position = builder.block_params(scalar_header)[0];
+        let in_bounds = builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, position, length);
+        builder
+            .ins()
+            .brif(in_bounds, scalar_body, &[], exit_block, &[]);
+
+        builder.switch_to_block(scalar_body);
+        builder.seal_block(scalar_body);
+        let offset = builder.ins().imul_imm(position, element_size);
+        let param_values: Vec<T::Lowered> = base_pointers
+            .iter()
+            .map(|base| {
+                let address = builder.ins().iadd(*base, offset);
+                Self::load(&mut builder, T::cranelift_repr(), flags, address, 0)
+            })
+            .collect();
+        let variables = Self::declare_variables(
+            &mut builder,
+            &root,
+            params,
+            &param_values,
+            T::cranelift_repr(),
+            &mut index,
+        );
+        let iterations = declare_iterations(&mut builder, &mut index, self.max_iterations);
+        let mut translator = ExprTranslator::<T, F> {
+            builder: &mut builder,
+            variables,
+            functions,
+            module: &mut self.module,
+            vector_type: None,
+            variable_index: index,
+            iterations,
+            _function_manager: std::marker::PhantomData,
+            _type: std::marker::PhantomData,
+        };
+        let result = translator.translate(root)?;
+        let address = builder.ins().iadd(output, offset);
+        Self::store(&mut builder, flags, result, address, 0);
+        let next = builder.ins().iadd_imm(position, 1);
+        builder.ins().jump(scalar_header, &[next]);
+        builder.seal_block(scalar_header);
+
+        builder.switch_to_block(exit_block);
+        builder.seal_block(exit_block);
+        builder.ins().return_(&[]);
         builder.finalize();
 
         Ok(())
     }
 
+    /// Loads a number whose components of type `repr` are stored one after another at `address + offset`.
+    fn load(
+        builder: &mut FunctionBuilder,
+        repr: Type,
+        flags: MemFlags,
+        address: Value,
+        offset: i32,
+    ) -> T::Lowered {
+        let values: Vec<Value> = (0..T::Lowered::COMPONENTS as i32)
+            .map(|component| {
+                let offset = offset + component * repr.bytes() as i32;
+                builder.ins().load(repr, flags, address, offset)
+            })
+            .collect();
+        T::Lowered::from_values(&values)
+    }
+
+    /// Stores the components of `value` one after another at `address + offset`.
+    fn store(
+        builder: &mut FunctionBuilder,
+        flags: MemFlags,
+        value: T::Lowered,
+        address: Value,
+        offset: i32,
+    ) {
+        let mut offset = offset;
+        for component in value.values() {
+            builder.ins().store(flags, component, address, offset);
+            offset += builder.func.dfg.value_type(component).bytes() as i32;
+        }
+    }
+
+    pub(crate) fn check_variables(root: &Expr, params: &[&str]) -> Result<(), JITError> {
+        let uninitialized: Box<[(String, Span)]> = root
+            .uninitialized_reads(params)
+            .into_iter()
+            .unique_by(|(identifier, _)| identifier.clone())
+            .collect();
+        match uninitialized.is_empty() {
+            true => Ok(()),
+            false => Err(JITError::UseOfUninitializedVariables(uninitialized)),
+        }
+    }
+
+    /// Declares a variable for every identifier in `node`.
+    ///
+    /// [`JIT::check_variables`] has to be called beforehand, so that every read is preceded by a definition.
     fn declare_variables(
         builder: &mut FunctionBuilder,
         node: &Expr,
         params: &[&str],
-        entry_block: Block,
-    ) -> Result<HashMap<String, Variable>, JITError> {
+        param_values: &[T::Lowered],
+        repr: Type,
+        index: &mut usize,
+    ) -> HashMap<String, Vec<Variable>> {
         let mut variables = HashMap::new();
-        let mut index = 0;
 
-        let mut vars = node.variables();
-        for (i, name) in params.iter().enumerate() {
-            vars.set_defined(&name.to_string());
-            let val = builder.block_params(entry_block)[i];
-            let var = Self::declare_variable(builder, &mut variables, &mut index, name);
-            builder.def_var(var, val);
+        for (name, val) in params.iter().zip(param_values) {
+            let var = Self::declare_variable(builder, &mut variables, index, name, repr);
+            for (component, value) in var.iter().zip(val.values()) {
+                builder.def_var(*component, value);
+            }
         }
-        let identifiers = match vars.initialized_identifiers() {
-            Ok(i) => i,
-            Err(uninitialized) => return Err(JITError::UseOfUninitializedVariables(uninitialized)),
-        };
-
-        for name in identifiers {
-            let _ = Self::declare_variable(builder, &mut variables, &mut index, &name);
+        for name in node.variables_all() {
+            let _ = Self::declare_variable(builder, &mut variables, index, &name, repr);
         }
 
-        Ok(variables)
+        variables
     }
 
     fn declare_variable(
         builder: &mut FunctionBuilder,
-        variables: &mut HashMap<String, Variable>,
+        variables: &mut HashMap<String, Vec<Variable>>,
         index: &mut usize,
         name: &str,
-    ) -> Variable {
-        let var = Variable::new(*index);
-        if !variables.contains_key(name) {
-            variables.insert(name.into(), var);
-            builder.declare_var(var, T::cranelift_repr());
-            *index += 1;
+        repr: Type,
+    ) -> Vec<Variable> {
+        if let Some(var) = variables.get(name) {
+            return var.clone();
+        }
+        let var: Vec<Variable> = (0..T::Lowered::COMPONENTS)
+            .map(|component| Variable::new(*index + component))
+            .collect();
+        for component in &var {
+            builder.declare_var(*component, repr);
         }
+        *index += var.len();
+        variables.insert(name.into(), var.clone());
         var
     }
 }
+
+/// Typed expressions give every value a [`ValueType`] of its own instead of `T`, so they are compiled by a `JIT<f64, F>`.
+impl<F: FunctionManager> JIT<f64, F> {
+    /// Compiles `expression` to a function whose parameters and result have the given types and returns a pointer to the compiled code.
+    ///
+    /// The compiled code has the signature `fn(..) -> returns` with one argument of the declared type per parameter.
+    /// Vector parameters are passed as a pointer to their components, a vector result is written to a pointer passed as the last argument instead.
+    /// Types are inferred and converted as described in the [`typed`](self::typed) module, explicit conversions are written as `x as i32`.
+    /// Constants are folded in the types of their operations, see [`JIT::set_optimize`].
+    /// The pointer remains valid until the module field of the JIT is deallocated.
+    ///
+    /// It is advised to use the provided [`compile_expression!`] macro with typed parameters like `(n: i32, x: f64) -> f64` instead.
+    pub fn compile_typed<E: AsRef<str>>(
+        &mut self,
+        expression: E,
+        parameters: &[(&str, ValueType)],
+        returns: ValueType,
+    ) -> Result<*const u8, JITError> {
+        let names: Vec<&str> = parameters.iter().map(|(name, _)| *name).collect();
+        let call_conv = self.module.isa().default_call_conv();
+        let translated = self
+            .syntax
+            .parse(expression.as_ref())
+            .map_err(JITError::from)
+            .and_then(|ast| {
+                Self::check_variables(&ast, &names)?;
+                let ast = definitions::inline(ast)?;
+                Ok(typed::check::<F>(ast, parameters, returns, call_conv)?)
+            })
+            .map(
+                |(root, variables)| match (self.optimize, self.assume_finite) {
+                    (true, false) => (typed::optimizer::optimize::<false>(root), variables),
+                    (true, true) => (typed::optimizer::optimize::<true>(root), variables),
+                    (false, _) => (root, variables),
+                },
+            )
+            .and_then(|(root, variables)| {
+                self.translate_typed(root, parameters, &variables, returns)
+            });
+        let id = self.declare_translated(translated)?;
+        Ok(self.finalize(id))
+    }
+}
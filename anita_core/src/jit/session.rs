@@ -0,0 +1,142 @@
+use std::{collections::HashMap, mem, sync::Arc};
+
+use cranelift_module::FuncId;
+
+use super::{
+    super::function_manager::FunctionManager,
+    compiled_function::{DynamicFunction, DynamicFunctionPointer, FrozenJITModule},
+    frontend::Syntax,
+    types::AnitaType,
+    Entry, JITError, JIT,
+};
+
+/// Compiles many named expressions into a single module.
+///
+/// The ISA and the function symbols are only set up once and all expressions are finalized together by [`Session::finish`].
+///
+/// ```ignore
+/// let mut session = Session::<f32, DefaultFunctions>::default();
+/// session.add("area", "a * b", &["a", "b"])?;
+/// session.add("circle", "3.14159 * r ^ 2", &["r"])?;
+/// let module = session.finish();
+/// let area = module.get("area").unwrap();
+/// assert_eq!(area.call(&[2.0, 3.0]), Ok(6.0));
+/// ```
+pub struct Session<T: AnitaType, F: FunctionManager> {
+    jit: JIT<T, F>,
+    functions: HashMap<String, (FuncId, Arc<[String]>)>,
+}
+
+impl<T: AnitaType, F: FunctionManager> Default for Session<T, F> {
+    fn default() -> Self {
+        Self {
+            jit: JIT::default(),
+            functions: HashMap::new(),
+        }
+    }
+}
+
+impl<T: AnitaType, F: FunctionManager> Session<T, F> {
+    /// Enables or disables the optimization pass for expressions added afterwards, see [`JIT::set_optimize`].
+    pub fn set_optimize(&mut self, optimize: bool) -> &mut Self {
+        self.jit.set_optimize(optimize);
+        self
+    }
+
+    /// Lets the optimization pass assume finite operands for expressions added afterwards, see [`JIT::set_assume_finite`].
+    pub fn set_assume_finite(&mut self, assume_finite: bool) -> &mut Self {
+        self.jit.set_assume_finite(assume_finite);
+        self
+    }
+
+    /// Sets the syntax of expressions added afterwards, see [`JIT::set_syntax`].
+    pub fn set_syntax(&mut self, syntax: Syntax) -> &mut Self {
+        self.jit.set_syntax(syntax);
+        self
+    }
+
+    /// Sets the loop iterations per evaluation of expressions added afterwards, see [`JIT::set_max_iterations`].
+    pub fn set_max_iterations(&mut self, max_iterations: u64) -> &mut Self {
+        self.jit.set_max_iterations(max_iterations);
+        self
+    }
+
+    /// Translates `expression` to a function of the `parameters` that can be retrieved as `name` after finishing the session.
+    ///
+    /// A failed expression leaves the session untouched, so other expressions can still be added.
+    pub fn add<E: AsRef<str>>(
+        &mut self,
+        name: &str,
+        expression: E,
+        parameters: &[&str],
+    ) -> Result<&mut Self, JITError> {
+        if self.functions.contains_key(name) {
+            return Err(JITError::DuplicateFunction(name.to_owned()));
+        }
+        let id = self
+            .jit
+            .define(expression.as_ref(), parameters, Entry::ParameterArray)?;
+        let parameters = parameters.iter().map(|p| p.to_string()).collect();
+        self.functions.insert(name.to_owned(), (id, parameters));
+        Ok(self)
+    }
+
+    /// Finalizes all added expressions at once.
+    pub fn finish(mut self) -> CompiledModule<T> {
+        self.jit
+            .module
+            .finalize_definitions()
+            .expect("Failed to compile expression");
+        let functions = self
+            .functions
+            .into_iter()
+            .map(|(name, (id, parameters))| {
+                let code_ptr = self.jit.module.get_finalized_function(id);
+                // SAFETY: Entry::ParameterArray functions are translated with exactly this signature.
+                let function_pointer =
+                    unsafe { mem::transmute::<*const u8, DynamicFunctionPointer<T>>(code_ptr) };
+                (name, (function_pointer, parameters))
+            })
+            .collect();
+        CompiledModule {
+            functions,
+            memory_region: Arc::new(self.jit.dissolve().into()),
+        }
+    }
+}
+
+type ModuleFunction<T> = (DynamicFunctionPointer<T>, Arc<[String]>);
+
+/// Shared handle to the functions compiled by a [`Session`].
+///
+/// The compiled code stays valid as long as the module or any function taken from it is alive.
+pub struct CompiledModule<T> {
+    functions: HashMap<String, ModuleFunction<T>>,
+    memory_region: Arc<FrozenJITModule>,
+}
+
+impl<T> Clone for CompiledModule<T> {
+    fn clone(&self) -> Self {
+        Self {
+            functions: self.functions.clone(),
+            memory_region: self.memory_region.clone(),
+        }
+    }
+}
+
+impl<T> CompiledModule<T> {
+    /// Returns the function added as `name`.
+    pub fn get(&self, name: &str) -> Option<DynamicFunction<T>> {
+        let (function_pointer, parameters) = self.functions.get(name)?;
+        Some(DynamicFunction::shared(
+            self.memory_region.clone(),
+            *function_pointer,
+            parameters.clone(),
+        ))
+    }
+
+    /// The names of all functions in the module.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+}
@@ -1,153 +1,823 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, error::Error, fmt, mem};
 
 use cranelift::{
     codegen::ir::FuncRef,
-    prelude::{FunctionBuilder, InstBuilder, Value, Variable},
+    prelude::{
+        types::I64, Block, EntityRef, FunctionBuilder, InstBuilder, IntCC, Signature,
+        StackSlotData, StackSlotKind, Type, Value, Variable,
+    },
 };
 use cranelift_jit::JITModule;
 use cranelift_module::{Module, ModuleError};
+use itertools::Itertools;
 
-use super::{super::function_manager::FunctionManager, frontend::Expr, types::AnitaType};
+use super::{
+    super::function_manager::{Derivatives, FunctionManager},
+    frontend::{definitions, optimizer::optimize, parser, Expr, LoopKind, Span},
+    types::{AnitaType, FunctionCall, Lowered, ValueType},
+    JIT,
+};
+
+/// Partial derivatives of `inbuilt_pow`, which lowers `x ^ y`.
+const INBUILT_POW_DERIVATIVES: Derivatives = Derivatives {
+    parameters: &["x", "y"],
+    partials: &["y * x ^ (y - 1)", "x ^ y * inbuilt_ln(x)"],
+};
 
 pub(super) struct ExprTranslator<'a, 'b, T: AnitaType, F: FunctionManager> {
     pub(super) builder: &'b mut FunctionBuilder<'a>,
-    pub(super) variables: HashMap<String, Variable>,
+    /// One Cranelift variable per component of every declared variable.
+    pub(super) variables: HashMap<String, Vec<Variable>>,
     pub(super) functions: HashMap<String, (FuncRef, usize)>,
     pub(super) module: &'b mut JITModule,
+    /// When set, every translated value is a vector of this type instead of a scalar.
+    pub(super) vector_type: Option<Type>,
+    /// Index of the next Cranelift variable, used when derivatives of functions declare their parameters.
+    pub(super) variable_index: usize,
+    /// Iterations left to the loops of the evaluation, see [`declare_iterations`].
+    pub(super) iterations: Variable,
     pub(super) _function_manager: std::marker::PhantomData<F>,
     pub(super) _type: std::marker::PhantomData<T>,
 }
 
-// TODO: improve Errors
 #[derive(Debug)]
 pub enum TranslatorError {
-    FunctionNotFound(String),
-    ModuleError(ModuleError),
+    FunctionNotFound {
+        name: String,
+        span: Span,
+    },
+    /// A variable was not declared before translation.
+    VariableNotFound {
+        name: String,
+        span: Span,
+    },
+    /// A function is called with a different amount of arguments than it takes.
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    /// A function of the [`FunctionManager`] does not take and return the type the expression is compiled for.
+    SignatureMismatch {
+        name: String,
+        expected: Box<Signature>,
+        found: Box<Signature>,
+        span: Span,
+    },
+    /// A function is called with a type that needs its derivatives but it has none.
+    MissingDerivative {
+        name: String,
+        span: Span,
+    },
+    /// The derivatives of a function can not be parsed or do not match its parameters.
+    InvalidDerivative {
+        name: String,
+        span: Span,
+    },
+    /// A cast to a type other than the one the expression is compiled for, which needs [`JIT::compile_typed`].
+    UnsupportedCast {
+        to: ValueType,
+        span: Span,
+    },
+    /// Two values of a typed expression have no type both can be converted to without an explicit cast.
+    IncompatibleTypes {
+        lhs: ValueType,
+        rhs: ValueType,
+        /// Covers the operation combining the values.
+        span: Option<Span>,
+    },
+    /// An imaginary literal or `i` in an expression compiled for a type without an [`AnitaType::imaginary`] unit.
+    UnsupportedImaginary {
+        span: Span,
+    },
+    /// A swizzle like `v.xy` in an expression without typed parameters, which has no vectors.
+    UnsupportedSwizzle {
+        span: Span,
+    },
+    /// A value of a typed expression can not be converted to the type it is used as, e.g. a vector to a scalar.
+    ///
+    /// The span covers the converted value, or the operation if two values of different types are combined.
+    InvalidConversion {
+        from: ValueType,
+        to: ValueType,
+        span: Option<Span>,
+    },
+    /// Vectors are compared with an operator other than `==` and `!=`.
+    VectorComparison {
+        ty: ValueType,
+        /// Covers the comparison.
+        span: Option<Span>,
+    },
+    /// A swizzle takes components the value does not have.
+    InvalidSwizzle {
+        components: String,
+        ty: ValueType,
+        span: Span,
+    },
+    /// An inbuilt function of typed expressions, like `dot`, is called with arguments of the wrong types.
+    InvalidArguments {
+        name: String,
+        found: Vec<ValueType>,
+        span: Span,
+    },
+    /// A function takes more parameters than the [`Interpreter`](crate::interpreter::Interpreter) can pass.
+    UnsupportedArity {
+        name: String,
+        arity: usize,
+        span: Span,
+    },
+    /// A loop in an expression compiled for a type without [`AnitaType::decided_conditions`], which can not decide when to stop.
+    UnsupportedLoop {
+        span: Span,
+    },
+    /// The variable of a loop in a typed expression is not a number that can be counted up, like a `bool` or a vector.
+    InvalidLoopVariable {
+        name: String,
+        ty: ValueType,
+        span: Span,
+    },
+    ModuleError(Box<ModuleError>),
+}
+
+impl TranslatorError {
+    /// Location of the error in the expression, if it was caused by a part of it.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TranslatorError::FunctionNotFound { span, .. }
+            | TranslatorError::VariableNotFound { span, .. }
+            | TranslatorError::ArityMismatch { span, .. }
+            | TranslatorError::SignatureMismatch { span, .. }
+            | TranslatorError::MissingDerivative { span, .. }
+            | TranslatorError::InvalidDerivative { span, .. }
+            | TranslatorError::UnsupportedCast { span, .. }
+            | TranslatorError::UnsupportedImaginary { span }
+            | TranslatorError::UnsupportedSwizzle { span }
+            | TranslatorError::InvalidSwizzle { span, .. }
+            | TranslatorError::InvalidArguments { span, .. }
+            | TranslatorError::UnsupportedArity { span, .. }
+            | TranslatorError::UnsupportedLoop { span }
+            | TranslatorError::InvalidLoopVariable { span, .. } => Some(*span),
+            TranslatorError::InvalidConversion { span, .. }
+            | TranslatorError::IncompatibleTypes { span, .. }
+            | TranslatorError::VectorComparison { span, .. } => *span,
+            TranslatorError::ModuleError(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for TranslatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranslatorError::FunctionNotFound { name, .. } => {
+                write!(f, "unknown function `{name}`")
+            }
+            TranslatorError::VariableNotFound { name, .. } => {
+                write!(f, "unknown variable `{name}`")
+            }
+            TranslatorError::ArityMismatch {
+                name,
+                expected,
+                found,
+                ..
+            } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                let verb = if *found == 1 { "was" } else { "were" };
+                write!(
+                    f,
+                    "function `{name}` takes {expected} argument{plural} but {found} {verb} given"
+                )
+            }
+            TranslatorError::SignatureMismatch {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "function `{name}` has the signature `{found}` instead of `{expected}`"
+            ),
+            TranslatorError::MissingDerivative { name, .. } => {
+                write!(f, "function `{name}` has no derivative")
+            }
+            TranslatorError::InvalidDerivative { name, .. } => {
+                write!(f, "the derivative of function `{name}` is invalid")
+            }
+            TranslatorError::UnsupportedCast { to, .. } => {
+                write!(f, "casts to `{to}` require typed parameters")
+            }
+            TranslatorError::IncompatibleTypes { lhs, rhs, .. } => {
+                write!(f, "`{lhs}` and `{rhs}` can not be combined without a cast")
+            }
+            TranslatorError::UnsupportedImaginary { .. } => {
+                write!(f, "imaginary numbers require a complex type")
+            }
+            TranslatorError::UnsupportedSwizzle { .. } => {
+                write!(f, "swizzles require typed parameters")
+            }
+            TranslatorError::InvalidConversion { from, to, .. } => {
+                write!(f, "`{from}` can not be converted to `{to}`")
+            }
+            TranslatorError::VectorComparison { ty, .. } => {
+                write!(f, "`{ty}` can only be compared with `==` and `!=`")
+            }
+            TranslatorError::InvalidSwizzle { components, ty, .. } => {
+                write!(f, "`{ty}` has no components `{components}`")
+            }
+            TranslatorError::InvalidArguments { name, found, .. } => write!(
+                f,
+                "function `{name}` can not be called with `{}`",
+                found.iter().join("`, `")
+            ),
+            TranslatorError::UnsupportedArity { name, arity, .. } => write!(
+                f,
+                "function `{name}` takes {arity} arguments, which is more than the interpreter supports"
+            ),
+            TranslatorError::UnsupportedLoop { .. } => {
+                write!(f, "loops require a type whose comparisons are decided")
+            }
+            TranslatorError::InvalidLoopVariable { name, ty, .. } => {
+                write!(f, "the loop variable `{name}` can not be a `{ty}`")
+            }
+            TranslatorError::ModuleError(error) => write!(f, "code generation failed: {error}"),
+        }
+    }
+}
+
+impl Error for TranslatorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TranslatorError::FunctionNotFound { .. }
+            | TranslatorError::VariableNotFound { .. }
+            | TranslatorError::ArityMismatch { .. }
+            | TranslatorError::SignatureMismatch { .. }
+            | TranslatorError::MissingDerivative { .. }
+            | TranslatorError::InvalidDerivative { .. }
+            | TranslatorError::UnsupportedCast { .. }
+            | TranslatorError::IncompatibleTypes { .. }
+            | TranslatorError::UnsupportedImaginary { .. }
+            | TranslatorError::UnsupportedSwizzle { .. }
+            | TranslatorError::InvalidConversion { .. }
+            | TranslatorError::VectorComparison { .. }
+            | TranslatorError::InvalidSwizzle { .. }
+            | TranslatorError::InvalidArguments { .. }
+            | TranslatorError::UnsupportedArity { .. }
+            | TranslatorError::UnsupportedLoop { .. }
+            | TranslatorError::InvalidLoopVariable { .. } => None,
+            TranslatorError::ModuleError(error) => Some(error.as_ref()),
+        }
+    }
 }
 
 impl From<ModuleError> for TranslatorError {
     fn from(value: ModuleError) -> Self {
-        Self::ModuleError(value)
+        Self::ModuleError(Box::new(value))
     }
 }
 
+/// A translated value, either a number or a boolean condition.
+///
+/// Comparisons and logical operators result in conditions as returned by [`AnitaType::truthy`], which conditionals branch on directly.
+/// Where a number is needed they are converted with [`AnitaType::from_condition`] to `1` if set and `0` otherwise,
+/// numbers used as conditions are converted with [`AnitaType::truthy`].
+#[derive(Clone, Copy)]
+enum Translated<L: Lowered> {
+    Number(L),
+    /// An `I8` that is `0` or `1` for scalars, a lane mask for vectors.
+    Condition(Value),
+}
+
 impl<T: AnitaType, F: FunctionManager> ExprTranslator<'_, '_, T, F> {
-    pub fn translate(&mut self, expr: Expr) -> Result<Value, TranslatorError> {
-        match expr {
-            Expr::VariableRead { identifier } => {
-                let variable = self
-                    .variables
-                    .get(&identifier)
-                    .unwrap_or_else(|| panic!("Variable {} does not exist", identifier));
-                Ok(self.builder.use_var(*variable))
-            }
-            Expr::Const { value } => Ok(T::constant(self.builder, value)),
+    /// Translates `expr` to a number, converting conditions to `0` or `1`.
+    pub fn translate(&mut self, expr: Expr) -> Result<T::Lowered, TranslatorError> {
+        let value = self.translate_value(expr)?;
+        Ok(self.number(value))
+    }
+
+    /// Translates `expr` to a condition, numbers other than `0` are true.
+    fn translate_condition(&mut self, expr: Expr) -> Result<Value, TranslatorError> {
+        let value = self.translate_value(expr)?;
+        Ok(self.condition(value))
+    }
+
+    fn number(&mut self, value: Translated<T::Lowered>) -> T::Lowered {
+        match value {
+            Translated::Number(number) => number,
+            Translated::Condition(condition) => T::from_condition(self.builder, condition),
+        }
+    }
+
+    fn condition(&mut self, value: Translated<T::Lowered>) -> Value {
+        match value {
+            Translated::Number(number) => T::truthy(self.builder, number),
+            Translated::Condition(condition) => condition,
+        }
+    }
+
+    fn translate_value(&mut self, expr: Expr) -> Result<Translated<T::Lowered>, TranslatorError> {
+        let number = match expr {
+            Expr::VariableRead { identifier, span } => {
+                let variable = self.variable(identifier, span)?;
+                self.read(&variable)
+            }
+            Expr::Const { value } => {
+                let constant = T::constant(self.builder, value);
+                match self.vector_type {
+                    Some(_) => T::splat(self.builder, constant),
+                    None => constant,
+                }
+            }
+            Expr::Integer { value } => {
+                let constant = T::integer_constant(self.builder, value);
+                match self.vector_type {
+                    Some(_) => T::splat(self.builder, constant),
+                    None => constant,
+                }
+            }
+            Expr::Imaginary { value, span } => {
+                let Some(imaginary) = T::imaginary(self.builder, value) else {
+                    return Err(TranslatorError::UnsupportedImaginary { span });
+                };
+                match self.vector_type {
+                    Some(_) => T::splat(self.builder, imaginary),
+                    None => imaginary,
+                }
+            }
             Expr::Chain { side, ret } => {
-                let _side = self.translate(*side)?;
-                let ret = self.translate(*ret)?;
-                Ok(ret)
+                // The value of the side is discarded, so it is not converted.
+                let _side = self.translate_value(*side)?;
+                return self.translate_value(*ret);
             }
-            Expr::Call { identifier, args } => {
-                let args: Vec<Value> = args.into_iter().map(|expr| {
-                    self.translate(expr)
-                }).collect::<Result<Vec<Value>, TranslatorError>>()?;
+            Expr::Call {
+                identifier,
+                args,
+                span,
+            } => {
+                let (func_ref, arity) = self.declare_function(&identifier, span)?;
+                if args.len() != arity {
+                    return Err(TranslatorError::ArityMismatch {
+                        name: identifier,
+                        expected: arity,
+                        found: args.len(),
+                        span,
+                    });
+                }
+                let args: Vec<T::Lowered> =
+                    args.into_iter()
+                        .map(|expr| self.translate(expr))
+                        .collect::<Result<Vec<T::Lowered>, TranslatorError>>()?;
 
-                Ok(self.function_call(&identifier, args.as_slice())?)
+                self.call(func_ref, &identifier, span, &args)?
             }
             Expr::Add { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::add(self.builder, lhs, rhs))
+                T::add(self.builder, lhs, rhs)
             }
             Expr::Sub { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::sub(self.builder, lhs, rhs))
+                T::sub(self.builder, lhs, rhs)
             }
             Expr::Mul { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::mul(self.builder, lhs, rhs))
+                T::mul(self.builder, lhs, rhs)
             }
             Expr::Div { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::div(self.builder, lhs, rhs))
+                T::div(self.builder, lhs, rhs)
             }
             Expr::Mod { lhs, rhs } => {
                 let (value, modulus) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::modulo(self.builder, value, modulus))
+                T::modulo(self.builder, value, modulus)
             }
             Expr::Exp { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(self.function_call("inbuilt_pow", &[lhs, rhs])?)
+                // Declared up front by `JIT::declare_inbuilt_functions`.
+                let (pow, _) = self.functions["inbuilt_pow"];
+                self.call(pow, "inbuilt_pow", Span::default(), &[lhs, rhs])?
             }
             Expr::Neg { value } => {
                 let value = self.translate(*value)?;
-                Ok(T::neg(self.builder, value))
-            }
-            Expr::Assign { identifier, value } => {
-                let variable = self
-                    .variables
-                    .get(&identifier)
-                    .copied()
-                    .unwrap_or_else(|| panic!("Variable {} does not exist", identifier));
+                T::neg(self.builder, value)
+            }
+            Expr::Assign {
+                identifier,
+                value,
+                span,
+            } => {
+                let variable = self.variable(identifier, span)?;
                 let value = self.translate(*value)?;
-                self.builder.def_var(variable, value);
-                Ok(self.builder.use_var(variable))
+                self.write(&variable, value);
+                self.read(&variable)
             }
             Expr::Eq { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::eq(self.builder, lhs, rhs))
+                return Ok(Translated::Condition(T::eq(self.builder, lhs, rhs)));
             }
             Expr::Neq { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::neq(self.builder, lhs, rhs))
+                return Ok(Translated::Condition(T::neq(self.builder, lhs, rhs)));
             }
             Expr::Gt { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::gt(self.builder, lhs, rhs))
+                return Ok(Translated::Condition(T::gt(self.builder, lhs, rhs)));
             }
             Expr::Lt { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::lt(self.builder, lhs, rhs))
+                return Ok(Translated::Condition(T::lt(self.builder, lhs, rhs)));
             }
             Expr::Geq { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::geq(self.builder, lhs, rhs))
+                return Ok(Translated::Condition(T::geq(self.builder, lhs, rhs)));
             }
             Expr::Leq { lhs, rhs } => {
                 let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::leq(self.builder, lhs, rhs))
+                return Ok(Translated::Condition(T::leq(self.builder, lhs, rhs)));
             }
-            Expr::And { lhs, rhs } => {
-                let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::and(self.builder, lhs, rhs))
+            Expr::And { lhs, rhs } => return self.logical(*lhs, *rhs, true),
+            Expr::Or { lhs, rhs } => return self.logical(*lhs, *rhs, false),
+            Expr::Not { value } => {
+                let condition = self.translate_condition(*value)?;
+                return Ok(Translated::Condition(self.invert(condition)));
             }
-            Expr::Or { lhs, rhs } => {
-                let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
-                Ok(T::or(self.builder, lhs, rhs))
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => return self.conditional(*condition, *then, *otherwise),
+            Expr::Cast { value, to, span } => match T::value_type() == Some(to) {
+                true => return self.translate_value(*value),
+                false => return Err(TranslatorError::UnsupportedCast { to, span }),
+            },
+            Expr::Swizzle { span, .. } => return Err(TranslatorError::UnsupportedSwizzle { span }),
+            Expr::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+                span,
+            } => self.iterate(kind, variable, *start, *end, *body, span)?,
+            Expr::Function { .. } => unreachable!("definitions are inlined before translation"),
+        };
+        Ok(Translated::Number(number))
+    }
+
+    fn variable(&self, identifier: String, span: Span) -> Result<Vec<Variable>, TranslatorError> {
+        match self.variables.get(&identifier) {
+            Some(variable) => Ok(variable.clone()),
+            None => Err(TranslatorError::VariableNotFound {
+                name: identifier,
+                span,
+            }),
+        }
+    }
+
+    fn read(&mut self, variable: &[Variable]) -> T::Lowered {
+        let values: Vec<Value> = variable
+            .iter()
+            .map(|component| self.builder.use_var(*component))
+            .collect();
+        T::Lowered::from_values(&values)
+    }
+
+    fn write(&mut self, variable: &[Variable], value: T::Lowered) {
+        for (component, value) in variable.iter().zip(value.values()) {
+            self.builder.def_var(*component, value);
+        }
+    }
+
+    /// Adds parameters to `block` that take values of the same kind as `value`, to merge the values of different branches.
+    ///
+    /// Returns the parameters as a value and the arguments to pass for `value`.
+    fn merge_params(
+        &mut self,
+        block: Block,
+        value: Translated<T::Lowered>,
+    ) -> (Translated<T::Lowered>, Vec<Value>) {
+        match value {
+            Translated::Number(_) => {
+                let params: Vec<Value> = (0..T::Lowered::COMPONENTS)
+                    .map(|_| self.builder.append_block_param(block, T::cranelift_repr()))
+                    .collect();
+                let number = T::Lowered::from_values(&params);
+                (Translated::Number(number), value_args(value))
             }
-            Expr::Not { value } => {
-                let value = self.translate(*value)?;
-                Ok(T::not(self.builder, value))
+            Translated::Condition(condition) => {
+                let repr = self.builder.func.dfg.value_type(condition);
+                let param = self.builder.append_block_param(block, repr);
+                (Translated::Condition(param), vec![condition])
+            }
+        }
+    }
+
+    /// Converts `value` to the kind of `like` and returns its values.
+    fn merge_args(
+        &mut self,
+        like: Translated<T::Lowered>,
+        value: Translated<T::Lowered>,
+    ) -> Vec<Value> {
+        let value = match like {
+            Translated::Number(_) => Translated::Number(self.number(value)),
+            Translated::Condition(_) => Translated::Condition(self.condition(value)),
+        };
+        value_args(value)
+    }
+
+    /// Lowers `&&` (`and == true`) and `||` so that `rhs` is only evaluated if it can change the result.
+    fn logical(
+        &mut self,
+        lhs: Expr,
+        rhs: Expr,
+        and: bool,
+    ) -> Result<Translated<T::Lowered>, TranslatorError> {
+        let lhs_condition = self.translate_condition(lhs)?;
+
+        if self.masked() {
+            // Lanes can not branch individually, so rhs is evaluated for all lanes and masked instead.
+            let evaluate_rhs = match and {
+                true => lhs_condition,
+                false => self.invert(lhs_condition),
+            };
+            let rhs = self.translate_masked(evaluate_rhs, rhs)?;
+            let rhs_condition = self.condition(rhs);
+            let condition = match and {
+                true => self.builder.ins().band(lhs_condition, rhs_condition),
+                false => self.builder.ins().bor(lhs_condition, rhs_condition),
+            };
+            return Ok(Translated::Condition(condition));
+        }
+
+        let (merge_block, result) = short_circuit(self.builder, lhs_condition, and);
+        let rhs_condition = self.translate_condition(rhs)?;
+        merge(self.builder, merge_block, &[rhs_condition]);
+        Ok(Translated::Condition(result))
+    }
+
+    /// Lowers a conditional so that only the taken branch is evaluated.
+    ///
+    /// The result is a condition if both branches are one, so that conditionals of comparisons need no conversion.
+    fn conditional(
+        &mut self,
+        condition: Expr,
+        then: Expr,
+        otherwise: Expr,
+    ) -> Result<Translated<T::Lowered>, TranslatorError> {
+        let condition = self.translate_condition(condition)?;
+
+        if self.masked() {
+            let then = self.translate_masked(condition, then)?;
+            let inverted = self.invert(condition);
+            let otherwise = self.translate_masked(inverted, otherwise)?;
+            return Ok(match (then, otherwise) {
+                (Translated::Condition(then), Translated::Condition(otherwise))
+                    if self.vector_type.is_some() =>
+                {
+                    // Lane masks of both branches have the same type, so they are selected bitwise.
+                    let selected = self.builder.ins().bitselect(condition, then, otherwise);
+                    Translated::Condition(selected)
+                }
+                (then, otherwise) => {
+                    let (then, otherwise) = (self.number(then), self.number(otherwise));
+                    Translated::Number(T::select(self.builder, condition, then, otherwise))
+                }
+            });
+        }
+
+        let branches = Branches::new(self.builder, condition);
+        let merge_conditions = produces_condition(&then) && produces_condition(&otherwise);
+        let then = match self.translate_value(then)? {
+            then @ Translated::Condition(_) if !merge_conditions => {
+                Translated::Number(self.number(then))
+            }
+            then => then,
+        };
+        let (result, then_args) = self.merge_params(branches.merge, then);
+        branches.otherwise(self.builder, &then_args);
+
+        let otherwise = self.translate_value(otherwise)?;
+        let otherwise_args = self.merge_args(result, otherwise);
+        branches.merge(self.builder, &otherwise_args);
+        Ok(result)
+    }
+
+    /// Lowers a loop to a header block that takes the loop variable and the result so far as parameters.
+    ///
+    /// Vectors run until no lane continues, the loop variable and the result of finished lanes are kept by [`AnitaType::select`].
+    fn iterate(
+        &mut self,
+        kind: LoopKind,
+        identifier: String,
+        start: Expr,
+        end: Expr,
+        body: Expr,
+        span: Span,
+    ) -> Result<T::Lowered, TranslatorError> {
+        if !T::decided_conditions() {
+            return Err(TranslatorError::UnsupportedLoop { span });
+        }
+        let variable = self.variable(identifier, span)?;
+        let start = self.translate(start)?;
+        let end = self.translate(end)?;
+        let initial = self.translate(Expr::Const {
+            value: kind.initial(),
+        })?;
+        let one = self.translate(Expr::Const { value: 1.0 })?;
+
+        let repr = self.vector_type.unwrap_or(T::cranelift_repr());
+        let args = [start.values(), initial.values()].concat();
+        let (blocks, params) = LoopBlocks::enter(self.builder, &vec![repr; args.len()], &args);
+        let (value, result) = params.split_at(T::Lowered::COMPONENTS);
+        let (value, result) = (
+            T::Lowered::from_values(value),
+            T::Lowered::from_values(result),
+        );
+
+        let active = match kind.inclusive() {
+            true => T::leq(self.builder, value, end),
+            false => T::lt(self.builder, value, end),
+        };
+        let any_active = match self.vector_type {
+            Some(_) => self.builder.ins().vany_true(active),
+            None => active,
+        };
+        blocks.branch(self.builder, self.iterations, any_active);
+
+        let iteration = match self.vector_type {
+            Some(_) => {
+                let before = self.read(&variable);
+                let value = T::select(self.builder, active, value, before);
+                self.write(&variable, value);
+                let iteration = self.translate_masked(active, body)?;
+                let iteration = self.number(iteration);
+                let combined = Self::combine(self.builder, kind, result, iteration);
+                T::select(self.builder, active, combined, result)
+            }
+            None => {
+                self.write(&variable, value);
+                let iteration = self.translate(body)?;
+                Self::combine(self.builder, kind, result, iteration)
             }
+        };
+        let next = T::add(self.builder, value, one);
+        blocks.repeat(self.builder, &[next.values(), iteration.values()].concat());
+        Ok(result)
+    }
+
+    /// Combines the result of the previous iterations of a loop with the value of the current one.
+    fn combine(
+        builder: &mut FunctionBuilder,
+        kind: LoopKind,
+        result: T::Lowered,
+        iteration: T::Lowered,
+    ) -> T::Lowered {
+        match kind {
+            LoopKind::For => iteration,
+            LoopKind::Sum => T::add(builder, result, iteration),
+            LoopKind::Product => T::mul(builder, result, iteration),
+        }
+    }
+
+    /// Whether branches are evaluated and merged with [`AnitaType::select`] instead of branching on conditions,
+    /// for vectors and for types without [`AnitaType::decided_conditions`].
+    fn masked(&self) -> bool {
+        self.vector_type.is_some() || !T::decided_conditions()
+    }
+
+    /// Inverts a condition as returned by [`AnitaType::truthy`].
+    fn invert(&mut self, condition: Value) -> Value {
+        match self.vector_type {
+            // Lane masks have all bits set or cleared.
+            Some(_) => self.builder.ins().bnot(condition),
+            None => T::invert(self.builder, condition),
+        }
+    }
+
+    /// Translates `expr` for all lanes of a vector but only keeps its assignments in the lanes selected by `mask`.
+    ///
+    /// Without [`AnitaType::decided_conditions`] the assignments are merged with their previous values by [`AnitaType::select`].
+    fn translate_masked(
+        &mut self,
+        mask: Value,
+        expr: Expr,
+    ) -> Result<Translated<T::Lowered>, TranslatorError> {
+        let assigned: Vec<Vec<Variable>> = expr
+            .variables_write()
+            .iter()
+            .unique()
+            .filter_map(|identifier| self.variables.get(identifier).cloned())
+            .collect();
+        let before: Vec<T::Lowered> = assigned
+            .iter()
+            .map(|variable| self.read(variable))
+            .collect();
+
+        let value = self.translate_value(expr)?;
+
+        for (variable, before) in assigned.into_iter().zip(before) {
+            let after = self.read(&variable);
+            let merged = T::select(self.builder, mask, after, before);
+            self.write(&variable, merged);
         }
+        Ok(value)
     }
 
-    fn function_call(
+    /// Lowers a call of a declared function through [`AnitaType::call`].
+    fn call(
         &mut self,
+        func_ref: FuncRef,
         identifier: &str,
+        span: Span,
+        args: &[T::Lowered],
+    ) -> Result<T::Lowered, TranslatorError> {
+        T::call(
+            &mut TranslatorCall {
+                translator: self,
+                func_ref,
+                identifier,
+                span,
+            },
+            args,
+        )
+    }
+
+    fn function_call(&mut self, func_ref: FuncRef, params: &[Value]) -> Value {
+        let Some(vector_type) = self.vector_type else {
+            let call = self.builder.ins().call(func_ref, params);
+            return self.builder.inst_results(call)[0];
+        };
+
+        // Functions only exist in scalar form, so vectors are split into lanes and reassembled.
+        let mut result = None;
+        for lane in 0..vector_type.lane_count() as u8 {
+            let lane_params: Vec<Value> = params
+                .iter()
+                .map(|param| self.builder.ins().extractlane(*param, lane))
+                .collect();
+            let call = self.builder.ins().call(func_ref, &lane_params);
+            let lane_result = self.builder.inst_results(call)[0];
+            result = Some(match result {
+                None => self.builder.ins().splat(vector_type, lane_result),
+                Some(vector) => self.builder.ins().insertlane(vector, lane_result, lane),
+            });
+        }
+        result.expect("vector types have at least one lane")
+    }
+
+    /// Calls a function that writes `results` components to a stack slot passed as its last argument.
+    fn function_call_with_output(
+        &mut self,
+        func_ref: FuncRef,
         params: &[Value],
-    ) -> Result<Value, TranslatorError> {
-        let (func_ref, _) = self.declare_function(identifier)?;
-        let call = self.builder.ins().call(func_ref, params);
-        Ok(self.builder.inst_results(call)[0])
+        results: usize,
+    ) -> Vec<Value> {
+        // Only numbers with a single component are vectorized, and those return their result directly.
+        assert!(
+            self.vector_type.is_none(),
+            "calls with an output pointer can not be vectorized"
+        );
+        let repr = T::cranelift_repr();
+        let slot = self.builder.create_sized_stack_slot(StackSlotData::new(
+            StackSlotKind::ExplicitSlot,
+            repr.bytes() * results as u32,
+            repr.bytes().ilog2() as u8,
+        ));
+        let pointer_type = self.module.target_config().pointer_type();
+        let output = self.builder.ins().stack_addr(pointer_type, slot, 0);
+        let args: Vec<Value> = params.iter().copied().chain([output]).collect();
+        self.builder.ins().call(func_ref, &args);
+        (0..results as i32)
+            .map(|result| {
+                let offset = result * repr.bytes() as i32;
+                self.builder.ins().stack_load(repr, slot, offset)
+            })
+            .collect()
     }
 
-    fn declare_function(&mut self, identifier: &str) -> Result<(FuncRef, usize), TranslatorError> {
+    fn declare_function(
+        &mut self,
+        identifier: &str,
+        span: Span,
+    ) -> Result<(FuncRef, usize), TranslatorError> {
         let Some(func) = self.functions.get(identifier) else {
             let Some(signature) =
                 F::function_signature(identifier, self.module.isa().default_call_conv())
             else {
-                return Err(TranslatorError::FunctionNotFound(identifier.to_owned()));
+                return Err(TranslatorError::FunctionNotFound {
+                    name: identifier.to_owned(),
+                    span,
+                });
             };
+            let pointer_type = self.module.target_config().pointer_type();
+            let arity = T::function_arity(&signature);
+            let expected = T::function_signature(arity, signature.call_conv, pointer_type);
+            if signature != expected {
+                return Err(TranslatorError::SignatureMismatch {
+                    name: identifier.to_owned(),
+                    expected: Box::new(expected),
+                    found: Box::new(signature),
+                    span,
+                });
+            }
             let func_id = self.module.declare_function(
                 identifier,
                 cranelift_module::Linkage::Import,
@@ -155,7 +825,7 @@ impl<T: AnitaType, F: FunctionManager> ExprTranslator<'_, '_, T, F> {
             )?;
             let func = (
                 self.module.declare_func_in_func(func_id, self.builder.func),
-                signature.params.len(),
+                arity,
             );
             self.functions.insert(identifier.into(), func);
             return Ok(func);
@@ -163,3 +833,255 @@ impl<T: AnitaType, F: FunctionManager> ExprTranslator<'_, '_, T, F> {
         Ok(func.to_owned())
     }
 }
+
+/// Gives [`AnitaType::call`] access to the function it lowers a call of.
+struct TranslatorCall<'t, 'a, 'b, T: AnitaType, F: FunctionManager> {
+    translator: &'t mut ExprTranslator<'a, 'b, T, F>,
+    func_ref: FuncRef,
+    identifier: &'t str,
+    span: Span,
+}
+
+impl<'a, T: AnitaType, F: FunctionManager> FunctionCall<'a> for TranslatorCall<'_, 'a, '_, T, F> {
+    fn builder(&mut self) -> &mut FunctionBuilder<'a> {
+        self.translator.builder
+    }
+
+    fn call(&mut self, args: &[Value]) -> Value {
+        self.translator.function_call(self.func_ref, args)
+    }
+
+    fn call_with_output(&mut self, args: &[Value], results: usize) -> Vec<Value> {
+        self.translator
+            .function_call_with_output(self.func_ref, args, results)
+    }
+
+    fn partial<S: AnitaType<Lowered = Value>>(
+        &mut self,
+        index: usize,
+        args: &[Value],
+    ) -> Result<Value, TranslatorError> {
+        let derivatives = match self.identifier {
+            "inbuilt_pow" => INBUILT_POW_DERIVATIVES,
+            identifier => F::function_derivatives(identifier).ok_or_else(|| {
+                TranslatorError::MissingDerivative {
+                    name: identifier.to_owned(),
+                    span: self.span,
+                }
+            })?,
+        };
+        let invalid = || TranslatorError::InvalidDerivative {
+            name: self.identifier.to_owned(),
+            span: self.span,
+        };
+        let partial = derivatives
+            .partials
+            .get(index)
+            .filter(|_| derivatives.parameters.len() == args.len())
+            .and_then(|partial| parser::expression(partial).ok())
+            .filter(|partial| {
+                partial
+                    .uninitialized_reads(derivatives.parameters)
+                    .is_empty()
+            })
+            .and_then(|partial| definitions::inline(partial).ok())
+            .ok_or_else(invalid)?;
+        let partial = optimize::<S>(partial);
+
+        // The partial is lowered in place with its parameters bound to the arguments.
+        let translator = &mut *self.translator;
+        let mut variable_index = translator.variable_index;
+        let repr = translator.vector_type.unwrap_or(S::cranelift_repr());
+        let variables = JIT::<S, F>::declare_variables(
+            translator.builder,
+            &partial,
+            derivatives.parameters,
+            args,
+            repr,
+            &mut variable_index,
+        );
+        let mut partial_translator = ExprTranslator::<S, F> {
+            builder: &mut *translator.builder,
+            variables,
+            functions: mem::take(&mut translator.functions),
+            module: &mut *translator.module,
+            vector_type: translator.vector_type,
+            variable_index,
+            iterations: translator.iterations,
+            _function_manager: std::marker::PhantomData,
+            _type: std::marker::PhantomData,
+        };
+        let value = partial_translator.translate(partial);
+        translator.functions = partial_translator.functions;
+        translator.variable_index = partial_translator.variable_index;
+        value.map_err(|error| match error {
+            TranslatorError::ModuleError(_) => error,
+            _ => invalid(),
+        })
+    }
+}
+
+/// The Cranelift values of a translated value, one per component of a number.
+fn value_args<L: Lowered>(value: Translated<L>) -> Vec<Value> {
+    match value {
+        Translated::Number(number) => number.values(),
+        Translated::Condition(condition) => vec![condition],
+    }
+}
+
+/// Declares the iterations left to the loops of an evaluation and sets them to `max_iterations`, see [`JIT::set_max_iterations`].
+///
+/// All loops of the evaluation take their iterations from it, so nesting loops does not multiply the limit.
+pub(super) fn declare_iterations(
+    builder: &mut FunctionBuilder,
+    index: &mut usize,
+    max_iterations: u64,
+) -> Variable {
+    let iterations = Variable::new(*index);
+    *index += 1;
+    builder.declare_var(iterations, I64);
+    // Limits above `i64::MAX` wrap to the same unsigned value.
+    let max_iterations = builder.ins().iconst(I64, max_iterations as i64);
+    builder.def_var(iterations, max_iterations);
+    iterations
+}
+
+/// Jumps to `block` with `args` and continues in it, no other branch may jump to it afterwards.
+pub(super) fn merge(builder: &mut FunctionBuilder, block: Block, args: &[Value]) {
+    builder.ins().jump(block, args);
+    builder.seal_block(block);
+    builder.switch_to_block(block);
+}
+
+/// Lowers the branch of `&&` (`and == true`) or `||` on the condition `lhs` and leaves the builder in the block evaluating `rhs`.
+///
+/// Returns the block to [`merge`] into with the condition of `rhs` and its parameter, the result.
+/// The result is `lhs` itself if it decides it.
+pub(super) fn short_circuit(
+    builder: &mut FunctionBuilder,
+    lhs: Value,
+    and: bool,
+) -> (Block, Value) {
+    let rhs_block = builder.create_block();
+    let merge_block = builder.create_block();
+    let repr = builder.func.dfg.value_type(lhs);
+    let result = builder.append_block_param(merge_block, repr);
+    match and {
+        true => builder.ins().brif(lhs, rhs_block, &[], merge_block, &[lhs]),
+        false => builder.ins().brif(lhs, merge_block, &[lhs], rhs_block, &[]),
+    };
+    builder.seal_block(rhs_block);
+    builder.switch_to_block(rhs_block);
+    (merge_block, result)
+}
+
+/// Blocks of a conditional that only evaluates the taken branch.
+pub(super) struct Branches {
+    otherwise: Block,
+    /// Takes the result of the branches as parameters, which are added while lowering `then`.
+    pub(super) merge: Block,
+}
+
+impl Branches {
+    /// Branches on `condition` and leaves the builder in the block of `then`.
+    pub(super) fn new(builder: &mut FunctionBuilder, condition: Value) -> Self {
+        let then = builder.create_block();
+        let otherwise = builder.create_block();
+        let merge = builder.create_block();
+        builder.ins().brif(condition, then, &[], otherwise, &[]);
+        builder.seal_block(then);
+        builder.seal_block(otherwise);
+        builder.switch_to_block(then);
+        Self { otherwise, merge }
+    }
+
+    /// Jumps to the merge block with the result of `then` and continues in the block of `otherwise`.
+    pub(super) fn otherwise(&self, builder: &mut FunctionBuilder, then: &[Value]) {
+        builder.ins().jump(self.merge, then);
+        builder.switch_to_block(self.otherwise);
+    }
+
+    /// Jumps to the merge block with the result of `otherwise` and continues in it.
+    pub(super) fn merge(self, builder: &mut FunctionBuilder, otherwise: &[Value]) {
+        merge(builder, self.merge, otherwise);
+    }
+}
+
+/// Blocks of a loop whose header takes the loop variable and the result so far as parameters.
+pub(super) struct LoopBlocks {
+    header: Block,
+    body: Block,
+    exit: Block,
+}
+
+impl LoopBlocks {
+    /// Jumps to a new header with parameters of the types `params` for the `args` and leaves the builder in it.
+    ///
+    /// Returns the parameters of the header.
+    pub(super) fn enter(
+        builder: &mut FunctionBuilder,
+        params: &[Type],
+        args: &[Value],
+    ) -> (Self, Vec<Value>) {
+        let blocks = Self {
+            header: builder.create_block(),
+            body: builder.create_block(),
+            exit: builder.create_block(),
+        };
+        let params = params
+            .iter()
+            .map(|repr| builder.append_block_param(blocks.header, *repr))
+            .collect();
+        builder.ins().jump(blocks.header, args);
+        builder.switch_to_block(blocks.header);
+        (blocks, params)
+    }
+
+    /// Branches from the header to the body while `active` is set and iterations are left, and takes one of them.
+    ///
+    /// Leaves the builder in the body.
+    pub(super) fn branch(
+        &self,
+        builder: &mut FunctionBuilder,
+        iterations: Variable,
+        active: Value,
+    ) {
+        let left = builder.use_var(iterations);
+        let any_left = builder.ins().icmp_imm(IntCC::NotEqual, left, 0);
+        let running = builder.ins().band(active, any_left);
+        builder.ins().brif(running, self.body, &[], self.exit, &[]);
+        builder.seal_block(self.body);
+        builder.seal_block(self.exit);
+
+        builder.switch_to_block(self.body);
+        let left = builder.ins().iadd_imm(left, -1);
+        builder.def_var(iterations, left);
+    }
+
+    /// Jumps back to the header with the `args` of the next iteration and continues after the loop.
+    pub(super) fn repeat(self, builder: &mut FunctionBuilder, args: &[Value]) {
+        builder.ins().jump(self.header, args);
+        builder.seal_block(self.header);
+        builder.switch_to_block(self.exit);
+    }
+}
+
+/// Whether `expr` is translated to a condition rather than a number.
+fn produces_condition(expr: &Expr) -> bool {
+    match expr {
+        Expr::Eq { .. }
+        | Expr::Neq { .. }
+        | Expr::Gt { .. }
+        | Expr::Lt { .. }
+        | Expr::Geq { .. }
+        | Expr::Leq { .. }
+        | Expr::And { .. }
+        | Expr::Or { .. }
+        | Expr::Not { .. } => true,
+        Expr::If {
+            then, otherwise, ..
+        } => produces_condition(then) && produces_condition(otherwise),
+        Expr::Chain { ret: value, .. } | Expr::Cast { value, .. } => produces_condition(value),
+        _ => false,
+    }
+}
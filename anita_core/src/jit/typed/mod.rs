@@ -0,0 +1,744 @@
+//! Type checking of expressions with typed parameters, see [`JIT::compile_typed`](super::JIT::compile_typed).
+//!
+//! Every value has a [`ValueType`]. Parameters have the declared types and a variable has the type of its first assignment in evaluation order.
+//! Operands of different types are converted to a common type before they are combined:
+//! floats win over integers and the wider float over the narrower one,
+//! integers of the same signedness become the wider one and mixed signedness becomes `i64`,
+//! except for `u64` which has no common type with signed integers and needs an explicit cast.
+//! Arithmetic on `bool` converts it to `0` or `1`, conditions and the operands of `!`, `&&` and `||` are converted to `bool` by comparing with `0`.
+//!
+//! Literals have no type of their own. They take the type of the value they are combined with, assigned to or returned as,
+//! but become `f64` if they have a fraction where an integer is expected. Literals that are cast with `as` are `f64`.
+//! Comparisons and logical operators return `bool`.
+//!
+//! Vectors (`vec2`, `vec3` and `vec4`) have `f32` components and are built with constructors like `vec3(x, y, z)` or `vec4(v.xy, 0, 1)`.
+//! Scalars combined with a vector are converted to `f32` and used for every component, vectors of different lengths can not be combined.
+//! Vectors can not be converted to any other type and are only compared with `==` and `!=`, which compare every component.
+//! The inbuilt functions `dot`, `cross`, `length` and `normalize` and the constructors are resolved before the functions of the [`FunctionManager`].
+//!
+//! Function arguments are converted to the parameter types of the [`FunctionManager`].
+//! Its signatures only have Cranelift types, so functions on unsigned integers are treated as if they took signed ones.
+
+use std::{collections::HashMap, marker::PhantomData};
+
+use cranelift::prelude::{isa::CallConv, types, AbiParam, Signature, Type};
+
+use super::{
+    frontend::{optimizer::Operation, Expr, LoopKind, Span},
+    types::ValueType,
+    TranslatorError,
+};
+use crate::function_manager::FunctionManager;
+
+pub(super) mod optimizer;
+pub(super) mod translator;
+
+/// Calls `$body` with `$type` as the [`AnitaType`](super::types::AnitaType) of a numeric [`ValueType`].
+macro_rules! numeric_type {
+    ($value_type:expr, $type:ident => $body:expr) => {
+        match $value_type {
+            ValueType::I32 => {
+                type $type = i32;
+                $body
+            }
+            ValueType::I64 => {
+                type $type = i64;
+                $body
+            }
+            ValueType::U32 => {
+                type $type = u32;
+                $body
+            }
+            ValueType::U64 => {
+                type $type = u64;
+                $body
+            }
+            ValueType::F32 => {
+                type $type = f32;
+                $body
+            }
+            ValueType::F64 => {
+                type $type = f64;
+                $body
+            }
+            ValueType::Bool => unreachable!("bool is not numeric"),
+            ValueType::Vec2 | ValueType::Vec3 | ValueType::Vec4 => {
+                unreachable!("vectors are lowered per component")
+            }
+        }
+    };
+}
+pub(super) use numeric_type;
+
+/// An expression in which every node has a type and every conversion is explicit.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Typed {
+    pub(super) node: Node,
+    pub(super) ty: ValueType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Node {
+    Variable(String),
+    Const(f64),
+    /// An integer literal that an `f64` can not hold exactly, see [`Expr::Integer`].
+    Integer(i128),
+    Chain(Box<Typed>, Box<Typed>),
+    Call {
+        identifier: String,
+        args: Vec<Typed>,
+    },
+    /// `+`, `-`, `*`, `/`, `%` or `^` on two operands of the type of the node.
+    Arithmetic {
+        operation: Operation,
+        lhs: Box<Typed>,
+        rhs: Box<Typed>,
+    },
+    Neg(Box<Typed>),
+    /// A comparison of two operands of the same type, the node is a `bool`.
+    Comparison {
+        operation: Operation,
+        lhs: Box<Typed>,
+        rhs: Box<Typed>,
+    },
+    /// `&&` (`and == true`) or `||` on two `bool` operands.
+    Logical {
+        and: bool,
+        lhs: Box<Typed>,
+        rhs: Box<Typed>,
+    },
+    Not(Box<Typed>),
+    Assign {
+        identifier: String,
+        value: Box<Typed>,
+    },
+    If {
+        condition: Box<Typed>,
+        then: Box<Typed>,
+        otherwise: Box<Typed>,
+    },
+    /// Converts the value to the type of the node, scalars are converted to vectors by using them for every component.
+    Cast(Box<Typed>),
+    /// A vector of the components of the `f32` and vector arguments, in order.
+    Construct(Vec<Typed>),
+    /// The components of a vector at the lanes, a single one is an `f32`.
+    Swizzle {
+        value: Box<Typed>,
+        lanes: Vec<u8>,
+    },
+    /// An inbuilt function on vectors of the same type.
+    VectorCall {
+        function: VectorFunction,
+        args: Vec<Typed>,
+    },
+    /// A loop whose variable, `start` and `end` have the same numeric type, `body` has the type of the node.
+    Loop {
+        kind: LoopKind,
+        variable: String,
+        start: Box<Typed>,
+        end: Box<Typed>,
+        body: Box<Typed>,
+    },
+}
+
+/// Inbuilt functions on vectors, see [`Node::VectorCall`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum VectorFunction {
+    Dot,
+    Cross,
+    Length,
+    Normalize,
+}
+
+impl VectorFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dot" => Some(VectorFunction::Dot),
+            "cross" => Some(VectorFunction::Cross),
+            "length" => Some(VectorFunction::Length),
+            "normalize" => Some(VectorFunction::Normalize),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            VectorFunction::Dot | VectorFunction::Cross => 2,
+            VectorFunction::Length | VectorFunction::Normalize => 1,
+        }
+    }
+}
+
+impl Typed {
+    fn new(node: Node, ty: ValueType) -> Self {
+        Self { node, ty }
+    }
+
+    /// Returns `true` if evaluating the expression has no side effects, like [`Expr::is_pure`].
+    fn is_pure(&self) -> bool {
+        match &self.node {
+            Node::Variable(_) | Node::Const(_) | Node::Integer(_) => true,
+            Node::Call { .. } | Node::Assign { .. } | Node::Loop { .. } => false,
+            Node::Neg(value)
+            | Node::Not(value)
+            | Node::Cast(value)
+            | Node::Swizzle { value, .. } => value.is_pure(),
+            Node::Chain(lhs, rhs)
+            | Node::Arithmetic { lhs, rhs, .. }
+            | Node::Comparison { lhs, rhs, .. }
+            | Node::Logical { lhs, rhs, .. } => lhs.is_pure() && rhs.is_pure(),
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => condition.is_pure() && then.is_pure() && otherwise.is_pure(),
+            Node::Construct(args) | Node::VectorCall { args, .. } => {
+                args.iter().all(Typed::is_pure)
+            }
+        }
+    }
+
+    /// Converts the value to `to`, if it does not have that type already.
+    ///
+    /// Vectors can not be converted, `span` is the location reported for them.
+    fn cast(self, to: ValueType, span: Option<Span>) -> Result<Self, TranslatorError> {
+        match (self.ty == to, self.ty.is_vector()) {
+            (true, _) => Ok(self),
+            (false, true) => Err(TranslatorError::InvalidConversion {
+                from: self.ty,
+                to,
+                span,
+            }),
+            (false, false) => Ok(Typed::new(Node::Cast(Box::new(self)), to)),
+        }
+    }
+}
+
+/// Assigns types to `root` and inserts the conversions between them.
+///
+/// Returns the typed expression, converted to `returns`, and the types of all variables including the parameters.
+/// Uninitialized variables have to be reported beforehand, see [`JIT::check_variables`](super::JIT::check_variables).
+pub(super) fn check<F: FunctionManager>(
+    root: Expr,
+    parameters: &[(&str, ValueType)],
+    returns: ValueType,
+    call_conv: CallConv,
+) -> Result<(Typed, HashMap<String, ValueType>), TranslatorError> {
+    let mut checker = Checker::<F> {
+        variables: parameters
+            .iter()
+            .map(|(name, ty)| (name.to_string(), *ty))
+            .collect(),
+        call_conv,
+        _function_manager: PhantomData,
+    };
+    let span = root.extent();
+    let typed = checker.check(root, returns)?.cast(returns, span)?;
+    Ok((typed, checker.variables))
+}
+
+/// The type of a function parameter or result of the [`FunctionManager`].
+pub(super) fn value_type_of(repr: Type) -> Option<ValueType> {
+    match repr {
+        types::I8 => Some(ValueType::Bool),
+        types::I32 => Some(ValueType::I32),
+        types::I64 => Some(ValueType::I64),
+        types::F32 => Some(ValueType::F32),
+        types::F64 => Some(ValueType::F64),
+        _ => None,
+    }
+}
+
+struct Checker<F: FunctionManager> {
+    variables: HashMap<String, ValueType>,
+    call_conv: CallConv,
+    _function_manager: PhantomData<F>,
+}
+
+impl<F: FunctionManager> Checker<F> {
+    /// Types `expr`, literals without a typed operand to combine with get the type `hint`.
+    fn check(&mut self, expr: Expr, hint: ValueType) -> Result<Typed, TranslatorError> {
+        Ok(match expr {
+            Expr::VariableRead { identifier, span } => {
+                let Some(ty) = self.variables.get(&identifier).copied() else {
+                    return Err(TranslatorError::VariableNotFound {
+                        name: identifier,
+                        span,
+                    });
+                };
+                Typed::new(Node::Variable(identifier), ty)
+            }
+            Expr::Const { value } => Typed::new(Node::Const(value), hint),
+            Expr::Integer { value } => Typed::new(Node::Integer(value), hint),
+            Expr::Imaginary { span, .. } => {
+                return Err(TranslatorError::UnsupportedImaginary { span })
+            }
+            Expr::Chain { side, ret } => {
+                let side = self.check(*side, hint)?;
+                let ret = self.check(*ret, hint)?;
+                let ty = ret.ty;
+                Typed::new(Node::Chain(Box::new(side), Box::new(ret)), ty)
+            }
+            Expr::Call {
+                identifier,
+                args,
+                span,
+            } => self.call(identifier, args, span)?,
+            Expr::Add { lhs, rhs } => self.arithmetic(Operation::Add, *lhs, *rhs, hint)?,
+            Expr::Sub { lhs, rhs } => self.arithmetic(Operation::Sub, *lhs, *rhs, hint)?,
+            Expr::Mul { lhs, rhs } => self.arithmetic(Operation::Mul, *lhs, *rhs, hint)?,
+            Expr::Div { lhs, rhs } => self.arithmetic(Operation::Div, *lhs, *rhs, hint)?,
+            Expr::Mod { lhs, rhs } => self.arithmetic(Operation::Mod, *lhs, *rhs, hint)?,
+            Expr::Exp { lhs, rhs } => self.arithmetic(Operation::Exp, *lhs, *rhs, hint)?,
+            Expr::Neg { value } => {
+                let span = value.extent();
+                let value = self.check(*value, numeric(hint))?;
+                let ty = match value.ty {
+                    ValueType::Bool => numeric(hint),
+                    ty => ty,
+                };
+                Typed::new(Node::Neg(Box::new(value.cast(ty, span)?)), ty)
+            }
+            Expr::Assign {
+                identifier, value, ..
+            } => {
+                let value = match self.variables.get(&identifier).copied() {
+                    Some(ty) => {
+                        let span = value.extent();
+                        let value = self.check(*value, ty)?;
+                        value.cast(ty, span)?
+                    }
+                    None => {
+                        let value = self.check(*value, hint)?;
+                        self.variables.insert(identifier.clone(), value.ty);
+                        value
+                    }
+                };
+                let ty = value.ty;
+                let value = Box::new(value);
+                Typed::new(Node::Assign { identifier, value }, ty)
+            }
+            Expr::Eq { lhs, rhs } => self.comparison(Operation::Eq, *lhs, *rhs)?,
+            Expr::Neq { lhs, rhs } => self.comparison(Operation::Neq, *lhs, *rhs)?,
+            Expr::Gt { lhs, rhs } => self.comparison(Operation::Gt, *lhs, *rhs)?,
+            Expr::Lt { lhs, rhs } => self.comparison(Operation::Lt, *lhs, *rhs)?,
+            Expr::Geq { lhs, rhs } => self.comparison(Operation::Geq, *lhs, *rhs)?,
+            Expr::Leq { lhs, rhs } => self.comparison(Operation::Leq, *lhs, *rhs)?,
+            Expr::And { lhs, rhs } => self.logical(true, *lhs, *rhs)?,
+            Expr::Or { lhs, rhs } => self.logical(false, *lhs, *rhs)?,
+            Expr::Not { value } => {
+                let value = self.condition(*value)?;
+                Typed::new(Node::Not(Box::new(value)), ValueType::Bool)
+            }
+            Expr::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let span = extent(&then, &otherwise);
+                let condition = self.condition(*condition)?;
+                let (then, otherwise) = self.operands(*then, *otherwise, hint)?;
+                let ty = common(then.ty, otherwise.ty, span)?;
+                let node = Node::If {
+                    condition: Box::new(condition),
+                    then: Box::new(then.cast(ty, span)?),
+                    otherwise: Box::new(otherwise.cast(ty, span)?),
+                };
+                Typed::new(node, ty)
+            }
+            Expr::Cast { value, to, span } => {
+                self.check(*value, ValueType::F64)?.cast(to, Some(span))?
+            }
+            Expr::Swizzle {
+                value,
+                components,
+                span,
+            } => {
+                let value = self.check(*value, ValueType::F32)?;
+                let lanes: Vec<u8> = components
+                    .bytes()
+                    .map(|component| match component {
+                        b'x' => 0,
+                        b'y' => 1,
+                        b'z' => 2,
+                        _ => 3,
+                    })
+                    .collect();
+                let components_of_value = value.ty.components();
+                if !value.ty.is_vector()
+                    || lanes
+                        .iter()
+                        .any(|lane| usize::from(*lane) >= components_of_value)
+                {
+                    return Err(TranslatorError::InvalidSwizzle {
+                        components,
+                        ty: value.ty,
+                        span,
+                    });
+                }
+                let ty = ValueType::vector(lanes.len()).expect("swizzles have 1 to 4 components");
+                let value = Box::new(value);
+                Typed::new(Node::Swizzle { value, lanes }, ty)
+            }
+            Expr::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+                span,
+            } => self.iterate(kind, variable, *start, *end, *body, span, hint)?,
+            Expr::Function { .. } => unreachable!("definitions are inlined before type checking"),
+        })
+    }
+
+    /// Types a loop, whose variable keeps its type if it was assigned before and otherwise takes the common type of the bounds.
+    ///
+    /// Sums and products of booleans are done in `hint`, or `f64` if that is a `bool` too.
+    #[allow(clippy::too_many_arguments)]
+    fn iterate(
+        &mut self,
+        kind: LoopKind,
+        variable: String,
+        start: Expr,
+        end: Expr,
+        body: Expr,
+        span: Span,
+        hint: ValueType,
+    ) -> Result<Typed, TranslatorError> {
+        let counter = self.variables.get(&variable).copied();
+        let bounds = extent(&start, &end);
+        let (start, end) = self.operands(start, end, counter.unwrap_or(ValueType::F64))?;
+        let counter = match counter {
+            Some(ty) => ty,
+            None => match common(start.ty, end.ty, bounds)? {
+                ValueType::Bool => ValueType::F64,
+                ty => ty,
+            },
+        };
+        if counter == ValueType::Bool || counter.is_vector() {
+            return Err(TranslatorError::InvalidLoopVariable {
+                name: variable,
+                ty: counter,
+                span,
+            });
+        }
+        self.variables.insert(variable.clone(), counter);
+        let body_span = body.extent();
+        let body = self.check(body, hint)?;
+        let ty = match (kind, body.ty) {
+            (LoopKind::Sum | LoopKind::Product, ValueType::Bool) => numeric(hint),
+            (_, ty) => ty,
+        };
+        let node = Node::Loop {
+            kind,
+            variable,
+            start: Box::new(start.cast(counter, bounds)?),
+            end: Box::new(end.cast(counter, bounds)?),
+            body: Box::new(body.cast(ty, body_span)?),
+        };
+        Ok(Typed::new(node, ty))
+    }
+
+    fn arithmetic(
+        &mut self,
+        operation: Operation,
+        lhs: Expr,
+        rhs: Expr,
+        hint: ValueType,
+    ) -> Result<Typed, TranslatorError> {
+        let span = extent(&lhs, &rhs);
+        let (lhs, rhs) = self.operands(lhs, rhs, numeric(hint))?;
+        let ty = match common(lhs.ty, rhs.ty, span)? {
+            ValueType::Bool => numeric(hint),
+            ty => ty,
+        };
+        let node = Node::Arithmetic {
+            operation,
+            lhs: Box::new(lhs.cast(ty, span)?),
+            rhs: Box::new(rhs.cast(ty, span)?),
+        };
+        Ok(Typed::new(node, ty))
+    }
+
+    fn comparison(
+        &mut self,
+        operation: Operation,
+        lhs: Expr,
+        rhs: Expr,
+    ) -> Result<Typed, TranslatorError> {
+        let span = extent(&lhs, &rhs);
+        let (lhs, rhs) = self.operands(lhs, rhs, ValueType::F64)?;
+        let ty = common(lhs.ty, rhs.ty, span)?;
+        if ty.is_vector() && !matches!(operation, Operation::Eq | Operation::Neq) {
+            return Err(TranslatorError::VectorComparison { ty, span });
+        }
+        let node = Node::Comparison {
+            operation,
+            lhs: Box::new(lhs.cast(ty, span)?),
+            rhs: Box::new(rhs.cast(ty, span)?),
+        };
+        Ok(Typed::new(node, ValueType::Bool))
+    }
+
+    fn logical(&mut self, and: bool, lhs: Expr, rhs: Expr) -> Result<Typed, TranslatorError> {
+        let lhs = Box::new(self.condition(lhs)?);
+        let rhs = Box::new(self.condition(rhs)?);
+        Ok(Typed::new(Node::Logical { and, lhs, rhs }, ValueType::Bool))
+    }
+
+    fn condition(&mut self, expr: Expr) -> Result<Typed, TranslatorError> {
+        let span = expr.extent();
+        self.check(expr, ValueType::Bool)?
+            .cast(ValueType::Bool, span)
+    }
+
+    /// Types two operands that are combined, an operand without a type of its own takes the type of the other one.
+    fn operands(
+        &mut self,
+        lhs: Expr,
+        rhs: Expr,
+        hint: ValueType,
+    ) -> Result<(Typed, Typed), TranslatorError> {
+        // Untyped operands have no side effects, so they can be checked out of evaluation order.
+        match (is_untyped(&lhs), is_untyped(&rhs)) {
+            (false, true) => {
+                let lhs = self.check(lhs, hint)?;
+                let rhs_hint = literal_type(&rhs, lhs.ty, hint);
+                Ok((lhs, self.check(rhs, rhs_hint)?))
+            }
+            (true, false) => {
+                let rhs = self.check(rhs, hint)?;
+                let lhs_hint = literal_type(&lhs, rhs.ty, hint);
+                Ok((self.check(lhs, lhs_hint)?, rhs))
+            }
+            _ => Ok((self.check(lhs, hint)?, self.check(rhs, hint)?)),
+        }
+    }
+
+    fn call(
+        &mut self,
+        identifier: String,
+        args: Vec<Expr>,
+        span: Span,
+    ) -> Result<Typed, TranslatorError> {
+        if let Some(ty) = ValueType::from_name(&identifier).filter(|ty| ty.is_vector()) {
+            return self.construct(identifier, ty, args, span);
+        }
+        if let Some(function) = VectorFunction::from_name(&identifier) {
+            return self.vector_call(function, identifier, args, span);
+        }
+        let Some(signature) = F::function_signature(&identifier, self.call_conv) else {
+            return Err(TranslatorError::FunctionNotFound {
+                name: identifier,
+                span,
+            });
+        };
+        if args.len() != signature.params.len() {
+            return Err(TranslatorError::ArityMismatch {
+                name: identifier,
+                expected: signature.params.len(),
+                found: args.len(),
+                span,
+            });
+        }
+        let param_types: Option<Vec<ValueType>> = signature
+            .params
+            .iter()
+            .map(|param| value_type_of(param.value_type))
+            .collect();
+        let return_type = match signature.returns.as_slice() {
+            [result] => value_type_of(result.value_type),
+            _ => None,
+        };
+        let (Some(param_types), Some(return_type)) = (param_types, return_type) else {
+            // Only the types of `ValueType` can be passed to functions.
+            let supported = |repr: Type| value_type_of(repr).unwrap_or(ValueType::F64);
+            let mut expected = Signature::new(signature.call_conv);
+            expected.params = signature
+                .params
+                .iter()
+                .map(|param| AbiParam::new(supported(param.value_type).cranelift_repr()))
+                .collect();
+            expected.returns = vec![AbiParam::new(match signature.returns.first() {
+                Some(result) => supported(result.value_type).cranelift_repr(),
+                None => types::F64,
+            })];
+            return Err(TranslatorError::SignatureMismatch {
+                name: identifier,
+                expected: Box::new(expected),
+                found: Box::new(signature),
+                span,
+            });
+        };
+        let args = args
+            .into_iter()
+            .zip(param_types)
+            .map(|(arg, ty)| self.check(arg, ty)?.cast(ty, Some(span)))
+            .collect::<Result<Vec<Typed>, TranslatorError>>()?;
+        Ok(Typed::new(Node::Call { identifier, args }, return_type))
+    }
+
+    /// Types a constructor like `vec3(x, y, z)`, whose arguments are scalars and vectors with as many components as the vector in total.
+    ///
+    /// A single scalar is used for every component.
+    fn construct(
+        &mut self,
+        identifier: String,
+        ty: ValueType,
+        args: Vec<Expr>,
+        span: Span,
+    ) -> Result<Typed, TranslatorError> {
+        let args = args
+            .into_iter()
+            .map(|arg| self.check(arg, ValueType::F32))
+            .collect::<Result<Vec<Typed>, TranslatorError>>()?;
+        let components: usize = args.iter().map(|arg| arg.ty.components()).sum();
+        match args.as_slice() {
+            [arg] if !arg.ty.is_vector() => {
+                let arg = args.into_iter().next().expect("one argument");
+                return arg.cast(ty, Some(span));
+            }
+            _ if components == ty.components() => {}
+            _ => {
+                return Err(TranslatorError::InvalidArguments {
+                    name: identifier,
+                    found: args.iter().map(|arg| arg.ty).collect(),
+                    span,
+                })
+            }
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| match arg.ty.is_vector() {
+                true => Ok(arg),
+                false => arg.cast(ValueType::F32, Some(span)),
+            })
+            .collect::<Result<Vec<Typed>, TranslatorError>>()?;
+        Ok(Typed::new(Node::Construct(args), ty))
+    }
+
+    /// Types a call of an inbuilt function on vectors, scalar arguments of `dot` and `cross` are used for every component.
+    fn vector_call(
+        &mut self,
+        function: VectorFunction,
+        identifier: String,
+        args: Vec<Expr>,
+        span: Span,
+    ) -> Result<Typed, TranslatorError> {
+        if args.len() != function.arity() {
+            return Err(TranslatorError::ArityMismatch {
+                name: identifier,
+                expected: function.arity(),
+                found: args.len(),
+                span,
+            });
+        }
+        let mut args = args.into_iter();
+        let args = match (args.next(), args.next()) {
+            (Some(lhs), Some(rhs)) => {
+                let (lhs, rhs) = self.operands(lhs, rhs, ValueType::F32)?;
+                vec![lhs, rhs]
+            }
+            (Some(value), None) => vec![self.check(value, ValueType::F32)?],
+            _ => unreachable!("vector functions take one or two arguments"),
+        };
+        let invalid = |args: &[Typed]| TranslatorError::InvalidArguments {
+            name: identifier.clone(),
+            found: args.iter().map(|arg| arg.ty).collect(),
+            span,
+        };
+        let ty = match args.as_slice() {
+            [lhs, rhs] => common(lhs.ty, rhs.ty, Some(span)).map_err(|_| invalid(&args))?,
+            [value] => value.ty,
+            _ => unreachable!("vector functions take one or two arguments"),
+        };
+        if !ty.is_vector() || (function == VectorFunction::Cross && ty != ValueType::Vec3) {
+            return Err(invalid(&args));
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| arg.cast(ty, Some(span)))
+            .collect::<Result<Vec<Typed>, TranslatorError>>()?;
+        let result = match function {
+            VectorFunction::Dot | VectorFunction::Length => ValueType::F32,
+            VectorFunction::Cross | VectorFunction::Normalize => ty,
+        };
+        Ok(Typed::new(Node::VectorCall { function, args }, result))
+    }
+}
+
+/// The type both `lhs` and `rhs` can be converted to, `span` covers the operation combining them.
+fn common(
+    lhs: ValueType,
+    rhs: ValueType,
+    span: Option<Span>,
+) -> Result<ValueType, TranslatorError> {
+    use ValueType::*;
+
+    Ok(match (lhs, rhs) {
+        _ if lhs == rhs => lhs,
+        (vector, _) | (_, vector) if vector.is_vector() => {
+            let other = if vector == lhs { rhs } else { lhs };
+            if other.is_vector() {
+                return Err(TranslatorError::InvalidConversion {
+                    from: rhs,
+                    to: lhs,
+                    span,
+                });
+            }
+            vector
+        }
+        (Bool, ty) | (ty, Bool) => ty,
+        (F64, _) | (_, F64) => F64,
+        (F32, _) | (_, F32) => F32,
+        (I64, I32) | (I32, I64) => I64,
+        (U64, U32) | (U32, U64) => U64,
+        (U64, _) | (_, U64) => return Err(TranslatorError::IncompatibleTypes { lhs, rhs, span }),
+        _ => I64,
+    })
+}
+
+/// The span enclosing two operands, see [`Expr::extent`].
+fn extent(lhs: &Expr, rhs: &Expr) -> Option<Span> {
+    match (lhs.extent(), rhs.extent()) {
+        (Some(lhs), Some(rhs)) => Some(lhs.join(rhs)),
+        (lhs, rhs) => lhs.or(rhs),
+    }
+}
+
+/// Arithmetic on `bool` is done in `hint`, or `f64` if that is a `bool` too.
+fn numeric(hint: ValueType) -> ValueType {
+    match hint {
+        ValueType::Bool => ValueType::F64,
+        ty => ty,
+    }
+}
+
+/// The type of the untyped operand `expr` that is combined with a value of type `other`.
+fn literal_type(expr: &Expr, other: ValueType, hint: ValueType) -> ValueType {
+    match other {
+        ValueType::Bool => hint,
+        ty if ty.is_integer() && has_fraction(expr) => ValueType::F64,
+        ty => ty,
+    }
+}
+
+/// Returns `true` if `expr` only consists of literals and operators, so it has no type of its own.
+fn is_untyped(expr: &Expr) -> bool {
+    match expr {
+        Expr::VariableRead { .. }
+        | Expr::Call { .. }
+        | Expr::Assign { .. }
+        | Expr::Cast { .. }
+        | Expr::Swizzle { .. } => false,
+        _ => expr.children().into_iter().all(is_untyped),
+    }
+}
+
+fn has_fraction(expr: &Expr) -> bool {
+    match expr {
+        Expr::Const { value } => value.fract() != 0.0,
+        _ => expr.children().into_iter().any(has_fraction),
+    }
+}
@@ -0,0 +1,253 @@
+use super::{numeric_type, Node, Typed};
+use crate::jit::{
+    frontend::optimizer::{Operation, MAX_EXPANDED_EXPONENT},
+    types::{AnitaType, ValueType},
+};
+
+/// Folds constant subexpressions and removes operations that do not change the result, like [`optimize`](crate::jit::frontend::optimizer::optimize).
+///
+/// Identities that only hold for finite operands are applied if `FINITE` is set, see [`optimize_assuming_finite`](crate::jit::frontend::optimizer::optimize_assuming_finite).
+/// Constants are folded with the arithmetic of the type of their node, so `7 / 2` is `3` as an `i32` and `3.5` as an `f64`.
+/// Vectors are not folded.
+pub(in crate::jit) fn optimize<const FINITE: bool>(expr: Typed) -> Typed {
+    let ty = expr.ty;
+    let node = match expr.node {
+        Node::Variable(_) | Node::Const(_) | Node::Integer(_) => expr.node,
+        Node::Chain(side, ret) => {
+            let (side, ret) = (optimize::<FINITE>(*side), optimize::<FINITE>(*ret));
+            if side.is_pure() {
+                return ret;
+            }
+            Node::Chain(Box::new(side), Box::new(ret))
+        }
+        Node::Call { identifier, args } => Node::Call {
+            identifier,
+            args: args.into_iter().map(optimize::<FINITE>).collect(),
+        },
+        Node::Arithmetic {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            return arithmetic::<FINITE>(
+                operation,
+                optimize::<FINITE>(*lhs),
+                optimize::<FINITE>(*rhs),
+                ty,
+            )
+        }
+        Node::Neg(value) => match optimize::<FINITE>(*value) {
+            Typed {
+                node: Node::Neg(value),
+                ..
+            } => return *value,
+            Typed {
+                node: Node::Const(value),
+                ..
+            } if ty.is_float() => Node::Const(-value),
+            Typed {
+                node: Node::Const(value),
+                ..
+            } => match fold(Operation::Sub, 0.0, value, ty) {
+                Some(value) => Node::Const(value),
+                None => Node::Neg(Box::new(Typed::new(Node::Const(value), ty))),
+            },
+            value => Node::Neg(Box::new(value)),
+        },
+        Node::Comparison {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            let (lhs, rhs) = (optimize::<FINITE>(*lhs), optimize::<FINITE>(*rhs));
+            match (&lhs.node, &rhs.node) {
+                (Node::Const(l), Node::Const(r)) => match fold(operation, *l, *r, lhs.ty) {
+                    Some(value) => Node::Const(value),
+                    None => Node::Comparison {
+                        operation,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    },
+                },
+                _ => Node::Comparison {
+                    operation,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            }
+        }
+        Node::Logical { and, lhs, rhs } => {
+            let (lhs, rhs) = (optimize::<FINITE>(*lhs), optimize::<FINITE>(*rhs));
+            match (&lhs.node, &rhs.node) {
+                (Node::Const(l), Node::Const(r)) => Node::Const(match and {
+                    true => (*l != 0.0 && *r != 0.0) as u8 as f64,
+                    false => (*l != 0.0 || *r != 0.0) as u8 as f64,
+                }),
+                _ => Node::Logical {
+                    and,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            }
+        }
+        Node::Not(value) => match optimize::<FINITE>(*value) {
+            Typed {
+                node: Node::Const(value),
+                ..
+            } => Node::Const((value == 0.0) as u8 as f64),
+            value => Node::Not(Box::new(value)),
+        },
+        Node::Assign { identifier, value } => Node::Assign {
+            identifier,
+            value: Box::new(optimize::<FINITE>(*value)),
+        },
+        Node::If {
+            condition,
+            then,
+            otherwise,
+        } => match optimize::<FINITE>(*condition) {
+            Typed {
+                node: Node::Const(value),
+                ..
+            } => return optimize::<FINITE>(if value != 0.0 { *then } else { *otherwise }),
+            condition => Node::If {
+                condition: Box::new(condition),
+                then: Box::new(optimize::<FINITE>(*then)),
+                otherwise: Box::new(optimize::<FINITE>(*otherwise)),
+            },
+        },
+        Node::Cast(value) => Node::Cast(Box::new(optimize::<FINITE>(*value))),
+        Node::Construct(args) => {
+            Node::Construct(args.into_iter().map(optimize::<FINITE>).collect())
+        }
+        Node::Swizzle { value, lanes } => Node::Swizzle {
+            value: Box::new(optimize::<FINITE>(*value)),
+            lanes,
+        },
+        Node::VectorCall { function, args } => Node::VectorCall {
+            function,
+            args: args.into_iter().map(optimize::<FINITE>).collect(),
+        },
+        Node::Loop {
+            kind,
+            variable,
+            start,
+            end,
+            body,
+        } => Node::Loop {
+            kind,
+            variable,
+            start: Box::new(optimize::<FINITE>(*start)),
+            end: Box::new(optimize::<FINITE>(*end)),
+            body: Box::new(optimize::<FINITE>(*body)),
+        },
+    };
+    Typed::new(node, ty)
+}
+
+/// Folds or simplifies arithmetic on the optimized operands of type `ty`.
+fn arithmetic<const FINITE: bool>(
+    operation: Operation,
+    lhs: Typed,
+    rhs: Typed,
+    ty: ValueType,
+) -> Typed {
+    let constant = |typed: &Typed| match typed.node {
+        Node::Const(value) => Some(value),
+        _ => None,
+    };
+    if let (Some(l), Some(r)) = (constant(&lhs), constant(&rhs)) {
+        if let Some(value) = fold(operation, l, r, ty) {
+            return Typed::new(Node::Const(value), ty);
+        }
+    }
+    let neg = |value: Typed| optimize::<FINITE>(Typed::new(Node::Neg(Box::new(value)), ty));
+    let zero = || Typed::new(Node::Const(0.0), ty);
+    let node = |operation, lhs, rhs| {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        let node = Node::Arithmetic {
+            operation,
+            lhs,
+            rhs,
+        };
+        Typed::new(node, ty)
+    };
+    match (operation, constant(&lhs), constant(&rhs)) {
+        (Operation::Add, _, Some(0.0)) | (Operation::Sub, _, Some(0.0)) => lhs,
+        (Operation::Add, Some(0.0), _) => rhs,
+        (Operation::Sub, Some(0.0), _) => neg(rhs),
+        (Operation::Mul, _, Some(1.0)) | (Operation::Div, _, Some(1.0)) => lhs,
+        (Operation::Mul, Some(1.0), _) => rhs,
+        (Operation::Mul, _, Some(-1.0)) => neg(lhs),
+        (Operation::Mul, Some(-1.0), _) => neg(rhs),
+        // `0 / 0` is NaN for floats, only types that divide it to `0` may drop the divisor.
+        (Operation::Div, Some(0.0), _)
+            if rhs.is_pure() && fold(Operation::Div, 0.0, 0.0, ty) == Some(0.0) =>
+        {
+            zero()
+        }
+        (Operation::Sub, _, _) if FINITE && lhs == rhs && lhs.is_pure() => zero(),
+        (Operation::Exp, _, Some(exponent)) => expand_power(lhs, exponent, ty),
+        (operation @ (Operation::Add | Operation::Sub), _, _) => match rhs.node {
+            Node::Neg(value) => {
+                let operation = match operation {
+                    Operation::Add => Operation::Sub,
+                    _ => Operation::Add,
+                };
+                node(operation, lhs, *value)
+            }
+            _ => node(operation, lhs, rhs),
+        },
+        _ => node(operation, lhs, rhs),
+    }
+}
+
+/// Replaces `base ^ exponent` for variables and small integers by repeated multiplication.
+fn expand_power(base: Typed, exponent: f64, ty: ValueType) -> Typed {
+    let expand = matches!(base.node, Node::Variable(_))
+        && !ty.is_vector()
+        && numeric_type!(ty, T => <T as AnitaType>::expand_powers())
+        && exponent.fract() == 0.0
+        && exponent.abs() <= MAX_EXPANDED_EXPONENT;
+    if !expand {
+        let (lhs, rhs) = (
+            Box::new(base),
+            Box::new(Typed::new(Node::Const(exponent), ty)),
+        );
+        let node = Node::Arithmetic {
+            operation: Operation::Exp,
+            lhs,
+            rhs,
+        };
+        return Typed::new(node, ty);
+    }
+    let product = (1..exponent.abs() as u32).fold(base.clone(), |product, _| {
+        let node = Node::Arithmetic {
+            operation: Operation::Mul,
+            lhs: Box::new(product),
+            rhs: Box::new(base.clone()),
+        };
+        Typed::new(node, ty)
+    });
+    match exponent {
+        0.0 => Typed::new(Node::Const(1.0), ty),
+        exponent if exponent < 0.0 => {
+            let node = Node::Arithmetic {
+                operation: Operation::Div,
+                lhs: Box::new(Typed::new(Node::Const(1.0), ty)),
+                rhs: Box::new(product),
+            };
+            Typed::new(node, ty)
+        }
+        _ => product,
+    }
+}
+
+/// Folds two literals with the arithmetic of `ty`, see [`AnitaType::fold`].
+fn fold(operation: Operation, lhs: f64, rhs: f64, ty: ValueType) -> Option<f64> {
+    match ty {
+        ValueType::Bool => None,
+        ty if ty.is_vector() => None,
+        ty => numeric_type!(ty, T => <T as AnitaType>::fold(operation, lhs, rhs)),
+    }
+}
@@ -0,0 +1,475 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use cranelift::{
+    codegen::ir::FuncRef,
+    prelude::{
+        types::{F32, F32X4, F64},
+        AbiParam, FunctionBuilder, InstBuilder, IntCC, Signature, Value, Variable,
+    },
+};
+use cranelift_jit::JITModule;
+use cranelift_module::{Linkage, Module};
+
+use super::{numeric_type, Node, Typed, VectorFunction};
+use crate::{
+    function_manager::FunctionManager,
+    jit::{
+        frontend::{optimizer::Operation, LoopKind},
+        translator::{merge, short_circuit, Branches, LoopBlocks},
+        types::{AnitaType, ValueType},
+        TranslatorError,
+    },
+};
+
+/// Names and addresses of [`AnitaType::inbuilt_functions`] of every numeric [`ValueType`], suffixed with the type like `inbuilt_pow_i32`.
+pub(in crate::jit) fn inbuilt_symbols() -> Vec<(String, *const u8)> {
+    ValueType::ALL
+        .into_iter()
+        .filter(|ty| *ty != ValueType::Bool && !ty.is_vector())
+        .flat_map(|ty| {
+            numeric_type!(ty, T => <T as AnitaType>::inbuilt_functions())
+                .into_vec()
+                .into_iter()
+                .map(move |(name, address, _)| (format!("{name}_{ty}"), address))
+        })
+        .collect()
+}
+
+/// Builds an `F32X4` from the `f32` values of up to four lanes, the unused lanes are copies of the first one.
+pub(in crate::jit) fn build_vector(builder: &mut FunctionBuilder, lanes: &[Value]) -> Value {
+    let vector = builder.ins().splat(F32X4, lanes[0]);
+    lanes
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(vector, |vector, (lane, value)| {
+            builder.ins().insertlane(vector, *value, lane as u8)
+        })
+}
+
+/// The `f32` values of the components of a vector of type `ty`.
+pub(in crate::jit) fn vector_lanes(
+    builder: &mut FunctionBuilder,
+    vector: Value,
+    ty: ValueType,
+) -> Vec<Value> {
+    (0..ty.components() as u8)
+        .map(|lane| builder.ins().extractlane(vector, lane))
+        .collect()
+}
+
+/// Lowers a [`Typed`] expression, every value is a single Cranelift value of [`ValueType::cranelift_repr`].
+pub(in crate::jit) struct TypedTranslator<'a, 'b, F: FunctionManager> {
+    pub(in crate::jit) builder: &'b mut FunctionBuilder<'a>,
+    pub(in crate::jit) variables: HashMap<String, Variable>,
+    pub(in crate::jit) functions: HashMap<String, FuncRef>,
+    pub(in crate::jit) module: &'b mut JITModule,
+    /// Iterations left to the loops of the evaluation, see [`JIT::set_max_iterations`](crate::jit::JIT::set_max_iterations).
+    pub(in crate::jit) iterations: Variable,
+    pub(in crate::jit) _function_manager: PhantomData<F>,
+}
+
+impl<F: FunctionManager> TypedTranslator<'_, '_, F> {
+    pub(in crate::jit) fn translate(&mut self, expr: Typed) -> Result<Value, TranslatorError> {
+        let ty = expr.ty;
+        match expr.node {
+            Node::Variable(identifier) => Ok(self.builder.use_var(self.variables[&identifier])),
+            Node::Const(value) => Ok(match ty {
+                ValueType::Bool => self
+                    .builder
+                    .ins()
+                    .iconst(ty.cranelift_repr(), (value != 0.0) as i64),
+                ty if ty.is_vector() => {
+                    let value = <f32 as AnitaType>::constant(self.builder, value);
+                    self.builder.ins().splat(F32X4, value)
+                }
+                ty => numeric_type!(ty, T => <T as AnitaType>::constant(self.builder, value)),
+            }),
+            Node::Integer(value) => Ok(match ty {
+                ValueType::Bool => self
+                    .builder
+                    .ins()
+                    .iconst(ty.cranelift_repr(), (value != 0) as i64),
+                ty if ty.is_vector() => {
+                    let value = <f32 as AnitaType>::integer_constant(self.builder, value);
+                    self.builder.ins().splat(F32X4, value)
+                }
+                ty => {
+                    numeric_type!(ty, T => <T as AnitaType>::integer_constant(self.builder, value))
+                }
+            }),
+            Node::Chain(side, ret) => {
+                self.translate(*side)?;
+                self.translate(*ret)
+            }
+            Node::Call { identifier, args } => {
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.translate(arg))
+                    .collect::<Result<Vec<Value>, TranslatorError>>()?;
+                let func_ref = self.declare_function(&identifier, None)?;
+                let call = self.builder.ins().call(func_ref, &args);
+                Ok(self.builder.inst_results(call)[0])
+            }
+            Node::Arithmetic {
+                operation: Operation::Exp,
+                lhs,
+                rhs,
+            } if !ty.is_vector() => {
+                let args = [self.translate(*lhs)?, self.translate(*rhs)?];
+                let func_ref = self.declare_function(&format!("inbuilt_pow_{ty}"), Some(ty))?;
+                let call = self.builder.ins().call(func_ref, &args);
+                Ok(self.builder.inst_results(call)[0])
+            }
+            Node::Arithmetic {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
+                self.arithmetic(operation, lhs, rhs, ty)
+            }
+            Node::Neg(value) => {
+                let value = self.translate(*value)?;
+                Ok(match ty.is_vector() {
+                    true => self.builder.ins().fneg(value),
+                    false => numeric_type!(ty, T => <T as AnitaType>::neg(self.builder, value)),
+                })
+            }
+            Node::Comparison {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let operand = lhs.ty;
+                let (lhs, rhs) = (self.translate(*lhs)?, self.translate(*rhs)?);
+                let builder = &mut *self.builder;
+                if operand == ValueType::Bool {
+                    let condition = match operation {
+                        Operation::Eq => IntCC::Equal,
+                        Operation::Neq => IntCC::NotEqual,
+                        Operation::Gt => IntCC::UnsignedGreaterThan,
+                        Operation::Lt => IntCC::UnsignedLessThan,
+                        Operation::Geq => IntCC::UnsignedGreaterThanOrEqual,
+                        Operation::Leq => IntCC::UnsignedLessThanOrEqual,
+                        _ => unreachable!("not a comparison"),
+                    };
+                    return Ok(builder.ins().icmp(condition, lhs, rhs));
+                }
+                if operand.is_vector() {
+                    // Vectors are equal if all of their components are.
+                    let lhs = vector_lanes(builder, lhs, operand);
+                    let rhs = vector_lanes(builder, rhs, operand);
+                    let equal: Vec<Value> = lhs
+                        .into_iter()
+                        .zip(rhs)
+                        .map(|(lhs, rhs)| <f32 as AnitaType>::eq(builder, lhs, rhs))
+                        .collect();
+                    let equal = equal
+                        .into_iter()
+                        .reduce(|all, equal| builder.ins().band(all, equal))
+                        .expect("vectors have components");
+                    return Ok(match operation {
+                        Operation::Eq => equal,
+                        _ => builder.ins().icmp_imm(IntCC::Equal, equal, 0),
+                    });
+                }
+                Ok(numeric_type!(operand, T => match operation {
+                    Operation::Eq => <T as AnitaType>::eq(builder, lhs, rhs),
+                    Operation::Neq => <T as AnitaType>::neq(builder, lhs, rhs),
+                    Operation::Gt => <T as AnitaType>::gt(builder, lhs, rhs),
+                    Operation::Lt => <T as AnitaType>::lt(builder, lhs, rhs),
+                    Operation::Geq => <T as AnitaType>::geq(builder, lhs, rhs),
+                    Operation::Leq => <T as AnitaType>::leq(builder, lhs, rhs),
+                    _ => unreachable!("not a comparison"),
+                }))
+            }
+            Node::Logical { and, lhs, rhs } => self.logical(*lhs, *rhs, and),
+            Node::Not(value) => {
+                let value = self.translate(*value)?;
+                Ok(self.builder.ins().icmp_imm(IntCC::Equal, value, 0))
+            }
+            Node::Assign { identifier, value } => {
+                let value = self.translate(*value)?;
+                self.builder.def_var(self.variables[&identifier], value);
+                Ok(value)
+            }
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.translate(*condition)?;
+                let branches = Branches::new(self.builder, condition);
+                let result = self
+                    .builder
+                    .append_block_param(branches.merge, ty.cranelift_repr());
+                let then = self.translate(*then)?;
+                branches.otherwise(self.builder, &[then]);
+                let otherwise = self.translate(*otherwise)?;
+                branches.merge(self.builder, &[otherwise]);
+                Ok(result)
+            }
+            Node::Cast(value) => {
+                let from = value.ty;
+                let value = self.translate(*value)?;
+                Ok(self.convert(value, from, ty))
+            }
+            Node::Construct(args) => {
+                let mut lanes = Vec::new();
+                for arg in args {
+                    let arg_type = arg.ty;
+                    let value = self.translate(arg)?;
+                    match arg_type.is_vector() {
+                        true => lanes.extend(vector_lanes(self.builder, value, arg_type)),
+                        false => lanes.push(value),
+                    }
+                }
+                Ok(build_vector(self.builder, &lanes))
+            }
+            Node::Swizzle { value, lanes } => {
+                let value = self.translate(*value)?;
+                let lanes: Vec<Value> = lanes
+                    .into_iter()
+                    .map(|lane| self.builder.ins().extractlane(value, lane))
+                    .collect();
+                Ok(match lanes.as_slice() {
+                    [lane] => *lane,
+                    lanes => build_vector(self.builder, lanes),
+                })
+            }
+            Node::VectorCall { function, args } => {
+                let operand = args[0].ty;
+                let args = args
+                    .into_iter()
+                    .map(|arg| self.translate(arg))
+                    .collect::<Result<Vec<Value>, TranslatorError>>()?;
+                Ok(self.vector_call(function, &args, operand))
+            }
+            Node::Loop {
+                kind,
+                variable,
+                start,
+                end,
+                body,
+            } => self.iterate(kind, &variable, *start, *end, *body, ty),
+        }
+    }
+
+    /// Lowers arithmetic other than `^` on scalars, or any arithmetic on vectors, of type `ty`.
+    fn arithmetic(
+        &mut self,
+        operation: Operation,
+        lhs: Value,
+        rhs: Value,
+        ty: ValueType,
+    ) -> Result<Value, TranslatorError> {
+        if ty.is_vector() {
+            return self.vector_arithmetic(operation, lhs, rhs, ty);
+        }
+        let builder = &mut *self.builder;
+        Ok(numeric_type!(ty, T => match operation {
+            Operation::Add => <T as AnitaType>::add(builder, lhs, rhs),
+            Operation::Sub => <T as AnitaType>::sub(builder, lhs, rhs),
+            Operation::Mul => <T as AnitaType>::mul(builder, lhs, rhs),
+            Operation::Div => <T as AnitaType>::div(builder, lhs, rhs),
+            Operation::Mod => <T as AnitaType>::modulo(builder, lhs, rhs),
+            _ => unreachable!("not an arithmetic operation"),
+        }))
+    }
+
+    /// Lowers a loop of type `ty` over a loop variable of the type of `start`, see [`LoopBlocks`].
+    fn iterate(
+        &mut self,
+        kind: LoopKind,
+        variable: &str,
+        start: Typed,
+        end: Typed,
+        body: Typed,
+        ty: ValueType,
+    ) -> Result<Value, TranslatorError> {
+        let counter = start.ty;
+        let start = self.translate(start)?;
+        let end = self.translate(end)?;
+        let initial = self.translate(Typed::new(Node::Const(kind.initial()), ty))?;
+        let one = self.translate(Typed::new(Node::Const(1.0), counter))?;
+
+        let params = [counter.cranelift_repr(), ty.cranelift_repr()];
+        let (blocks, params) = LoopBlocks::enter(self.builder, &params, &[start, initial]);
+        let (value, result) = (params[0], params[1]);
+        let builder = &mut *self.builder;
+        let active = numeric_type!(counter, T => match kind.inclusive() {
+            true => <T as AnitaType>::leq(builder, value, end),
+            false => <T as AnitaType>::lt(builder, value, end),
+        });
+        blocks.branch(self.builder, self.iterations, active);
+
+        self.builder.def_var(self.variables[variable], value);
+        let iteration = self.translate(body)?;
+        let iteration = match kind {
+            LoopKind::For => iteration,
+            LoopKind::Sum => self.arithmetic(Operation::Add, result, iteration, ty)?,
+            LoopKind::Product => self.arithmetic(Operation::Mul, result, iteration, ty)?,
+        };
+        let next = self.arithmetic(Operation::Add, value, one, counter)?;
+        blocks.repeat(self.builder, &[next, iteration]);
+        Ok(result)
+    }
+
+    /// Lowers arithmetic on two vectors of type `ty`, `%` and `^` per component.
+    fn vector_arithmetic(
+        &mut self,
+        operation: Operation,
+        lhs: Value,
+        rhs: Value,
+        ty: ValueType,
+    ) -> Result<Value, TranslatorError> {
+        let builder = &mut *self.builder;
+        Ok(match operation {
+            Operation::Add => builder.ins().fadd(lhs, rhs),
+            Operation::Sub => builder.ins().fsub(lhs, rhs),
+            Operation::Mul => builder.ins().fmul(lhs, rhs),
+            Operation::Div => builder.ins().fdiv(lhs, rhs),
+            Operation::Mod | Operation::Exp => {
+                let lhs = vector_lanes(self.builder, lhs, ty);
+                let rhs = vector_lanes(self.builder, rhs, ty);
+                let mut lanes = Vec::new();
+                for (lhs, rhs) in lhs.into_iter().zip(rhs) {
+                    lanes.push(match operation {
+                        Operation::Mod => <f32 as AnitaType>::modulo(self.builder, lhs, rhs),
+                        _ => {
+                            let name = format!("inbuilt_pow_{}", ValueType::F32);
+                            let func_ref = self.declare_function(&name, Some(ValueType::F32))?;
+                            let call = self.builder.ins().call(func_ref, &[lhs, rhs]);
+                            self.builder.inst_results(call)[0]
+                        }
+                    });
+                }
+                build_vector(self.builder, &lanes)
+            }
+            _ => unreachable!("not an arithmetic operation"),
+        })
+    }
+
+    /// Lowers an inbuilt function on vectors of type `operand`.
+    fn vector_call(
+        &mut self,
+        function: VectorFunction,
+        args: &[Value],
+        operand: ValueType,
+    ) -> Value {
+        let builder = &mut *self.builder;
+        let dot = |builder: &mut FunctionBuilder, lhs: Value, rhs: Value| {
+            let product = builder.ins().fmul(lhs, rhs);
+            vector_lanes(builder, product, operand)
+                .into_iter()
+                .reduce(|sum, lane| builder.ins().fadd(sum, lane))
+                .expect("vectors have components")
+        };
+        match function {
+            VectorFunction::Dot => dot(builder, args[0], args[1]),
+            VectorFunction::Length => {
+                let square = dot(builder, args[0], args[0]);
+                builder.ins().sqrt(square)
+            }
+            VectorFunction::Normalize => {
+                let square = dot(builder, args[0], args[0]);
+                let length = builder.ins().sqrt(square);
+                let length = builder.ins().splat(F32X4, length);
+                builder.ins().fdiv(args[0], length)
+            }
+            VectorFunction::Cross => {
+                let a = vector_lanes(builder, args[0], operand);
+                let b = vector_lanes(builder, args[1], operand);
+                let lanes: Vec<Value> = [(1, 2), (2, 0), (0, 1)]
+                    .into_iter()
+                    .map(|(i, j)| {
+                        let lhs = builder.ins().fmul(a[i], b[j]);
+                        let rhs = builder.ins().fmul(a[j], b[i]);
+                        builder.ins().fsub(lhs, rhs)
+                    })
+                    .collect();
+                build_vector(builder, &lanes)
+            }
+        }
+    }
+
+    /// Lowers `&&` (`and == true`) and `||` on booleans, see [`short_circuit`].
+    fn logical(&mut self, lhs: Typed, rhs: Typed, and: bool) -> Result<Value, TranslatorError> {
+        let lhs = self.translate(lhs)?;
+        let (merge_block, result) = short_circuit(self.builder, lhs, and);
+        let rhs = self.translate(rhs)?;
+        merge(self.builder, merge_block, &[rhs]);
+        Ok(result)
+    }
+
+    /// Converts like the `as` operator of Rust, floats saturate when converted to integers and NaN becomes `0`.
+    ///
+    /// Conversions to `bool` compare with `0`, conversions from it result in `0` or `1`.
+    fn convert(&mut self, value: Value, from: ValueType, to: ValueType) -> Value {
+        let builder = &mut *self.builder;
+        let repr = to.cranelift_repr();
+        match (from, to) {
+            _ if from == to => value,
+            (from, to) if to.is_vector() => {
+                let value = self.convert(value, from, ValueType::F32);
+                self.builder.ins().splat(F32X4, value)
+            }
+            (ValueType::Bool, to) => {
+                numeric_type!(to, T => <T as AnitaType>::from_condition(builder, value))
+            }
+            (from, ValueType::Bool) => {
+                numeric_type!(from, T => <T as AnitaType>::truthy(builder, value))
+            }
+            (ValueType::F32, ValueType::F64) => builder.ins().fpromote(F64, value),
+            (ValueType::F64, ValueType::F32) => builder.ins().fdemote(F32, value),
+            (from, to) if from.is_float() => match to.is_signed() {
+                true => builder.ins().fcvt_to_sint_sat(repr, value),
+                false => builder.ins().fcvt_to_uint_sat(repr, value),
+            },
+            (from, to) if to.is_float() => match from.is_signed() {
+                true => builder.ins().fcvt_from_sint(repr, value),
+                false => builder.ins().fcvt_from_uint(repr, value),
+            },
+            (from, _) => {
+                let from_bits = from.cranelift_repr().bits();
+                match repr.bits().cmp(&from_bits) {
+                    std::cmp::Ordering::Equal => value,
+                    std::cmp::Ordering::Less => builder.ins().ireduce(repr, value),
+                    std::cmp::Ordering::Greater => match from.is_signed() {
+                        true => builder.ins().sextend(repr, value),
+                        false => builder.ins().uextend(repr, value),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Declares a function of the [`FunctionManager`], or the inbuilt function of type `inbuilt` with two parameters.
+    fn declare_function(
+        &mut self,
+        identifier: &str,
+        inbuilt: Option<ValueType>,
+    ) -> Result<FuncRef, TranslatorError> {
+        if let Some(func_ref) = self.functions.get(identifier) {
+            return Ok(*func_ref);
+        }
+        let call_conv = self.module.isa().default_call_conv();
+        let signature = match inbuilt {
+            Some(ty) => Signature {
+                params: vec![AbiParam::new(ty.cranelift_repr()); 2],
+                returns: vec![AbiParam::new(ty.cranelift_repr())],
+                call_conv,
+            },
+            // The checker only lets through calls of functions with a signature.
+            None => F::function_signature(identifier, call_conv)
+                .expect("function was checked before translation"),
+        };
+        let func_id = self
+            .module
+            .declare_function(identifier, Linkage::Import, &signature)?;
+        let func_ref = self.module.declare_func_in_func(func_id, self.builder.func);
+        self.functions.insert(identifier.to_owned(), func_ref);
+        Ok(func_ref)
+    }
+}
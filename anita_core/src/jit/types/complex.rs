@@ -0,0 +1,315 @@
+use std::marker::PhantomData;
+
+use cranelift::prelude::{isa::CallConv, FunctionBuilder, InstBuilder, Signature, Type, Value};
+
+use super::{output_arity, output_signature, pointer_type, AnitaType, FunctionCall, Real};
+use crate::{
+    function_manager::FunctionManager,
+    jit::{frontend::optimizer::Operation, TranslatorError},
+};
+
+/// A complex number `re + im i`.
+///
+/// Expressions may use imaginary literals like `2.5i`, and `i` is the imaginary unit unless it is a parameter or assigned.
+/// Comparisons with `==` and `!=` compare both parts, the ordering comparisons only the real parts.
+/// `a % b` is `a - b * trunc(a / b)` with both parts of the quotient truncated, and a number is true if either part is not `0`.
+///
+/// Functions take and return complex numbers, see [`ComplexFunctions`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex<T> {
+    pub re: T,
+    pub im: T,
+}
+
+impl<T> Complex<T> {
+    pub fn new(re: T, im: T) -> Self {
+        Self { re, im }
+    }
+}
+
+impl<T: Real> AnitaType for Complex<T> {
+    type Lowered = [Value; 2];
+
+    fn cranelift_repr() -> Type {
+        T::cranelift_repr()
+    }
+
+    fn constant(builder: &mut FunctionBuilder, value: f64) -> [Value; 2] {
+        [T::constant(builder, value), T::constant(builder, 0.0)]
+    }
+
+    fn has_imaginary_unit() -> bool {
+        true
+    }
+
+    fn imaginary(builder: &mut FunctionBuilder, value: f64) -> Option<[Value; 2]> {
+        Some([T::constant(builder, 0.0), T::constant(builder, value)])
+    }
+
+    fn add(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        [T::add(builder, a, c), T::add(builder, b, d)]
+    }
+
+    fn sub(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        [T::sub(builder, a, c), T::sub(builder, b, d)]
+    }
+
+    fn mul(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        // (a + bi)(c + di) = (ac - bd) + (ad + bc)i
+        let (ac, bd) = (T::mul(builder, a, c), T::mul(builder, b, d));
+        let (ad, bc) = (T::mul(builder, a, d), T::mul(builder, b, c));
+        [T::sub(builder, ac, bd), T::add(builder, ad, bc)]
+    }
+
+    fn div(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        // (a + bi) / (c + di) = ((ac + bd) + (bc - ad)i) / (c² + d²)
+        let (cc, dd) = (T::mul(builder, c, c), T::mul(builder, d, d));
+        let norm = T::add(builder, cc, dd);
+        let (ac, bd) = (T::mul(builder, a, c), T::mul(builder, b, d));
+        let (bc, ad) = (T::mul(builder, b, c), T::mul(builder, a, d));
+        let re = T::add(builder, ac, bd);
+        let im = T::sub(builder, bc, ad);
+        [T::div(builder, re, norm), T::div(builder, im, norm)]
+    }
+
+    fn modulo(builder: &mut FunctionBuilder, value: [Value; 2], modulus: [Value; 2]) -> [Value; 2] {
+        // value - modulus * trunc(value / modulus), where trunc(x) = x - x % 1 for both parts.
+        let quotient = Self::div(builder, value, modulus);
+        let one = T::constant(builder, 1.0);
+        let truncated = quotient.map(|part| {
+            let fraction = T::modulo(builder, part, one);
+            T::sub(builder, part, fraction)
+        });
+        let multiple = Self::mul(builder, modulus, truncated);
+        Self::sub(builder, value, multiple)
+    }
+
+    fn neg(builder: &mut FunctionBuilder, [a, b]: [Value; 2]) -> [Value; 2] {
+        [T::neg(builder, a), T::neg(builder, b)]
+    }
+
+    fn eq(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let re = T::eq(builder, a, c);
+        let im = T::eq(builder, b, d);
+        builder.ins().band(re, im)
+    }
+
+    fn neq(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let re = T::neq(builder, a, c);
+        let im = T::neq(builder, b, d);
+        builder.ins().bor(re, im)
+    }
+
+    fn gt(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [c, _]: [Value; 2]) -> Value {
+        T::gt(builder, a, c)
+    }
+
+    fn lt(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [c, _]: [Value; 2]) -> Value {
+        T::lt(builder, a, c)
+    }
+
+    fn geq(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [c, _]: [Value; 2]) -> Value {
+        T::geq(builder, a, c)
+    }
+
+    fn leq(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [c, _]: [Value; 2]) -> Value {
+        T::leq(builder, a, c)
+    }
+
+    fn truthy(builder: &mut FunctionBuilder, [a, b]: [Value; 2]) -> Value {
+        let re = T::truthy(builder, a);
+        let im = T::truthy(builder, b);
+        builder.ins().bor(re, im)
+    }
+
+    fn select(
+        builder: &mut FunctionBuilder,
+        condition: Value,
+        [a, b]: [Value; 2],
+        [c, d]: [Value; 2],
+    ) -> [Value; 2] {
+        [
+            T::select(builder, condition, a, c),
+            T::select(builder, condition, b, d),
+        ]
+    }
+
+    fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> [Value; 2] {
+        [
+            T::from_condition(builder, condition),
+            T::constant(builder, 0.0),
+        ]
+    }
+
+    /// Literals are real, so they fold like `T` unless a power of a negative number is not real.
+    fn fold(operation: Operation, lhs: f64, rhs: f64) -> Option<f64> {
+        match operation {
+            Operation::Exp if lhs < 0.0 && rhs.fract() != 0.0 => None,
+            operation => T::fold(operation, lhs, rhs),
+        }
+    }
+
+    /// Functions take the real and imaginary part of every argument followed by a pointer to the `Complex<T>` they write their result to.
+    fn function_signature(arity: usize, call_conv: CallConv, pointer_type: Type) -> Signature {
+        output_signature(T::cranelift_repr(), 2, arity, call_conv, pointer_type)
+    }
+
+    fn function_arity(signature: &Signature) -> usize {
+        output_arity(signature, 2)
+    }
+
+    fn call<'a, C: FunctionCall<'a>>(
+        call: &mut C,
+        args: &[[Value; 2]],
+    ) -> Result<[Value; 2], TranslatorError> {
+        let args: Vec<Value> = args.iter().flatten().copied().collect();
+        match call.call_with_output(&args, 2)[..] {
+            [re, im] => Ok([re, im]),
+            _ => unreachable!("two results were requested"),
+        }
+    }
+
+    fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]> {
+        Box::new([
+            ("inbuilt_pow", inbuilt_pow::<T> as *const u8, 2),
+            ("inbuilt_ln", ln::<T> as *const u8, 1),
+        ])
+    }
+}
+
+/// The functions of [`Complex`] numbers with parts of type `T`.
+///
+/// | Identifier | Description |
+/// |------------|-------------|
+/// | `re(z)`    | Real part |
+/// | `im(z)`    | Imaginary part |
+/// | `abs(z)`   | Magnitude |
+/// | `arg(z)`   | Angle in `(-π, π]` |
+/// | `conj(z)`  | Complex conjugate |
+/// | `exp(z)`   | Exponential function |
+/// | `ln(z)`    | Principal natural logarithm |
+///
+/// The functions that return a real number return it with an imaginary part of `0`.
+pub struct ComplexFunctions<T> {
+    _type: PhantomData<T>,
+}
+
+impl<T: Real> ComplexFunctions<T> {
+    const FUNCTIONS: [&'static str; 7] = ["re", "im", "abs", "arg", "conj", "exp", "ln"];
+}
+
+impl<T: Real> FunctionManager for ComplexFunctions<T> {
+    fn function_symbols() -> Box<[(&'static str, *const u8)]> {
+        Box::new([
+            ("re", re::<T> as *const u8),
+            ("im", im::<T> as *const u8),
+            ("abs", abs::<T> as *const u8),
+            ("arg", arg::<T> as *const u8),
+            ("conj", conj::<T> as *const u8),
+            ("exp", exp::<T> as *const u8),
+            ("ln", ln::<T> as *const u8),
+        ])
+    }
+
+    fn function_signature(identifier: &str, calling_convention: CallConv) -> Option<Signature> {
+        Self::FUNCTIONS
+            .contains(&identifier)
+            .then(|| Complex::<T>::function_signature(1, calling_convention, pointer_type()))
+    }
+}
+
+/// Evaluates `f` on the complex number `re + im i` in `f64` and writes its result to `out`.
+fn apply<T: Real>(re: T, im: T, out: *mut Complex<T>, f: impl Fn(Complex<f64>) -> Complex<f64>) {
+    let result = f(Complex::new(re.to_f64(), im.to_f64()));
+    let result = Complex::new(T::from_f64(result.re), T::from_f64(result.im));
+    // SAFETY: the translator passes a stack slot that fits a `Complex<T>`.
+    unsafe { out.write(result) };
+}
+
+fn real(value: f64) -> Complex<f64> {
+    Complex::new(value, 0.0)
+}
+
+fn exponential(z: Complex<f64>) -> Complex<f64> {
+    let magnitude = z.re.exp();
+    Complex::new(magnitude * z.im.cos(), magnitude * z.im.sin())
+}
+
+fn logarithm(z: Complex<f64>) -> Complex<f64> {
+    Complex::new(z.re.hypot(z.im).ln(), z.im.atan2(z.re))
+}
+
+fn product(lhs: Complex<f64>, rhs: Complex<f64>) -> Complex<f64> {
+    Complex::new(
+        lhs.re * rhs.re - lhs.im * rhs.im,
+        lhs.re * rhs.im + lhs.im * rhs.re,
+    )
+}
+
+/// `z ^ w`, computed by repeated multiplication for integer exponents and as `exp(w ln(z))` otherwise.
+fn power(z: Complex<f64>, w: Complex<f64>) -> Complex<f64> {
+    if w.im == 0.0 {
+        if z.im == 0.0 && (z.re >= 0.0 || w.re.fract() == 0.0) {
+            return real(z.re.powf(w.re));
+        }
+        if w.re.fract() == 0.0 && w.re.abs() <= i32::MAX as f64 {
+            let (mut base, mut exponent, mut result) = (z, w.re.abs() as u32, real(1.0));
+            while exponent > 0 {
+                if exponent & 1 == 1 {
+                    result = product(result, base);
+                }
+                base = product(base, base);
+                exponent >>= 1;
+            }
+            if w.re < 0.0 {
+                let norm = result.re * result.re + result.im * result.im;
+                result = Complex::new(result.re / norm, -result.im / norm);
+            }
+            return result;
+        }
+    }
+    if z.re == 0.0 && z.im == 0.0 {
+        return real(0.0);
+    }
+    exponential(product(w, logarithm(z)))
+}
+
+extern "C" fn inbuilt_pow<T: Real>(
+    re: T,
+    im: T,
+    exponent_re: T,
+    exponent_im: T,
+    out: *mut Complex<T>,
+) {
+    let exponent = Complex::new(exponent_re.to_f64(), exponent_im.to_f64());
+    apply(re, im, out, |z| power(z, exponent));
+}
+
+extern "C" fn re<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, |z| real(z.re));
+}
+
+extern "C" fn im<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, |z| real(z.im));
+}
+
+extern "C" fn abs<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, |z| real(z.re.hypot(z.im)));
+}
+
+extern "C" fn arg<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, |z| real(z.im.atan2(z.re)));
+}
+
+extern "C" fn conj<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, |z| Complex::new(z.re, -z.im));
+}
+
+extern "C" fn exp<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, exponential);
+}
+
+extern "C" fn ln<T: Real>(re: T, im: T, out: *mut Complex<T>) {
+    apply(re, im, out, logarithm);
+}
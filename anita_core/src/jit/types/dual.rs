@@ -0,0 +1,169 @@
+use cranelift::prelude::{FunctionBuilder, Type, Value};
+
+use super::{AnitaType, FunctionCall};
+use crate::jit::{frontend::optimizer::Operation, TranslatorError};
+
+/// A dual number `value + derivative ε` with `ε² = 0`.
+///
+/// Evaluating an expression on dual numbers computes its derivative alongside its value (forward-mode automatic differentiation).
+/// Passing the variable to differentiate for as [`Dual::variable`] and every other parameter as [`Dual::constant`]
+/// results in the partial derivative with respect to that variable.
+///
+/// Comparisons, truthiness and conditionals only look at the value, so the derivative of `x > 0` is `0`
+/// and conditionals take the derivative of the branch that was evaluated.
+/// Function calls use the partial derivatives of [`FunctionManager::function_derivatives`](crate::function_manager::FunctionManager::function_derivatives),
+/// calling a function without derivatives fails to compile.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Dual<T> {
+    pub value: T,
+    pub derivative: T,
+}
+
+impl<T> Dual<T> {
+    pub fn new(value: T, derivative: T) -> Self {
+        Self { value, derivative }
+    }
+}
+
+impl<T: From<u8>> Dual<T> {
+    /// A value that does not depend on the variable, its derivative is `0`.
+    pub fn constant(value: T) -> Self {
+        Self::new(value, T::from(0))
+    }
+
+    /// The variable itself, its derivative is `1`.
+    pub fn variable(value: T) -> Self {
+        Self::new(value, T::from(1))
+    }
+}
+
+impl<T: AnitaType<Lowered = Value>> AnitaType for Dual<T> {
+    type Lowered = [Value; 2];
+
+    fn cranelift_repr() -> Type {
+        T::cranelift_repr()
+    }
+
+    fn constant(builder: &mut FunctionBuilder, value: f64) -> [Value; 2] {
+        [T::constant(builder, value), T::constant(builder, 0.0)]
+    }
+
+    fn add(builder: &mut FunctionBuilder, [a, da]: [Value; 2], [b, db]: [Value; 2]) -> [Value; 2] {
+        [T::add(builder, a, b), T::add(builder, da, db)]
+    }
+
+    fn sub(builder: &mut FunctionBuilder, [a, da]: [Value; 2], [b, db]: [Value; 2]) -> [Value; 2] {
+        [T::sub(builder, a, b), T::sub(builder, da, db)]
+    }
+
+    fn mul(builder: &mut FunctionBuilder, [a, da]: [Value; 2], [b, db]: [Value; 2]) -> [Value; 2] {
+        let value = T::mul(builder, a, b);
+        let lhs = T::mul(builder, da, b);
+        let rhs = T::mul(builder, a, db);
+        [value, T::add(builder, lhs, rhs)]
+    }
+
+    fn div(builder: &mut FunctionBuilder, [a, da]: [Value; 2], [b, db]: [Value; 2]) -> [Value; 2] {
+        // (a / b)' = (da - (a / b) * db) / b
+        let value = T::div(builder, a, b);
+        let scaled = T::mul(builder, value, db);
+        let numerator = T::sub(builder, da, scaled);
+        [value, T::div(builder, numerator, b)]
+    }
+
+    fn modulo(
+        builder: &mut FunctionBuilder,
+        [a, da]: [Value; 2],
+        [b, db]: [Value; 2],
+    ) -> [Value; 2] {
+        // a % b = a - trunc(a / b) * b, where trunc(a / b) = (a - a % b) / b is locally constant.
+        let value = T::modulo(builder, a, b);
+        let truncated = T::sub(builder, a, value);
+        let quotient = T::div(builder, truncated, b);
+        let scaled = T::mul(builder, quotient, db);
+        [value, T::sub(builder, da, scaled)]
+    }
+
+    fn neg(builder: &mut FunctionBuilder, [a, da]: [Value; 2]) -> [Value; 2] {
+        [T::neg(builder, a), T::neg(builder, da)]
+    }
+
+    fn eq(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [b, _]: [Value; 2]) -> Value {
+        T::eq(builder, a, b)
+    }
+
+    fn neq(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [b, _]: [Value; 2]) -> Value {
+        T::neq(builder, a, b)
+    }
+
+    fn gt(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [b, _]: [Value; 2]) -> Value {
+        T::gt(builder, a, b)
+    }
+
+    fn lt(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [b, _]: [Value; 2]) -> Value {
+        T::lt(builder, a, b)
+    }
+
+    fn geq(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [b, _]: [Value; 2]) -> Value {
+        T::geq(builder, a, b)
+    }
+
+    fn leq(builder: &mut FunctionBuilder, [a, _]: [Value; 2], [b, _]: [Value; 2]) -> Value {
+        T::leq(builder, a, b)
+    }
+
+    fn truthy(builder: &mut FunctionBuilder, [a, _]: [Value; 2]) -> Value {
+        T::truthy(builder, a)
+    }
+
+    fn select(
+        builder: &mut FunctionBuilder,
+        condition: Value,
+        [a, da]: [Value; 2],
+        [b, db]: [Value; 2],
+    ) -> [Value; 2] {
+        [
+            T::select(builder, condition, a, b),
+            T::select(builder, condition, da, db),
+        ]
+    }
+
+    fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> [Value; 2] {
+        [
+            T::from_condition(builder, condition),
+            T::constant(builder, 0.0),
+        ]
+    }
+
+    /// Literals have a derivative of `0`, so they fold like `T`.
+    fn fold(operation: Operation, lhs: f64, rhs: f64) -> Option<f64> {
+        T::fold(operation, lhs, rhs)
+    }
+
+    /// Applies the chain rule, `f(a, b)' = ∂f/∂a * da + ∂f/∂b * db`.
+    ///
+    /// Arguments with a derivative of `0` do not contribute, even where the partial derivative is not finite.
+    fn call<'a, C: FunctionCall<'a>>(
+        call: &mut C,
+        args: &[[Value; 2]],
+    ) -> Result<[Value; 2], TranslatorError> {
+        let values: Vec<Value> = args.iter().map(|[value, _]| *value).collect();
+        let value = call.call(&values);
+        let mut derivative = T::constant(call.builder(), 0.0);
+        for (index, [_, tangent]) in args.iter().enumerate() {
+            let partial = call.partial::<T>(index, &values)?;
+            let builder = call.builder();
+            let term = T::mul(builder, partial, *tangent);
+            let zero = T::constant(builder, 0.0);
+            let contributes = T::truthy(builder, *tangent);
+            let term = T::select(builder, contributes, term, zero);
+            derivative = T::add(builder, derivative, term);
+        }
+        Ok([value, derivative])
+    }
+
+    fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]> {
+        T::inbuilt_functions()
+    }
+}
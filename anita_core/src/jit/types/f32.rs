@@ -1,14 +1,28 @@
-use cranelift::prelude::{types::F32, FloatCC, FunctionBuilder, InstBuilder, Type, Value};
+use cranelift::prelude::{
+    types::{F32, F32X4},
+    FloatCC, FunctionBuilder, InstBuilder, MemFlags, Type, Value,
+};
 
-use super::AnitaType;
+use super::{AnitaType, ValueType};
+use crate::jit::frontend::optimizer::Operation;
 
 impl AnitaType for f32 {
+    type Lowered = Value;
+
     fn cranelift_repr() -> Type {
         F32
     }
 
-    fn constant(builder: &mut FunctionBuilder, value: f32) -> Value {
-        builder.ins().f32const(value)
+    fn value_type() -> Option<ValueType> {
+        Some(ValueType::F32)
+    }
+
+    fn cranelift_vector_repr() -> Option<Type> {
+        Some(F32X4)
+    }
+
+    fn constant(builder: &mut FunctionBuilder, value: f64) -> Value {
+        builder.ins().f32const(value as f32)
     }
 
     fn add(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
@@ -62,29 +76,81 @@ impl AnitaType for f32 {
         builder.ins().fcmp(FloatCC::LessThanOrEqual, lhs, rhs)
     }
 
-    fn and(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
-        let zero = builder.ins().f32const(0.0);
-        let two = builder.ins().f32const(2.0);
-        let lhs = builder.ins().fcmp(FloatCC::NotEqual, lhs, zero);
-        let rhs = builder.ins().fcmp(FloatCC::NotEqual, rhs, zero);
-        let intermediate = builder.ins().fadd(lhs, rhs);
-        builder.ins().fcmp(FloatCC::Equal, intermediate, two)
+    fn truthy(builder: &mut FunctionBuilder, value: Value) -> Value {
+        let zero = constant_like(builder, value, 0.0);
+        builder.ins().fcmp(FloatCC::NotEqual, value, zero)
+    }
+
+    fn select(
+        builder: &mut FunctionBuilder,
+        condition: Value,
+        then: Value,
+        otherwise: Value,
+    ) -> Value {
+        if builder.func.dfg.value_type(then).is_vector() {
+            let mask = builder.ins().bitcast(F32X4, MemFlags::new(), condition);
+            builder.ins().bitselect(mask, then, otherwise)
+        } else {
+            builder.ins().select(condition, then, otherwise)
+        }
+    }
+
+    fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> Value {
+        let (one, zero) = if builder.func.dfg.value_type(condition).is_vector() {
+            let one = builder.ins().f32const(1.0);
+            let zero = builder.ins().f32const(0.0);
+            (
+                builder.ins().splat(F32X4, one),
+                builder.ins().splat(F32X4, zero),
+            )
+        } else {
+            (builder.ins().f32const(1.0), builder.ins().f32const(0.0))
+        };
+        Self::select(builder, condition, one, zero)
+    }
+
+    fn fold(operation: Operation, lhs: f64, rhs: f64) -> Option<f64> {
+        let (lhs, rhs) = (lhs as f32, rhs as f32);
+        let folded = match operation {
+            Operation::Add => lhs + rhs,
+            Operation::Sub => lhs - rhs,
+            Operation::Mul => lhs * rhs,
+            Operation::Div => lhs / rhs,
+            Operation::Mod => lhs - (lhs / rhs).trunc() * rhs,
+            Operation::Exp => lhs.powf(rhs),
+            Operation::Eq => (lhs == rhs) as u8 as f32,
+            Operation::Neq => (lhs != rhs) as u8 as f32,
+            Operation::Gt => (lhs > rhs) as u8 as f32,
+            Operation::Lt => (lhs < rhs) as u8 as f32,
+            Operation::Geq => (lhs >= rhs) as u8 as f32,
+            Operation::Leq => (lhs <= rhs) as u8 as f32,
+            Operation::And => (lhs != 0.0 && rhs != 0.0) as u8 as f32,
+            Operation::Or => (lhs != 0.0 || rhs != 0.0) as u8 as f32,
+        };
+        Some(f64::from(folded))
+    }
+
+    fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]> {
+        Box::new([
+            ("inbuilt_pow", inbuilt_pow as *const u8, 2),
+            ("inbuilt_ln", inbuilt_ln as *const u8, 1),
+        ])
     }
+}
 
-    fn or(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
-        let zero = builder.ins().f32const(0.0);
-        let lhs = builder.ins().fcmp(FloatCC::NotEqual, lhs, zero);
-        let rhs = builder.ins().fcmp(FloatCC::NotEqual, rhs, zero);
-        let intermediate = builder.ins().fadd(lhs, rhs);
-        builder.ins().fcmp(FloatCC::NotEqual, intermediate, zero)
-    }
+extern "C" fn inbuilt_pow(value: f32, exponent: f32) -> f32 {
+    value.powf(exponent)
+}
 
-    fn not(builder: &mut FunctionBuilder, value: Value) -> Value {
-        let zero = builder.ins().f32const(0.0);
-        builder.ins().fcmp(FloatCC::Equal, value, zero)
-    }
+extern "C" fn inbuilt_ln(value: f32) -> f32 {
+    value.ln()
+}
 
-    extern "C" fn inbuilt_pow(self, value: Self) -> Self {
-        self.powf(value)
+/// Creates a constant with the same shape (scalar or vector) as `like`.
+fn constant_like(builder: &mut FunctionBuilder, like: Value, value: f32) -> Value {
+    let constant = builder.ins().f32const(value);
+    match builder.func.dfg.value_type(like) {
+        F32X4 => builder.ins().splat(F32X4, constant),
+        _ => constant,
     }
 }
@@ -1,14 +1,28 @@
-use cranelift::prelude::{types::F64, FloatCC, FunctionBuilder, InstBuilder, Type, Value};
+use cranelift::prelude::{
+    types::{F64, F64X2},
+    FloatCC, FunctionBuilder, InstBuilder, MemFlags, Type, Value,
+};
 
-use super::AnitaType;
+use super::{AnitaType, ValueType};
+use crate::jit::frontend::optimizer::Operation;
 
 impl AnitaType for f64 {
+    type Lowered = Value;
+
     fn cranelift_repr() -> Type {
         F64
     }
 
-    fn constant(builder: &mut FunctionBuilder, value: f32) -> Value {
-        builder.ins().f64const(value as f64)
+    fn value_type() -> Option<ValueType> {
+        Some(ValueType::F64)
+    }
+
+    fn cranelift_vector_repr() -> Option<Type> {
+        Some(F64X2)
+    }
+
+    fn constant(builder: &mut FunctionBuilder, value: f64) -> Value {
+        builder.ins().f64const(value)
     }
 
     fn add(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
@@ -62,29 +76,79 @@ impl AnitaType for f64 {
         builder.ins().fcmp(FloatCC::LessThanOrEqual, lhs, rhs)
     }
 
-    fn and(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
-        let zero = builder.ins().f64const(0.0);
-        let two = builder.ins().f64const(2.0);
-        let lhs = builder.ins().fcmp(FloatCC::NotEqual, lhs, zero);
-        let rhs = builder.ins().fcmp(FloatCC::NotEqual, rhs, zero);
-        let intermediate = builder.ins().fadd(lhs, rhs);
-        builder.ins().fcmp(FloatCC::Equal, intermediate, two)
-    }
-
-    fn or(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
-        let zero = builder.ins().f64const(0.0);
-        let lhs = builder.ins().fcmp(FloatCC::NotEqual, lhs, zero);
-        let rhs = builder.ins().fcmp(FloatCC::NotEqual, rhs, zero);
-        let intermediate = builder.ins().fadd(lhs, rhs);
-        builder.ins().fcmp(FloatCC::NotEqual, intermediate, zero)
-    }
-
-    fn not(builder: &mut FunctionBuilder, value: Value) -> Value {
-        let zero = builder.ins().f64const(0.0);
-        builder.ins().fcmp(FloatCC::Equal, value, zero)
-    }
-
-    extern "C" fn inbuilt_pow(self, value: Self) -> Self {
-        self.powf(value)
-    }
-}
\ No newline at end of file
+    fn truthy(builder: &mut FunctionBuilder, value: Value) -> Value {
+        let zero = constant_like(builder, value, 0.0);
+        builder.ins().fcmp(FloatCC::NotEqual, value, zero)
+    }
+
+    fn select(
+        builder: &mut FunctionBuilder,
+        condition: Value,
+        then: Value,
+        otherwise: Value,
+    ) -> Value {
+        if builder.func.dfg.value_type(then).is_vector() {
+            let mask = builder.ins().bitcast(F64X2, MemFlags::new(), condition);
+            builder.ins().bitselect(mask, then, otherwise)
+        } else {
+            builder.ins().select(condition, then, otherwise)
+        }
+    }
+
+    fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> Value {
+        let (one, zero) = if builder.func.dfg.value_type(condition).is_vector() {
+            let one = builder.ins().f64const(1.0);
+            let zero = builder.ins().f64const(0.0);
+            (
+                builder.ins().splat(F64X2, one),
+                builder.ins().splat(F64X2, zero),
+            )
+        } else {
+            (builder.ins().f64const(1.0), builder.ins().f64const(0.0))
+        };
+        Self::select(builder, condition, one, zero)
+    }
+
+    fn fold(operation: Operation, lhs: f64, rhs: f64) -> Option<f64> {
+        Some(match operation {
+            Operation::Add => lhs + rhs,
+            Operation::Sub => lhs - rhs,
+            Operation::Mul => lhs * rhs,
+            Operation::Div => lhs / rhs,
+            Operation::Mod => lhs - (lhs / rhs).trunc() * rhs,
+            Operation::Exp => lhs.powf(rhs),
+            Operation::Eq => (lhs == rhs) as u8 as f64,
+            Operation::Neq => (lhs != rhs) as u8 as f64,
+            Operation::Gt => (lhs > rhs) as u8 as f64,
+            Operation::Lt => (lhs < rhs) as u8 as f64,
+            Operation::Geq => (lhs >= rhs) as u8 as f64,
+            Operation::Leq => (lhs <= rhs) as u8 as f64,
+            Operation::And => (lhs != 0.0 && rhs != 0.0) as u8 as f64,
+            Operation::Or => (lhs != 0.0 || rhs != 0.0) as u8 as f64,
+        })
+    }
+
+    fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]> {
+        Box::new([
+            ("inbuilt_pow", inbuilt_pow as *const u8, 2),
+            ("inbuilt_ln", inbuilt_ln as *const u8, 1),
+        ])
+    }
+}
+
+extern "C" fn inbuilt_pow(value: f64, exponent: f64) -> f64 {
+    value.powf(exponent)
+}
+
+extern "C" fn inbuilt_ln(value: f64) -> f64 {
+    value.ln()
+}
+
+/// Creates a constant with the same shape (scalar or vector) as `like`.
+fn constant_like(builder: &mut FunctionBuilder, like: Value, value: f64) -> Value {
+    let constant = builder.ins().f64const(value);
+    match builder.func.dfg.value_type(like) {
+        F64X2 => builder.ins().splat(F64X2, constant),
+        _ => constant,
+    }
+}
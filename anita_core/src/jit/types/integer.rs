@@ -0,0 +1,260 @@
+//! Fixed-width integers.
+//!
+//! Arithmetic wraps around on overflow like [`i32::wrapping_add`] and friends.
+//! `/` rounds toward zero and `%` is the truncating remainder, so its sign follows the dividend like for floats.
+//! Division by zero does not trap: `x / 0` is `0` and `x % 0` is `x`, which keeps `x == (x / y) * y + x % y` intact.
+//! `MIN / -1` wraps to `MIN` and `MIN % -1` is `0`.
+//!
+//! Literals are truncated toward zero and wrapped into the range of the type, e.g. `2.7` is `2` and `-1` is `u32::MAX` as a `u32`.
+//! Integer literals keep their exact value beyond the precision of an `f64`, e.g. `18446744073709551615` is `u64::MAX`.
+//! `x ^ y` is the wrapping power and `1 / x ^ -y` for negative exponents.
+//! Comparisons and logical operators return `0` or `1`.
+
+use cranelift::prelude::{
+    types::{I32, I64},
+    FunctionBuilder, InstBuilder, IntCC, Type, Value,
+};
+
+use super::{AnitaType, ValueType};
+use crate::jit::frontend::optimizer::Operation;
+
+macro_rules! integer_type {
+    ($type:ty, $repr:expr, $value_type:ident, $signed:literal) => {
+        impl AnitaType for $type {
+            type Lowered = Value;
+
+            fn cranelift_repr() -> Type {
+                $repr
+            }
+
+            fn value_type() -> Option<ValueType> {
+                Some(ValueType::$value_type)
+            }
+
+            fn constant(builder: &mut FunctionBuilder, value: f64) -> Value {
+                Self::integer_constant(builder, value as i128)
+            }
+
+            /// Wraps the exact value into the range of the type.
+            fn integer_constant(builder: &mut FunctionBuilder, value: i128) -> Value {
+                // Cranelift expects the immediate of narrow types zero-extended.
+                let bits = <$type as Integer>::wrap(value) as i64
+                    & (u64::MAX >> (64 - $repr.bits())) as i64;
+                builder.ins().iconst($repr, bits)
+            }
+
+            fn add(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                builder.ins().iadd(lhs, rhs)
+            }
+
+            fn sub(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                builder.ins().isub(lhs, rhs)
+            }
+
+            fn mul(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                builder.ins().imul(lhs, rhs)
+            }
+
+            fn div(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                divide(builder, lhs, rhs, $signed, false)
+            }
+
+            fn modulo(builder: &mut FunctionBuilder, value: Value, modulus: Value) -> Value {
+                divide(builder, value, modulus, $signed, true)
+            }
+
+            fn neg(builder: &mut FunctionBuilder, value: Value) -> Value {
+                builder.ins().ineg(value)
+            }
+
+            fn eq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                builder.ins().icmp(IntCC::Equal, lhs, rhs)
+            }
+
+            fn neq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                builder.ins().icmp(IntCC::NotEqual, lhs, rhs)
+            }
+
+            fn gt(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                let condition = match $signed {
+                    true => IntCC::SignedGreaterThan,
+                    false => IntCC::UnsignedGreaterThan,
+                };
+                builder.ins().icmp(condition, lhs, rhs)
+            }
+
+            fn lt(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                let condition = match $signed {
+                    true => IntCC::SignedLessThan,
+                    false => IntCC::UnsignedLessThan,
+                };
+                builder.ins().icmp(condition, lhs, rhs)
+            }
+
+            fn geq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                let condition = match $signed {
+                    true => IntCC::SignedGreaterThanOrEqual,
+                    false => IntCC::UnsignedGreaterThanOrEqual,
+                };
+                builder.ins().icmp(condition, lhs, rhs)
+            }
+
+            fn leq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value {
+                let condition = match $signed {
+                    true => IntCC::SignedLessThanOrEqual,
+                    false => IntCC::UnsignedLessThanOrEqual,
+                };
+                builder.ins().icmp(condition, lhs, rhs)
+            }
+
+            fn truthy(builder: &mut FunctionBuilder, value: Value) -> Value {
+                builder.ins().icmp_imm(IntCC::NotEqual, value, 0)
+            }
+
+            fn select(
+                builder: &mut FunctionBuilder,
+                condition: Value,
+                then: Value,
+                otherwise: Value,
+            ) -> Value {
+                builder.ins().select(condition, then, otherwise)
+            }
+
+            fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> Value {
+                builder.ins().uextend($repr, condition)
+            }
+
+            /// Folds with the wrapping arithmetic of the type.
+            ///
+            /// Results that can not be represented exactly as an `f64` literal are left for runtime.
+            fn fold(operation: Operation, lhs: f64, rhs: f64) -> Option<f64> {
+                let (lhs, rhs) = (from_literal::<$type>(lhs), from_literal::<$type>(rhs));
+                let folded: $type = match operation {
+                    Operation::Add => lhs.wrapping_add(rhs),
+                    Operation::Sub => lhs.wrapping_sub(rhs),
+                    Operation::Mul => lhs.wrapping_mul(rhs),
+                    Operation::Div => match rhs {
+                        0 => 0,
+                        _ => lhs.wrapping_div(rhs),
+                    },
+                    Operation::Mod => match rhs {
+                        0 => lhs,
+                        _ => lhs.wrapping_rem(rhs),
+                    },
+                    Operation::Exp => inbuilt_pow(lhs, rhs),
+                    Operation::Eq => (lhs == rhs) as $type,
+                    Operation::Neq => (lhs != rhs) as $type,
+                    Operation::Gt => (lhs > rhs) as $type,
+                    Operation::Lt => (lhs < rhs) as $type,
+                    Operation::Geq => (lhs >= rhs) as $type,
+                    Operation::Leq => (lhs <= rhs) as $type,
+                    Operation::And => (lhs != 0 && rhs != 0) as $type,
+                    Operation::Or => (lhs != 0 || rhs != 0) as $type,
+                };
+                let value = folded as f64;
+                (value as i128 == folded as i128).then_some(value)
+            }
+
+            fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]> {
+                let pow: extern "C" fn($type, $type) -> $type = inbuilt_pow;
+                Box::new([("inbuilt_pow", pow as *const u8, 2)])
+            }
+        }
+
+        impl Integer for $type {
+            fn wrap(value: i128) -> Self {
+                value as $type
+            }
+
+            fn exponent(self) -> (bool, u64) {
+                let exponent = self as i128;
+                (exponent < 0, exponent.unsigned_abs() as u64)
+            }
+
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                self.wrapping_mul(rhs)
+            }
+
+            fn wrapping_div(self, rhs: Self) -> Self {
+                match rhs {
+                    0 => 0,
+                    _ => self.wrapping_div(rhs),
+                }
+            }
+        }
+    };
+}
+
+integer_type!(i32, I32, I32, true);
+integer_type!(i64, I64, I64, true);
+integer_type!(u32, I32, U32, false);
+integer_type!(u64, I64, U64, false);
+
+/// Operations shared by the integer types, used to implement them generically.
+trait Integer: Copy {
+    fn wrap(value: i128) -> Self;
+    /// Returns whether `self` is negative and its magnitude when used as an exponent.
+    fn exponent(self) -> (bool, u64);
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    /// Wrapping division that returns `0` for a zero divisor.
+    fn wrapping_div(self, rhs: Self) -> Self;
+}
+
+/// Truncates a literal toward zero and wraps it into the range of `T`.
+fn from_literal<T: Integer>(value: f64) -> T {
+    T::wrap(value as i128)
+}
+
+extern "C" fn inbuilt_pow<T: Integer>(base: T, exponent: T) -> T {
+    let (negative, mut exponent) = exponent.exponent();
+    let (mut base, mut power) = (base, T::wrap(1));
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            power = power.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    match negative {
+        true => T::wrap(1).wrapping_div(power),
+        false => power,
+    }
+}
+
+/// Lowers `lhs / rhs` or `lhs % rhs` without trapping for a zero divisor or `MIN / -1`.
+fn divide(
+    builder: &mut FunctionBuilder,
+    lhs: Value,
+    rhs: Value,
+    signed: bool,
+    remainder: bool,
+) -> Value {
+    let repr = builder.func.dfg.value_type(lhs);
+    let by_zero = builder.ins().icmp_imm(IntCC::Equal, rhs, 0);
+    let one = builder.ins().iconst(repr, 1);
+    let result = if signed {
+        // Dividing by -1 is a negation, which wraps instead of trapping.
+        let by_minus_one = builder.ins().icmp_imm(IntCC::Equal, rhs, -1);
+        let replaced = builder.ins().bor(by_zero, by_minus_one);
+        let divisor = builder.ins().select(replaced, one, rhs);
+        match remainder {
+            true => builder.ins().srem(lhs, divisor),
+            false => {
+                let quotient = builder.ins().sdiv(lhs, divisor);
+                let negated = builder.ins().ineg(lhs);
+                builder.ins().select(by_minus_one, negated, quotient)
+            }
+        }
+    } else {
+        let divisor = builder.ins().select(by_zero, one, rhs);
+        match remainder {
+            true => builder.ins().urem(lhs, divisor),
+            false => builder.ins().udiv(lhs, divisor),
+        }
+    };
+    let zero_divisor_result = match remainder {
+        true => lhs,
+        false => builder.ins().iconst(repr, 0),
+    };
+    builder.ins().select(by_zero, zero_divisor_result, result)
+}
@@ -0,0 +1,675 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    marker::PhantomData,
+};
+
+use cranelift::prelude::{
+    isa::CallConv, FloatCC, FunctionBuilder, InstBuilder, Signature, Type, Value,
+};
+
+use super::{output_arity, output_signature, pointer_type, AnitaType, FunctionCall, Real};
+use crate::{function_manager::FunctionManager, jit::TranslatorError};
+
+/// A closed interval `[lower, upper]` that encloses every value an expression can take.
+///
+/// Evaluating an expression on intervals of its parameters results in an interval that contains its value for all parameters in them.
+/// Every operation rounds its bounds outward, so the result is sound despite rounding errors.
+/// Literals that can not be represented exactly are widened to the neighbouring numbers, and literals are not folded at compile time.
+///
+/// Comparisons can be undecided, e.g. `[0, 2] < [1, 3]` is possibly true and possibly false.
+/// Converted to numbers, conditions are `[1, 1]` if they are certainly true, `[0, 0]` if they are certainly false and `[0, 1]` otherwise.
+/// Conditionals with an undecided condition evaluate both branches and result in the union of both, the same applies to assignments in them.
+///
+/// Division by an interval containing `0` results in `[-inf, inf]`.
+/// Bounds that are not a number, e.g. of `[0, 0] * [inf, inf]`, are NaN.
+/// Functions take and return intervals, see [`IntervalFunctions`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Interval<T> {
+    pub lower: T,
+    pub upper: T,
+}
+
+impl<T> Interval<T> {
+    pub const fn new(lower: T, upper: T) -> Self {
+        Self { lower, upper }
+    }
+}
+
+impl<T: Copy> Interval<T> {
+    /// The interval only containing `value`.
+    pub fn point(value: T) -> Self {
+        Self::new(value, value)
+    }
+}
+
+/// Conditions are `I8` values with the bit `POSSIBLY` set if they can be true and `CERTAINLY` if they are true for all values.
+const POSSIBLY: i64 = 1;
+const CERTAINLY: i64 = 2;
+
+impl<T: Real> AnitaType for Interval<T> {
+    type Lowered = [Value; 2];
+
+    fn cranelift_repr() -> Type {
+        T::cranelift_repr()
+    }
+
+    fn constant(builder: &mut FunctionBuilder, value: f64) -> [Value; 2] {
+        let nearest = T::from_f64(value);
+        // Literals with a fraction may have been rounded when they were parsed.
+        let exact = !value.is_finite() || (value.fract() == 0.0 && nearest.to_f64() == value);
+        let (lower, upper) = match exact {
+            true => (nearest, nearest),
+            false => (nearest.next_down(), nearest.next_up()),
+        };
+        [
+            T::constant(builder, lower.to_f64()),
+            T::constant(builder, upper.to_f64()),
+        ]
+    }
+
+    fn add(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        let lower = builder.ins().fadd(a, c);
+        let upper = builder.ins().fadd(b, d);
+        outward::<T>(builder, lower, upper)
+    }
+
+    fn sub(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        let lower = builder.ins().fsub(a, d);
+        let upper = builder.ins().fsub(b, c);
+        outward::<T>(builder, lower, upper)
+    }
+
+    fn mul(builder: &mut FunctionBuilder, lhs: [Value; 2], rhs: [Value; 2]) -> [Value; 2] {
+        // Both operands are the same number, e.g. in `x * x`, so the result is not negative.
+        if lhs == rhs {
+            return square::<T>(builder, lhs);
+        }
+        let [a, b] = lhs;
+        let [c, d] = rhs;
+        let products = [
+            builder.ins().fmul(a, c),
+            builder.ins().fmul(a, d),
+            builder.ins().fmul(b, c),
+            builder.ins().fmul(b, d),
+        ];
+        hull::<T>(builder, products)
+    }
+
+    fn div(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        let quotients = [
+            builder.ins().fdiv(a, c),
+            builder.ins().fdiv(a, d),
+            builder.ins().fdiv(b, c),
+            builder.ins().fdiv(b, d),
+        ];
+        let [lower, upper] = hull::<T>(builder, quotients);
+        let zero = T::constant(builder, 0.0);
+        let from_below = builder.ins().fcmp(FloatCC::LessThanOrEqual, c, zero);
+        let to_above = builder.ins().fcmp(FloatCC::GreaterThanOrEqual, d, zero);
+        let contains_zero = builder.ins().band(from_below, to_above);
+        let infinity = T::constant(builder, f64::INFINITY);
+        let negative_infinity = T::constant(builder, f64::NEG_INFINITY);
+        [
+            builder
+                .ins()
+                .select(contains_zero, negative_infinity, lower),
+            builder.ins().select(contains_zero, infinity, upper),
+        ]
+    }
+
+    /// The remainder has the sign of the dividend, and its magnitude is below the divisor and at most the dividend.
+    fn modulo(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> [Value; 2] {
+        let (c, d) = (builder.ins().fabs(c), builder.ins().fabs(d));
+        let magnitude = builder.ins().fmax(c, d);
+        let negative_magnitude = builder.ins().fneg(magnitude);
+        let zero = T::constant(builder, 0.0);
+        let lower = builder.ins().fmax(a, negative_magnitude);
+        let upper = builder.ins().fmin(b, magnitude);
+        [
+            builder.ins().fmin(lower, zero),
+            builder.ins().fmax(upper, zero),
+        ]
+    }
+
+    fn neg(builder: &mut FunctionBuilder, [a, b]: [Value; 2]) -> [Value; 2] {
+        [builder.ins().fneg(b), builder.ins().fneg(a)]
+    }
+
+    fn eq(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let equal_lower = builder.ins().fcmp(FloatCC::Equal, a, d);
+        let equal_upper = builder.ins().fcmp(FloatCC::Equal, b, c);
+        let certainly = builder.ins().band(equal_lower, equal_upper);
+        let below = builder
+            .ins()
+            .fcmp(FloatCC::UnorderedOrLessThanOrEqual, a, d);
+        let above = builder
+            .ins()
+            .fcmp(FloatCC::UnorderedOrLessThanOrEqual, c, b);
+        let possibly = builder.ins().band(below, above);
+        condition(builder, certainly, possibly)
+    }
+
+    fn neq(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let below = builder.ins().fcmp(FloatCC::LessThan, b, c);
+        let above = builder.ins().fcmp(FloatCC::LessThan, d, a);
+        let certainly = builder.ins().bor(below, above);
+        let different_lower = builder.ins().fcmp(FloatCC::NotEqual, a, d);
+        let different_upper = builder.ins().fcmp(FloatCC::NotEqual, b, c);
+        let possibly = builder.ins().bor(different_lower, different_upper);
+        condition(builder, certainly, possibly)
+    }
+
+    fn gt(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let certainly = builder.ins().fcmp(FloatCC::GreaterThan, a, d);
+        let possibly = builder.ins().fcmp(FloatCC::UnorderedOrGreaterThan, b, c);
+        condition(builder, certainly, possibly)
+    }
+
+    fn lt(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let certainly = builder.ins().fcmp(FloatCC::LessThan, b, c);
+        let possibly = builder.ins().fcmp(FloatCC::UnorderedOrLessThan, a, d);
+        condition(builder, certainly, possibly)
+    }
+
+    fn geq(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let certainly = builder.ins().fcmp(FloatCC::GreaterThanOrEqual, a, d);
+        let possibly = builder
+            .ins()
+            .fcmp(FloatCC::UnorderedOrGreaterThanOrEqual, b, c);
+        condition(builder, certainly, possibly)
+    }
+
+    fn leq(builder: &mut FunctionBuilder, [a, b]: [Value; 2], [c, d]: [Value; 2]) -> Value {
+        let certainly = builder.ins().fcmp(FloatCC::LessThanOrEqual, b, c);
+        let possibly = builder
+            .ins()
+            .fcmp(FloatCC::UnorderedOrLessThanOrEqual, a, d);
+        condition(builder, certainly, possibly)
+    }
+
+    fn truthy(builder: &mut FunctionBuilder, value: [Value; 2]) -> Value {
+        let zero = T::constant(builder, 0.0);
+        Self::neq(builder, value, [zero, zero])
+    }
+
+    /// Picks `then` if the condition is certainly true, `otherwise` if it is certainly false and the union of both if it is undecided.
+    fn select(
+        builder: &mut FunctionBuilder,
+        condition: Value,
+        then: [Value; 2],
+        otherwise: [Value; 2],
+    ) -> [Value; 2] {
+        let (certainly, possibly) = decompose(builder, condition);
+        let union = [
+            builder.ins().fmin(then[0], otherwise[0]),
+            builder.ins().fmax(then[1], otherwise[1]),
+        ];
+        [0, 1].map(|bound| {
+            let undecided = builder
+                .ins()
+                .select(possibly, union[bound], otherwise[bound]);
+            builder.ins().select(certainly, then[bound], undecided)
+        })
+    }
+
+    fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> [Value; 2] {
+        let (certainly, possibly) = decompose(builder, condition);
+        [
+            T::from_condition(builder, certainly),
+            T::from_condition(builder, possibly),
+        ]
+    }
+
+    fn decided_conditions() -> bool {
+        false
+    }
+
+    /// `x * x * x` treats the factors as independent, `pow` does not.
+    fn expand_powers() -> bool {
+        false
+    }
+
+    /// A condition is possibly true if it is not certainly false, and certainly true if it is not possibly false.
+    fn invert(builder: &mut FunctionBuilder, condition: Value) -> Value {
+        let (certainly, possibly) = decompose(builder, condition);
+        let certainly = builder.ins().bxor_imm(certainly, 1);
+        let possibly = builder.ins().bxor_imm(possibly, 1);
+        // The bits swap roles.
+        self::condition(builder, possibly, certainly)
+    }
+
+    /// Functions take the bounds of every argument followed by a pointer to the `Interval<T>` they write their result to.
+    fn function_signature(arity: usize, call_conv: CallConv, pointer_type: Type) -> Signature {
+        output_signature(T::cranelift_repr(), 2, arity, call_conv, pointer_type)
+    }
+
+    fn function_arity(signature: &Signature) -> usize {
+        output_arity(signature, 2)
+    }
+
+    fn call<'a, C: FunctionCall<'a>>(
+        call: &mut C,
+        args: &[[Value; 2]],
+    ) -> Result<[Value; 2], TranslatorError> {
+        let args: Vec<Value> = args.iter().flatten().copied().collect();
+        match call.call_with_output(&args, 2)[..] {
+            [lower, upper] => Ok([lower, upper]),
+            _ => unreachable!("two results were requested"),
+        }
+    }
+
+    fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]> {
+        Box::new([
+            ("inbuilt_pow", pow::<T> as *const u8, 2),
+            ("inbuilt_ln", ln::<T> as *const u8, 1),
+        ])
+    }
+}
+
+/// Combines two `I8` flags that are `0` or `1` into a condition.
+fn condition(builder: &mut FunctionBuilder, certainly: Value, possibly: Value) -> Value {
+    let certainly = builder.ins().imul_imm(certainly, CERTAINLY);
+    let possibly = builder.ins().imul_imm(possibly, POSSIBLY);
+    builder.ins().bor(certainly, possibly)
+}
+
+/// Splits a condition into the flags whether it is certainly and whether it is possibly true.
+fn decompose(builder: &mut FunctionBuilder, condition: Value) -> (Value, Value) {
+    let certainly = builder.ins().band_imm(condition, CERTAINLY);
+    let certainly = builder.ins().ushr_imm(certainly, 1);
+    let possibly = builder.ins().band_imm(condition, POSSIBLY);
+    (certainly, possibly)
+}
+
+/// Moves a bound computed with rounding to nearest by at least one unit in the last place, down if `up` is `false`.
+///
+/// `|x| * EPSILON + TINY` is at least the distance to the neighbouring number, so adding it can not round back to `x`.
+/// Infinite and NaN bounds are kept.
+fn round<T: Real>(builder: &mut FunctionBuilder, value: Value, up: bool) -> Value {
+    let magnitude = builder.ins().fabs(value);
+    let epsilon = T::constant(builder, T::EPSILON);
+    let tiny = T::constant(builder, T::TINY);
+    let distance = builder.ins().fmul(magnitude, epsilon);
+    let distance = builder.ins().fadd(distance, tiny);
+    let rounded = match up {
+        true => builder.ins().fadd(value, distance),
+        false => builder.ins().fsub(value, distance),
+    };
+    // Infinity minus infinity is NaN.
+    let not_a_number = builder.ins().fcmp(FloatCC::Unordered, rounded, rounded);
+    builder.ins().select(not_a_number, value, rounded)
+}
+
+fn outward<T: Real>(builder: &mut FunctionBuilder, lower: Value, upper: Value) -> [Value; 2] {
+    [
+        round::<T>(builder, lower, false),
+        round::<T>(builder, upper, true),
+    ]
+}
+
+/// The smallest interval containing all `values`, rounded outward.
+fn hull<T: Real>(builder: &mut FunctionBuilder, [a, b, c, d]: [Value; 4]) -> [Value; 2] {
+    let (ab, cd) = (builder.ins().fmin(a, b), builder.ins().fmin(c, d));
+    let lower = builder.ins().fmin(ab, cd);
+    let (ab, cd) = (builder.ins().fmax(a, b), builder.ins().fmax(c, d));
+    let upper = builder.ins().fmax(ab, cd);
+    outward::<T>(builder, lower, upper)
+}
+
+fn square<T: Real>(builder: &mut FunctionBuilder, [a, b]: [Value; 2]) -> [Value; 2] {
+    let (aa, bb) = (builder.ins().fmul(a, a), builder.ins().fmul(b, b));
+    let lower = builder.ins().fmin(aa, bb);
+    let upper = builder.ins().fmax(aa, bb);
+    let [lower, upper] = outward::<T>(builder, lower, upper);
+    let zero = T::constant(builder, 0.0);
+    let from_below = builder.ins().fcmp(FloatCC::LessThanOrEqual, a, zero);
+    let to_above = builder.ins().fcmp(FloatCC::GreaterThanOrEqual, b, zero);
+    let contains_zero = builder.ins().band(from_below, to_above);
+    let lower = builder.ins().fmax(lower, zero);
+    [builder.ins().select(contains_zero, zero, lower), upper]
+}
+
+/// The functions of [`DefaultFunctions`](../../../anita/default_functions/struct.DefaultFunctions.html) on [`Interval`]s with bounds of type `T`.
+///
+/// Every function returns an interval containing its value for all arguments in the argument intervals.
+/// Arguments are restricted to the domain of the function, e.g. `sqrt([-1, 4])` is `[0, 2]`, and intervals outside of it result in NaN bounds.
+/// `is_nan`, `is_finite`, `is_infinite` and `is_normal` return `[0, 1]` if they are true for some values and false for others.
+pub struct IntervalFunctions<T> {
+    _type: PhantomData<T>,
+}
+
+impl<T: Real> FunctionManager for IntervalFunctions<T> {
+    fn function_symbols() -> Box<[(&'static str, *const u8)]> {
+        Box::new([
+            ("min", min::<T> as *const u8),
+            ("max", max::<T> as *const u8),
+            ("floor", floor::<T> as *const u8),
+            ("round", round_half_away::<T> as *const u8),
+            ("ceil", ceil::<T> as *const u8),
+            ("is_nan", is_nan::<T> as *const u8),
+            ("is_finite", is_finite::<T> as *const u8),
+            ("is_infinite", is_infinite::<T> as *const u8),
+            ("is_normal", is_normal::<T> as *const u8),
+            ("pow", pow::<T> as *const u8),
+            ("mod", remainder::<T> as *const u8),
+            ("ln", ln::<T> as *const u8),
+            ("log2", log2::<T> as *const u8),
+            ("log10", log10::<T> as *const u8),
+            ("exp", exp::<T> as *const u8),
+            ("exp2", exp2::<T> as *const u8),
+            ("cos", cos::<T> as *const u8),
+            ("acos", acos::<T> as *const u8),
+            ("cosh", cosh::<T> as *const u8),
+            ("acosh", acosh::<T> as *const u8),
+            ("sin", sin::<T> as *const u8),
+            ("asin", asin::<T> as *const u8),
+            ("sinh", sinh::<T> as *const u8),
+            ("asinh", asinh::<T> as *const u8),
+            ("tan", tan::<T> as *const u8),
+            ("atan", atan::<T> as *const u8),
+            ("atan2", atan2::<T> as *const u8),
+            ("tanh", tanh::<T> as *const u8),
+            ("atanh", atanh::<T> as *const u8),
+            ("sqrt", sqrt::<T> as *const u8),
+            ("cbrt", cbrt::<T> as *const u8),
+            ("abs", abs::<T> as *const u8),
+            ("hypot", hypot::<T> as *const u8),
+        ])
+    }
+
+    fn function_signature(identifier: &str, calling_convention: CallConv) -> Option<Signature> {
+        let arity = match identifier {
+            "min" | "max" | "pow" | "mod" | "atan2" | "hypot" => 2,
+            _ if Self::function_symbols()
+                .iter()
+                .any(|(name, _)| *name == identifier) =>
+            {
+                1
+            }
+            _ => return None,
+        };
+        Some(Interval::<T>::function_signature(
+            arity,
+            calling_convention,
+            pointer_type(),
+        ))
+    }
+}
+
+type Bounds = Interval<f64>;
+
+impl Bounds {
+    const ENTIRE: Bounds = Interval::new(f64::NEG_INFINITY, f64::INFINITY);
+    const NAN: Bounds = Interval::new(f64::NAN, f64::NAN);
+    const TRUE: Bounds = Interval::new(1.0, 1.0);
+    const FALSE: Bounds = Interval::new(0.0, 0.0);
+    const UNDECIDED: Bounds = Interval::new(0.0, 1.0);
+
+    fn has_nan(self) -> bool {
+        self.lower.is_nan() || self.upper.is_nan()
+    }
+
+    fn contains(self, value: f64) -> bool {
+        self.lower <= value && value <= self.upper
+    }
+
+    /// Widens the bounds of a function that is not exactly rounded by one unit in the last place.
+    fn widen(self) -> Bounds {
+        Interval::new(self.lower.next_down(), self.upper.next_up())
+    }
+
+    /// Restricts the interval to `[lower, upper]`, an interval outside of it becomes NaN.
+    fn restrict(self, lower: f64, upper: f64) -> Bounds {
+        let restricted = Interval::new(self.lower.max(lower), self.upper.min(upper));
+        match restricted.lower <= restricted.upper {
+            true => restricted,
+            false => Bounds::NAN,
+        }
+    }
+
+    fn abs(self) -> Bounds {
+        match (self.lower >= 0.0, self.upper <= 0.0) {
+            (true, _) => self,
+            (_, true) => Interval::new(-self.upper, -self.lower),
+            _ => Interval::new(0.0, self.upper.max(-self.lower)),
+        }
+    }
+
+    fn truth(certainly: bool, possibly: bool) -> Bounds {
+        match (certainly, possibly) {
+            (true, _) => Bounds::TRUE,
+            (false, true) => Bounds::UNDECIDED,
+            (false, false) => Bounds::FALSE,
+        }
+    }
+}
+
+/// Applies a non-decreasing function exactly rounded to nearest, like `floor`.
+fn exact(x: Bounds, f: fn(f64) -> f64) -> Bounds {
+    Interval::new(f(x.lower), f(x.upper))
+}
+
+/// Applies a function that is non-decreasing on its domain `[lower, upper]`.
+fn increasing(x: Bounds, lower: f64, upper: f64, f: fn(f64) -> f64) -> Bounds {
+    let x = x.restrict(lower, upper);
+    Interval::new(f(x.lower), f(x.upper)).widen()
+}
+
+/// Applies a function that is non-increasing on its domain `[lower, upper]`.
+fn decreasing(x: Bounds, lower: f64, upper: f64, f: fn(f64) -> f64) -> Bounds {
+    let x = x.restrict(lower, upper);
+    Interval::new(f(x.upper), f(x.lower)).widen()
+}
+
+/// The hull of `f` at the corners of `x` and `y`, for functions that are monotonic in each argument.
+fn corners(x: Bounds, y: Bounds, f: fn(f64, f64) -> f64) -> Bounds {
+    let values = [
+        f(x.lower, y.lower),
+        f(x.lower, y.upper),
+        f(x.upper, y.lower),
+        f(x.upper, y.upper),
+    ];
+    let lower = values.into_iter().fold(f64::INFINITY, f64::min);
+    let upper = values.into_iter().fold(f64::NEG_INFINITY, f64::max);
+    match values.iter().any(|value| value.is_nan()) {
+        true => Bounds::NAN,
+        false => Interval::new(lower, upper).widen(),
+    }
+}
+
+/// Returns whether `x` may contain `offset + k * period` for an integer `k`.
+///
+/// The check is widened to stay correct despite `period` being rounded.
+fn may_contain_period(x: Bounds, offset: f64, period: f64) -> bool {
+    let slack = 1e-12 * x.lower.abs().max(x.upper.abs()).max(1.0);
+    let k = ((x.lower - slack - offset) / period).ceil();
+    offset + k * period <= x.upper + slack
+}
+
+/// `sin` or `cos`, which have the value `1` at `maximum + k * 2π` and `-1` at `minimum + k * 2π`.
+fn periodic(x: Bounds, f: fn(f64) -> f64, maximum: f64, minimum: f64) -> Bounds {
+    if x.has_nan() {
+        return Bounds::NAN;
+    }
+    let width = x.upper - x.lower;
+    // Infinite bounds have a NaN width.
+    if width.is_nan() || width >= TAU || x.lower.abs().max(x.upper.abs()) > 1e15 {
+        return Interval::new(-1.0, 1.0);
+    }
+    let values = Interval::new(f(x.lower).min(f(x.upper)), f(x.lower).max(f(x.upper))).widen();
+    let lower = match may_contain_period(x, minimum, TAU) {
+        true => -1.0,
+        false => values.lower.max(-1.0),
+    };
+    let upper = match may_contain_period(x, maximum, TAU) {
+        true => 1.0,
+        false => values.upper.min(1.0),
+    };
+    Interval::new(lower, upper)
+}
+
+fn tangent(x: Bounds) -> Bounds {
+    if x.has_nan() {
+        return Bounds::NAN;
+    }
+    let width = x.upper - x.lower;
+    if width.is_nan() || width >= PI || may_contain_period(x, FRAC_PI_2, PI) {
+        return Bounds::ENTIRE;
+    }
+    Interval::new(x.lower.tan(), x.upper.tan()).widen()
+}
+
+/// `atan2(y, x)`, which is continuous except at the origin and on the negative `x` axis.
+fn angle(y: Bounds, x: Bounds) -> Bounds {
+    if y.has_nan() || x.has_nan() {
+        return Bounds::NAN;
+    }
+    if y.contains(0.0) && x.lower <= 0.0 {
+        return Interval::new(-PI, PI).widen();
+    }
+    corners(y, x, f64::atan2)
+}
+
+fn power(x: Bounds, y: Bounds) -> Bounds {
+    if y.lower == y.upper && y.lower.fract() == 0.0 && y.lower.abs() <= i32::MAX as f64 {
+        return integer_power(x, y.lower as i32);
+    }
+    corners(x.restrict(0.0, f64::INFINITY), y, f64::powf)
+}
+
+/// `x ^ n`, with the bounds computed by `powf`.
+///
+/// `powi` multiplies repeatedly, so its error grows with `n` and can not be covered by widening.
+fn integer_power(x: Bounds, n: i32) -> Bounds {
+    if n == 0 {
+        return Bounds::TRUE;
+    }
+    let magnitude = n.unsigned_abs();
+    let exponent = magnitude as f64;
+    let even = magnitude.is_multiple_of(2);
+    let power = match (even, x.abs()) {
+        (true, abs) => Interval::new(abs.lower.powf(exponent), abs.upper.powf(exponent)),
+        (false, _) => Interval::new(x.lower.powf(exponent), x.upper.powf(exponent)),
+    }
+    .widen();
+    let power = match even {
+        true => Interval::new(power.lower.max(0.0), power.upper),
+        false => power,
+    };
+    match (
+        n < 0,
+        power.lower > 0.0 || power.upper < 0.0,
+        power.lower >= 0.0,
+    ) {
+        (false, _, _) => power,
+        (true, true, _) => Interval::new(1.0 / power.upper, 1.0 / power.lower).widen(),
+        (true, false, true) => Interval::new((1.0 / power.upper).next_down(), f64::INFINITY),
+        (true, false, false) => Bounds::ENTIRE,
+    }
+}
+
+fn modulus(x: Bounds, y: Bounds) -> Bounds {
+    let magnitude = y.abs().upper;
+    Interval::new(
+        x.lower.max(-magnitude).min(0.0),
+        x.upper.min(magnitude).max(0.0),
+    )
+}
+
+fn classify(x: Bounds, f: fn(f64) -> bool) -> Bounds {
+    let (lower, upper) = (f(x.lower), f(x.upper));
+    // Between the bounds are all values with the same classification, unless they have a different sign or the bounds are NaN.
+    match x.has_nan() || x.lower.is_sign_negative() != x.upper.is_sign_negative() {
+        true => Bounds::truth(false, true),
+        false => Bounds::truth(lower && upper, lower || upper),
+    }
+}
+
+/// Converts a bound computed in `f64` to `T`, rounding down if `up` is `false` and up otherwise.
+fn convert<T: Real>(value: f64, up: bool) -> T {
+    let nearest = T::from_f64(value);
+    match (up, nearest.to_f64()) {
+        (false, converted) if converted > value => nearest.next_down(),
+        (true, converted) if converted < value => nearest.next_up(),
+        _ => nearest,
+    }
+}
+
+fn write<T: Real>(out: *mut Interval<T>, bounds: Bounds) {
+    let result = Interval::new(convert(bounds.lower, false), convert(bounds.upper, true));
+    // SAFETY: the translator passes a stack slot that fits an `Interval<T>`.
+    unsafe { out.write(result) };
+}
+
+macro_rules! unary {
+    ($($name:ident => $function:expr),* $(,)?) => {
+        $(
+            extern "C" fn $name<T: Real>(lower: T, upper: T, out: *mut Interval<T>) {
+                let f: fn(Bounds) -> Bounds = $function;
+                write(out, f(Interval::new(lower.to_f64(), upper.to_f64())));
+            }
+        )*
+    };
+}
+
+macro_rules! binary {
+    ($($name:ident => $function:expr),* $(,)?) => {
+        $(
+            extern "C" fn $name<T: Real>(
+                lower: T,
+                upper: T,
+                other_lower: T,
+                other_upper: T,
+                out: *mut Interval<T>,
+            ) {
+                let f: fn(Bounds, Bounds) -> Bounds = $function;
+                let x = Interval::new(lower.to_f64(), upper.to_f64());
+                let y = Interval::new(other_lower.to_f64(), other_upper.to_f64());
+                write(out, f(x, y));
+            }
+        )*
+    };
+}
+
+unary! {
+    floor => |x| exact(x, f64::floor),
+    round_half_away => |x| exact(x, f64::round),
+    ceil => |x| exact(x, f64::ceil),
+    abs => Bounds::abs,
+    is_nan => |x| Bounds::truth(x.lower.is_nan() && x.upper.is_nan(), x.has_nan()),
+    is_finite => |x| classify(x, f64::is_finite),
+    is_infinite => |x| classify(x, f64::is_infinite),
+    is_normal => |x| match x.contains(0.0) {
+        true => Bounds::truth(false, x.lower.is_normal() || x.upper.is_normal() || x.lower != x.upper),
+        false => classify(x, f64::is_normal),
+    },
+    ln => |x| increasing(x, 0.0, f64::INFINITY, f64::ln),
+    log2 => |x| increasing(x, 0.0, f64::INFINITY, f64::log2),
+    log10 => |x| increasing(x, 0.0, f64::INFINITY, f64::log10),
+    exp => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::exp),
+    exp2 => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::exp2),
+    cos => |x| periodic(x, f64::cos, 0.0, PI),
+    acos => |x| decreasing(x, -1.0, 1.0, f64::acos),
+    cosh => |x| increasing(x.abs(), 0.0, f64::INFINITY, f64::cosh),
+    acosh => |x| increasing(x, 1.0, f64::INFINITY, f64::acosh),
+    sin => |x| periodic(x, f64::sin, FRAC_PI_2, -FRAC_PI_2),
+    asin => |x| increasing(x, -1.0, 1.0, f64::asin),
+    sinh => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::sinh),
+    asinh => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::asinh),
+    tan => tangent,
+    atan => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::atan),
+    tanh => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::tanh),
+    atanh => |x| increasing(x, -1.0, 1.0, f64::atanh),
+    sqrt => |x| increasing(x, 0.0, f64::INFINITY, f64::sqrt),
+    cbrt => |x| increasing(x, f64::NEG_INFINITY, f64::INFINITY, f64::cbrt),
+}
+
+binary! {
+    min => |x, y| Interval::new(x.lower.min(y.lower), x.upper.min(y.upper)),
+    max => |x, y| Interval::new(x.lower.max(y.lower), x.upper.max(y.upper)),
+    pow => power,
+    remainder => modulus,
+    atan2 => angle,
+    hypot => |x, y| corners(x.abs(), y.abs(), f64::hypot),
+}
@@ -1,27 +1,284 @@
-use cranelift::prelude::{FunctionBuilder, Type, Value};
+use cranelift::prelude::{
+    isa::CallConv, AbiParam, FunctionBuilder, InstBuilder, IntCC, Signature, Type, Value,
+};
+
+use super::{frontend::optimizer::Operation, TranslatorError};
+
+pub use complex::{Complex, ComplexFunctions};
+pub use dual::Dual;
+pub use interval::{Interval, IntervalFunctions};
+pub use real::Real;
+pub use value_type::{Primitive, ValueType};
+pub use vector::{Vec2, Vec3, Vec4};
 
 pub trait AnitaType {
+    /// The Cranelift values a number is lowered to.
+    ///
+    /// Numbers that fit into a single Cranelift value use [`Value`], numbers with several components an array with one value per component.
+    type Lowered: Lowered;
+
+    /// Type of every component of a number.
+    ///
+    /// Parameters and results in memory are laid out as their components one after another, like a `#[repr(C)]` struct.
     fn cranelift_repr() -> Type;
 
-    fn constant(builder: &mut FunctionBuilder, value: f32) -> Value;
-    fn add(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn sub(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn mul(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn div(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn modulo(builder: &mut FunctionBuilder, value: Value, modulus: Value) -> Value;
-    fn neg(builder: &mut FunctionBuilder, value: Value) -> Value;
-    fn eq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn neq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn gt(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn lt(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn geq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn leq(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn and(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn or(builder: &mut FunctionBuilder, lhs: Value, rhs: Value) -> Value;
-    fn not(builder: &mut FunctionBuilder, value: Value) -> Value;
-
-    extern "C" fn inbuilt_pow(self, value: Self) -> Self;
+    /// The [`ValueType`] of the number if it is one, which makes casts to it like `x as f64` a no-op.
+    fn value_type() -> Option<ValueType> {
+        None
+    }
+
+    /// Vector type used when lowering batch evaluation loops.
+    ///
+    /// Returning `None` makes batch evaluation process one element per iteration.
+    /// If a vector type is returned, the arithmetic hooks below must also accept values of that type.
+    /// Only numbers with a single component are vectorized.
+    fn cranelift_vector_repr() -> Option<Type> {
+        None
+    }
+
+    /// Broadcasts every component of a number into every lane of [`AnitaType::cranelift_vector_repr`].
+    fn splat(builder: &mut FunctionBuilder, value: Self::Lowered) -> Self::Lowered {
+        let vector_type = Self::cranelift_vector_repr()
+            .expect("splat requires cranelift_vector_repr to return a vector type");
+        let components: Vec<Value> = value
+            .values()
+            .into_iter()
+            .map(|component| builder.ins().splat(vector_type, component))
+            .collect();
+        Self::Lowered::from_values(&components)
+    }
+
+    /// Creates a literal.
+    ///
+    /// Literals are parsed at full `f64` precision and converted to the closest representable value here.
+    fn constant(builder: &mut FunctionBuilder, value: f64) -> Self::Lowered;
+
+    /// Creates an integer literal that an `f64` can not hold exactly, see [`Expr::Integer`](crate::jit::frontend::Expr::Integer).
+    ///
+    /// Types without exact integers round it with [`AnitaType::constant`].
+    fn integer_constant(builder: &mut FunctionBuilder, value: i128) -> Self::Lowered {
+        Self::constant(builder, value as f64)
+    }
+
+    /// Whether the number has an imaginary unit, which enables imaginary literals like `2.5i`.
+    ///
+    /// Reads of `i` that is neither a parameter nor assigned before then refer to the imaginary unit instead of a variable.
+    /// Types returning `true` have to create the literals in [`AnitaType::imaginary`].
+    fn has_imaginary_unit() -> bool {
+        false
+    }
+
+    /// Creates the imaginary literal `value i`, `None` if the type has no imaginary unit.
+    fn imaginary(_builder: &mut FunctionBuilder, _value: f64) -> Option<Self::Lowered> {
+        None
+    }
+
+    fn add(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Self::Lowered;
+    fn sub(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Self::Lowered;
+    fn mul(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Self::Lowered;
+    fn div(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Self::Lowered;
+    fn modulo(
+        builder: &mut FunctionBuilder,
+        value: Self::Lowered,
+        modulus: Self::Lowered,
+    ) -> Self::Lowered;
+    fn neg(builder: &mut FunctionBuilder, value: Self::Lowered) -> Self::Lowered;
+    /// Comparisons return a condition like [`AnitaType::truthy`].
+    ///
+    /// Conditions are branched on directly and only converted with [`AnitaType::from_condition`] where a number is needed, e.g. in `(x > 1) + 2`.
+    fn eq(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Value;
+    fn neq(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Value;
+    fn gt(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Value;
+    fn lt(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Value;
+    fn geq(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Value;
+    fn leq(builder: &mut FunctionBuilder, lhs: Self::Lowered, rhs: Self::Lowered) -> Value;
+
+    /// Returns a Cranelift condition that is set if `value` is not zero.
+    ///
+    /// This is the truthiness used by `!`, `&&`, `||` and conditionals.
+    ///
+    /// Scalars produce an `I8` condition and vectors a lane mask, like the comparisons.
+    fn truthy(builder: &mut FunctionBuilder, value: Self::Lowered) -> Value;
+    /// Picks `then` where `condition` is set and `otherwise` everywhere else.
+    fn select(
+        builder: &mut FunctionBuilder,
+        condition: Value,
+        then: Self::Lowered,
+        otherwise: Self::Lowered,
+    ) -> Self::Lowered;
+    /// Converts a condition as returned by [`AnitaType::truthy`] to `1` where it is set and `0` everywhere else.
+    fn from_condition(builder: &mut FunctionBuilder, condition: Value) -> Self::Lowered;
+
+    /// Whether every condition is either set or not, so that conditionals only evaluate the taken branch.
+    ///
+    /// Types whose conditions can be undecided, like comparisons of overlapping intervals, return `false`.
+    /// Both branches of conditionals and both operands of `&&` and `||` are then evaluated and merged with [`AnitaType::select`],
+    /// conditions are combined bitwise with `band` and `bor` and inverted with [`AnitaType::invert`].
+    fn decided_conditions() -> bool {
+        true
+    }
+
+    /// Inverts a scalar condition, used by `!` and to merge the branches of conditionals.
+    fn invert(builder: &mut FunctionBuilder, condition: Value) -> Value {
+        builder.ins().icmp_imm(IntCC::Equal, condition, 0)
+    }
+
+    /// Signature of a function of the [`FunctionManager`](crate::function_manager::FunctionManager)
+    /// or of [`AnitaType::inbuilt_functions`] with `arity` parameters.
+    ///
+    /// By default functions take and return single components of [`AnitaType::cranelift_repr`].
+    fn function_signature(arity: usize, call_conv: CallConv, _pointer_type: Type) -> Signature {
+        Signature {
+            params: vec![AbiParam::new(Self::cranelift_repr()); arity],
+            returns: vec![AbiParam::new(Self::cranelift_repr())],
+            call_conv,
+        }
+    }
+
+    /// Amount of parameters of a function with `signature`, the inverse of [`AnitaType::function_signature`].
+    fn function_arity(signature: &Signature) -> usize {
+        signature.params.len()
+    }
+
+    /// Lowers a call of a function of the [`FunctionManager`](crate::function_manager::FunctionManager) or of [`AnitaType::inbuilt_functions`].
+    ///
+    /// Functions have the signature of [`AnitaType::function_signature`].
+    /// By default they are applied to every component separately.
+    fn call<'a, C: FunctionCall<'a>>(
+        call: &mut C,
+        args: &[Self::Lowered],
+    ) -> Result<Self::Lowered, TranslatorError> {
+        let args: Vec<Vec<Value>> = args.iter().map(Lowered::values).collect();
+        let components: Vec<Value> = (0..Self::Lowered::COMPONENTS)
+            .map(|component| {
+                let component_args: Vec<Value> = args.iter().map(|arg| arg[component]).collect();
+                call.call(&component_args)
+            })
+            .collect();
+        Ok(Self::Lowered::from_values(&components))
+    }
+
+    /// Evaluates `operation` on two literals at compile time with the semantics of the runtime lowering.
+    ///
+    /// The operands are converted the same way as by [`AnitaType::constant`] before they are combined.
+    /// Returning `None` keeps the operation for runtime, e.g. if the result can not be represented as a literal.
+    fn fold(_operation: Operation, _lhs: f64, _rhs: f64) -> Option<f64> {
+        None
+    }
+
+    /// Whether the optimizer may replace `x ^ n` for small integers `n` by repeated multiplication.
+    ///
+    /// Types for which the product is less precise than the power, like intervals, return `false`.
+    fn expand_powers() -> bool {
+        true
+    }
+
+    /// Runtime functions the lowering relies on as name, address and amount of parameters.
+    ///
+    /// They have the signature of [`AnitaType::function_signature`].
+    /// `inbuilt_pow(x, y)` is used for `x ^ y` and `inbuilt_ln(x)` for its derivative.
+    fn inbuilt_functions() -> Box<[(&'static str, *const u8, usize)]>;
+}
+
+/// The Cranelift values a number is lowered to, see [`AnitaType::Lowered`].
+pub trait Lowered: Copy {
+    /// Amount of Cranelift values per number.
+    const COMPONENTS: usize;
+
+    /// Creates a number from exactly [`Lowered::COMPONENTS`] values.
+    fn from_values(values: &[Value]) -> Self;
+    fn values(&self) -> Vec<Value>;
+}
+
+impl Lowered for Value {
+    const COMPONENTS: usize = 1;
+
+    fn from_values(values: &[Value]) -> Self {
+        match values {
+            [value] => *value,
+            _ => panic!("expected 1 value but got {}", values.len()),
+        }
+    }
+
+    fn values(&self) -> Vec<Value> {
+        vec![*self]
+    }
+}
+
+impl<const N: usize> Lowered for [Value; N] {
+    const COMPONENTS: usize = N;
+
+    fn from_values(values: &[Value]) -> Self {
+        values
+            .try_into()
+            .unwrap_or_else(|_| panic!("expected {N} values but got {}", values.len()))
+    }
+
+    fn values(&self) -> Vec<Value> {
+        self.to_vec()
+    }
+}
+
+/// A call of a function on single components, passed to [`AnitaType::call`].
+pub trait FunctionCall<'a> {
+    fn builder(&mut self) -> &mut FunctionBuilder<'a>;
+
+    /// Emits the call with one value per parameter.
+    fn call(&mut self, args: &[Value]) -> Value;
+
+    /// Emits the call with one value per parameter followed by a pointer to `results` values of [`AnitaType::cranelift_repr`],
+    /// which the function writes its result to, and returns these values.
+    ///
+    /// Returning several values through memory does not depend on how the platform passes them in registers.
+    /// It is not available for vectorized types.
+    fn call_with_output(&mut self, args: &[Value], results: usize) -> Vec<Value>;
+
+    /// Lowers the partial derivative of the function with respect to its `index`th parameter at `args`.
+    ///
+    /// The derivative is taken from [`FunctionManager::function_derivatives`](crate::function_manager::FunctionManager::function_derivatives)
+    /// and evaluated with the arithmetic of `S`.
+    fn partial<S: AnitaType<Lowered = Value>>(
+        &mut self,
+        index: usize,
+        args: &[Value],
+    ) -> Result<Value, TranslatorError>;
+}
+
+/// Signature of functions that take `arity` numbers of `components` values of `repr`
+/// followed by a pointer to write their result to, see [`FunctionCall::call_with_output`].
+fn output_signature(
+    repr: Type,
+    components: usize,
+    arity: usize,
+    call_conv: CallConv,
+    pointer_type: Type,
+) -> Signature {
+    let mut params = vec![AbiParam::new(repr); arity * components];
+    params.push(AbiParam::new(pointer_type));
+    Signature {
+        params,
+        returns: Vec::new(),
+        call_conv,
+    }
+}
+
+/// Amount of parameters of a function with an [`output_signature`].
+fn output_arity(signature: &Signature, components: usize) -> usize {
+    signature.params.len().saturating_sub(1) / components
+}
+
+/// Pointer type of the host, which functions of a [`FunctionManager`](crate::function_manager::FunctionManager)
+/// are compiled for like the expressions calling them.
+pub(crate) fn pointer_type() -> Type {
+    Type::int(usize::BITS as u16).expect("pointers are 16 to 128 bits")
 }
 
+mod complex;
+mod dual;
 mod f32;
-mod f64;
\ No newline at end of file
+mod f64;
+mod integer;
+mod interval;
+mod real;
+mod value_type;
+mod vector;
@@ -0,0 +1,61 @@
+use cranelift::prelude::Value;
+
+use super::AnitaType;
+
+/// Floating point types that [`Complex`](super::Complex) and [`Interval`](super::Interval) numbers are made of.
+///
+/// Their functions are evaluated in `f64` and converted back.
+pub trait Real: AnitaType<Lowered = Value> + Copy + 'static {
+    /// Difference between `1` and the next larger number, every number `x` is at most `|x| * EPSILON` away from its neighbours.
+    const EPSILON: f64;
+    /// Smallest positive number.
+    const TINY: f64;
+
+    fn to_f64(self) -> f64;
+    /// Converts to the closest number.
+    fn from_f64(value: f64) -> Self;
+    fn next_up(self) -> Self;
+    fn next_down(self) -> Self;
+}
+
+impl Real for f32 {
+    const EPSILON: f64 = f32::EPSILON as f64;
+    const TINY: f64 = 1e-45_f32 as f64;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn next_up(self) -> Self {
+        self.next_up()
+    }
+
+    fn next_down(self) -> Self {
+        self.next_down()
+    }
+}
+
+impl Real for f64 {
+    const EPSILON: f64 = f64::EPSILON;
+    const TINY: f64 = 5e-324;
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn next_up(self) -> Self {
+        self.next_up()
+    }
+
+    fn next_down(self) -> Self {
+        self.next_down()
+    }
+}
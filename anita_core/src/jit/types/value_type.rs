@@ -0,0 +1,148 @@
+use std::fmt;
+
+use cranelift::prelude::{
+    types::{F32, F32X4, F64, I32, I64, I8},
+    Type,
+};
+
+/// Type of a parameter, result or intermediate value of an expression compiled with [`JIT::compile_typed`](crate::jit::JIT::compile_typed).
+///
+/// The names are the ones used in casts like `x as i32`.
+/// `vec2`, `vec3` and `vec4` are vectors of `f32` components, see [`Vec3`](super::Vec3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Bool,
+    I32,
+    I64,
+    U32,
+    U64,
+    F32,
+    F64,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+impl ValueType {
+    pub const ALL: [ValueType; 10] = [
+        ValueType::Bool,
+        ValueType::I32,
+        ValueType::I64,
+        ValueType::U32,
+        ValueType::U64,
+        ValueType::F32,
+        ValueType::F64,
+        ValueType::Vec2,
+        ValueType::Vec3,
+        ValueType::Vec4,
+    ];
+
+    /// The Cranelift type a value is lowered to, `bool` is an `I8` that is either `0` or `1`.
+    ///
+    /// Vectors of every length are an `F32X4` whose unused lanes are ignored.
+    pub fn cranelift_repr(self) -> Type {
+        match self {
+            ValueType::Bool => I8,
+            ValueType::I32 | ValueType::U32 => I32,
+            ValueType::I64 | ValueType::U64 => I64,
+            ValueType::F32 => F32,
+            ValueType::F64 => F64,
+            ValueType::Vec2 | ValueType::Vec3 | ValueType::Vec4 => F32X4,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::Bool => "bool",
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::U32 => "u32",
+            ValueType::U64 => "u64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+            ValueType::Vec2 => "vec2",
+            ValueType::Vec3 => "vec3",
+            ValueType::Vec4 => "vec4",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| ty.name() == name)
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, ValueType::F32 | ValueType::F64)
+    }
+
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            ValueType::I32 | ValueType::I64 | ValueType::U32 | ValueType::U64
+        )
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(self, ValueType::I32 | ValueType::I64)
+    }
+
+    pub fn is_vector(self) -> bool {
+        self.components() > 1
+    }
+
+    /// The amount of components of a vector, `1` for scalars.
+    pub fn components(self) -> usize {
+        match self {
+            ValueType::Vec2 => 2,
+            ValueType::Vec3 => 3,
+            ValueType::Vec4 => 4,
+            _ => 1,
+        }
+    }
+
+    /// The vector with `components` components, `f32` for a single one.
+    pub fn vector(components: usize) -> Option<Self> {
+        match components {
+            1 => Some(ValueType::F32),
+            2 => Some(ValueType::Vec2),
+            3 => Some(ValueType::Vec3),
+            4 => Some(ValueType::Vec4),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Rust types that can be parameters and results of typed expressions, see [`ValueType`].
+///
+/// Compiled code takes scalars by value and vectors as a pointer to their components.
+/// A vector result is written to a pointer passed after the parameters instead of being returned.
+pub trait Primitive: Copy {
+    const VALUE_TYPE: ValueType;
+    /// The type compiled code takes the value as.
+    type Argument: Copy;
+
+    /// The argument passing `self` to compiled code, which is only valid as long as `self` is.
+    fn argument(&self) -> Self::Argument;
+}
+
+macro_rules! primitive {
+    ($($type:ty => $value_type:ident),*) => {
+        $(
+            impl Primitive for $type {
+                const VALUE_TYPE: ValueType = ValueType::$value_type;
+                type Argument = Self;
+
+                fn argument(&self) -> Self {
+                    *self
+                }
+            }
+        )*
+    };
+}
+
+primitive!(bool => Bool, i32 => I32, i64 => I64, u32 => U32, u64 => U64, f32 => F32, f64 => F64);
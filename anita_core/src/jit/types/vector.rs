@@ -0,0 +1,47 @@
+use super::{Primitive, ValueType};
+
+macro_rules! vector {
+    ($($(#[$attribute:meta])* $name:ident($($component:ident),+) => $value_type:ident),* $(,)?) => {
+        $(
+            $(#[$attribute])*
+            #[repr(C)]
+            #[derive(Debug, Clone, Copy, PartialEq, Default)]
+            pub struct $name {
+                $(pub $component: f32,)+
+            }
+
+            impl $name {
+                pub const fn new($($component: f32),+) -> Self {
+                    Self { $($component),+ }
+                }
+
+                /// The vector with every component set to `value`.
+                pub const fn splat(value: f32) -> Self {
+                    Self { $($component: value),+ }
+                }
+            }
+
+            impl Primitive for $name {
+                const VALUE_TYPE: ValueType = ValueType::$value_type;
+                type Argument = *const Self;
+
+                fn argument(&self) -> *const Self {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+vector! {
+    /// A `vec2` of a typed expression.
+    Vec2(x, y) => Vec2,
+    /// A `vec3` of a typed expression.
+    ///
+    /// Vectors are built with `vec3(x, y, z)` and their components are read with swizzles like `v.x` or `v.zyx`.
+    /// Arithmetic is done per component, scalars are converted to `f32` and used for every component.
+    /// `dot`, `cross`, `length` and `normalize` are inbuilt.
+    Vec3(x, y, z) => Vec3,
+    /// A `vec4` of a typed expression.
+    Vec4(x, y, z, w) => Vec4,
+}
@@ -3,6 +3,7 @@
 #![warn(clippy::unwrap_used)]
 
 pub mod function_manager;
+pub mod interpreter;
 pub mod jit;
 
 pub use cranelift;
@@ -0,0 +1,81 @@
+use anita_core::{compile_batch_expression, jit::compiled_function::CallError};
+
+#[test]
+fn batch_matches_scalar() {
+    let function =
+        compile_batch_expression!("x * y + 2 ^ x", (x, y) -> f32).expect("Compilation failed");
+    let x: Vec<f32> = (0..11).map(|i| i as f32 * 0.5).collect();
+    let y: Vec<f32> = (0..11).map(|i| 10.0 - i as f32).collect();
+    let mut output = vec![0.0; 11];
+    function.call(&[&x, &y], &mut output).expect("Call failed");
+    for i in 0..11 {
+        assert_eq!(output[i], x[i] * y[i] + 2_f32.powf(x[i]));
+    }
+}
+
+#[test]
+fn batch_f64() {
+    let function =
+        compile_batch_expression!("y = x % 3; y * 2 - 1", (x) -> f64).expect("Compilation failed");
+    let x: Vec<f64> = (0..7).map(f64::from).collect();
+    let mut output = vec![0.0; 7];
+    function.call(&[&x], &mut output).expect("Call failed");
+    for i in 0..7 {
+        assert_eq!(output[i], (x[i] % 3.0) * 2.0 - 1.0);
+    }
+}
+
+#[test]
+fn batch_empty_output() {
+    let function = compile_batch_expression!("x", (x) -> f32).expect("Compilation failed");
+    function.call(&[&[]], &mut []).expect("Call failed");
+}
+
+#[test]
+fn batch_argument_mismatch() {
+    let function = compile_batch_expression!("x + y", (x, y) -> f32).expect("Compilation failed");
+    let mut output = [0.0; 4];
+    assert_eq!(
+        function.call(&[&[1.0; 4]], &mut output),
+        Err(CallError::ArgumentCountMismatch {
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(
+        function.call(&[&[1.0; 4], &[1.0; 3]], &mut output),
+        Err(CallError::LengthMismatch {
+            expected: 4,
+            found: 3
+        })
+    );
+}
+
+#[test]
+fn batch_masked_branches() {
+    let function = compile_batch_expression!("y = 0; z = if(x, y = x, y - 1); x || (y = 5); y + z", (x) -> f32)
+        .expect("Compilation failed");
+    let x = [0.0, 1.0, 2.0, 0.0, 3.0, 0.0];
+    let mut output = [0.0; 6];
+    function.call(&[&x], &mut output).expect("Call failed");
+    for (i, result) in output.into_iter().enumerate() {
+        let expected = if x[i] != 0.0 { 2.0 * x[i] } else { 5.0 - 1.0 };
+        assert_eq!(result, expected);
+    }
+}
+
+#[test]
+fn batch_conditions() {
+    let function =
+        compile_batch_expression!("(x > 1 ? x < 3 : x == 0) + (x > 4 ? 1 : x > 2)", (x) -> f32)
+            .expect("Compilation failed");
+    let x: Vec<f32> = (0..11).map(|i| i as f32 * 0.5).collect();
+    let mut output = vec![0.0; 11];
+    function.call(&[&x], &mut output).expect("Call failed");
+    for i in 0..11 {
+        let x = x[i];
+        let lhs = if x > 1.0 { x < 3.0 } else { x == 0.0 };
+        let rhs = if x > 4.0 { true } else { x > 2.0 };
+        assert_eq!(output[i], lhs as u8 as f32 + rhs as u8 as f32, "x = {x}");
+    }
+}
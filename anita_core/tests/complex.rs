@@ -0,0 +1,175 @@
+use std::f64::consts::PI;
+
+use anita_core::{
+    function_manager::NoFunctions,
+    jit::{
+        frontend::{parser, Expr, Span},
+        types::{Complex, ComplexFunctions},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+fn evaluate(expression: &str, z: Complex<f64>) -> Complex<f64> {
+    JIT::<Complex<f64>, ComplexFunctions<f64>>::default()
+        .compile_dynamic(expression, &["z"])
+        .expect("Compilation failed")
+        .call(&[z])
+        .unwrap()
+}
+
+fn assert_close(actual: Complex<f64>, expected: Complex<f64>) {
+    assert!(
+        (actual.re - expected.re).abs() < 1e-12 && (actual.im - expected.im).abs() < 1e-12,
+        "{actual:?} != {expected:?}"
+    );
+}
+
+#[test]
+fn arithmetic() {
+    let z = Complex::new(3.0, 4.0);
+    assert_eq!(evaluate("z + 1", z), Complex::new(4.0, 4.0));
+    assert_eq!(evaluate("z - 2i", z), Complex::new(3.0, 2.0));
+    assert_eq!(evaluate("z * z", z), Complex::new(-7.0, 24.0));
+    assert_eq!(evaluate("z / (1 + 2i)", z), Complex::new(2.2, -0.4));
+    assert_eq!(evaluate("-z", z), Complex::new(-3.0, -4.0));
+    assert_eq!(
+        evaluate("z % 2", Complex::new(5.0, 0.0)),
+        Complex::new(1.0, 0.0)
+    );
+    assert_eq!(
+        evaluate("z % 2", Complex::new(5.0, 3.0)),
+        Complex::new(1.0, 1.0)
+    );
+}
+
+#[test]
+fn imaginary_unit() {
+    let z = Complex::new(1.0, 1.0);
+    assert_eq!(evaluate("i * i", z), Complex::new(-1.0, 0.0));
+    assert_eq!(evaluate("2.5i + z", z), Complex::new(1.0, 3.5));
+    assert_eq!(evaluate("z * -i", z), Complex::new(1.0, -1.0));
+    // Assigned or passed as a parameter, `i` is an ordinary variable.
+    assert_eq!(evaluate("i = 2; i * z", z), Complex::new(2.0, 2.0));
+    let function = JIT::<Complex<f64>, ComplexFunctions<f64>>::default()
+        .compile_dynamic("i * 2", &["i"])
+        .expect("Compilation failed");
+    assert_eq!(
+        function.call(&[Complex::new(3.0, 0.0)]),
+        Ok(Complex::new(6.0, 0.0))
+    );
+    // An `i` assigned on one branch stays a variable and may be read unassigned.
+    let Err(e) = JIT::<Complex<f64>, ComplexFunctions<f64>>::default()
+        .compile_dynamic("if z > 0 { i = 2 } else { z = 1 }; i", &["z"])
+    else {
+        panic!("Compilation succeeded");
+    };
+    assert!(matches!(e, JITError::UseOfUninitializedVariables(_)));
+}
+
+#[test]
+fn powers() {
+    let z = Complex::new(1.0, 2.0);
+    assert_eq!(evaluate("z ^ 2", z), Complex::new(-3.0, 4.0));
+    assert_eq!(evaluate("z ^ -1", z), Complex::new(0.2, -0.4));
+    assert_close(evaluate("(-1) ^ 0.5", z), Complex::new(0.0, 1.0));
+    assert_close(evaluate("i ^ i", z), Complex::new((-PI / 2.0).exp(), 0.0));
+    assert_eq!(evaluate("2 ^ 10", z), Complex::new(1024.0, 0.0));
+}
+
+#[test]
+fn functions() {
+    let z = Complex::new(3.0, -4.0);
+    assert_eq!(evaluate("re(z)", z), Complex::new(3.0, 0.0));
+    assert_eq!(evaluate("im(z)", z), Complex::new(-4.0, 0.0));
+    assert_eq!(evaluate("abs(z)", z), Complex::new(5.0, 0.0));
+    assert_eq!(evaluate("arg(-1)", z), Complex::new(PI, 0.0));
+    assert_eq!(evaluate("conj(z)", z), Complex::new(3.0, 4.0));
+    assert_close(
+        evaluate("exp(i * z)", Complex::new(PI, 0.0)),
+        Complex::new(-1.0, 0.0),
+    );
+    assert_close(
+        evaluate("ln(z)", z),
+        Complex::new(5f64.ln(), (-4f64).atan2(3.0)),
+    );
+    assert_close(evaluate("exp(ln(z))", z), z);
+}
+
+#[test]
+fn single_precision() {
+    let function = JIT::<Complex<f32>, ComplexFunctions<f32>>::default()
+        .compile_dynamic("abs(z * i) + conj(z)", &["z"])
+        .expect("Compilation failed");
+    assert_eq!(
+        function.call(&[Complex::new(3.0, 4.0)]),
+        Ok(Complex::new(8.0, -4.0))
+    );
+}
+
+#[test]
+fn comparisons() {
+    let z = Complex::new(1.0, 2.0);
+    assert_eq!(evaluate("z == 1 + 2i", z), Complex::new(1.0, 0.0));
+    assert_eq!(evaluate("z == 1", z), Complex::new(0.0, 0.0));
+    assert_eq!(evaluate("z != 1", z), Complex::new(1.0, 0.0));
+    // Ordering only compares the real parts.
+    assert_eq!(evaluate("z < 2i", z), Complex::new(0.0, 0.0));
+    assert_eq!(evaluate("z >= 1 ? z : 0", z), z);
+    // A number is true if either part is not zero.
+    assert_eq!(evaluate("2i ? 1 : 0", z), Complex::new(1.0, 0.0));
+    assert_eq!(evaluate("!(z - 2i)", z), Complex::new(0.0, 0.0));
+}
+
+#[test]
+fn batch() {
+    let mut jit = JIT::<Complex<f64>, ComplexFunctions<f64>>::default();
+    let code_ptr = jit
+        .compile_batch("z * i + conj(z)", &["z"])
+        .expect("Compilation failed");
+    // SAFETY: batch functions have exactly this signature.
+    let function = unsafe {
+        std::mem::transmute::<
+            *const u8,
+            extern "C" fn(*const *const Complex<f64>, *mut Complex<f64>, usize),
+        >(code_ptr)
+    };
+    let input = [Complex::new(1.0, 2.0), Complex::new(-3.0, 0.5)];
+    let mut output = [Complex::default(); 2];
+    function([input.as_ptr()].as_ptr(), output.as_mut_ptr(), input.len());
+    assert_eq!(output, [Complex::new(-1.0, -1.0), Complex::new(-3.5, -3.5)]);
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(
+        parser::expression("2.5i").expect("Parsing failed"),
+        Expr::Imaginary {
+            value: 2.5,
+            span: Span::new(0, 4)
+        }
+    );
+    assert!(parser::expression("2in").is_err());
+    for expression in ["1 + 2i", "z * -0.5i", "-(-3i)", "0x10i ^ 2"] {
+        let parsed = parser::expression(expression).expect("Parsing failed");
+        let reparsed = parser::expression(&parsed.to_string()).expect("Parsing failed");
+        assert!(parsed.same_structure(&reparsed), "{expression}");
+    }
+}
+
+#[test]
+fn real_types_reject_imaginary_numbers() {
+    let Err(e) = JIT::<f64, NoFunctions>::default().compile("x + 2i", &["x"]) else {
+        panic!("Compilation succeeded");
+    };
+    assert!(matches!(
+        e,
+        JITError::TranslatorError(TranslatorError::UnsupportedImaginary { .. })
+    ));
+    assert_eq!(e.span().map(|span| (span.start, span.end)), Some((4, 6)));
+
+    // Without an imaginary unit `i` stays a variable.
+    let Err(e) = JIT::<f64, NoFunctions>::default().compile("i * x", &["x"]) else {
+        panic!("Compilation succeeded");
+    };
+    assert!(matches!(e, JITError::UseOfUninitializedVariables(_)));
+}
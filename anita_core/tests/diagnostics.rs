@@ -0,0 +1,143 @@
+use std::error::Error;
+
+use anita_core::{
+    compile_expression,
+    function_manager::NoFunctions,
+    jit::{
+        frontend::{diagnostic, Span, Syntax},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+fn error(expression: &str) -> JITError {
+    match JIT::<f32, NoFunctions>::default().compile_dynamic(expression, &["x"]) {
+        Ok(_) => panic!("Compilation of {expression:?} succeeded"),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn unknown_function_span() {
+    let expression = "x + foo(x, 2)";
+    let error = error(expression);
+    match &error {
+        JITError::TranslatorError(TranslatorError::FunctionNotFound { name, span }) => {
+            assert_eq!(name, "foo");
+            assert_eq!(&expression[span.start..span.end], "foo(x, 2)");
+        }
+        error => panic!("Expected FunctionNotFound but got: {error:?}"),
+    }
+    assert_eq!(error.to_string(), "unknown function `foo`");
+    assert!(error.source().is_some());
+}
+
+#[test]
+fn uninitialized_variable_spans() {
+    let expression = "x + b * a; b";
+    let JITError::UseOfUninitializedVariables(variables) = error(expression) else {
+        panic!("Expected UseOfUninitializedVariables");
+    };
+    let found: Vec<(&str, &str)> = variables
+        .iter()
+        .map(|(name, span)| (name.as_str(), &expression[span.start..span.end]))
+        .collect();
+    assert_eq!(found, [("b", "b"), ("a", "a")]);
+    assert_eq!(variables[0].1.start, 4);
+    assert_eq!(variables[1].1.start, 8);
+}
+
+#[test]
+fn parse_error_span() {
+    let error = error("x + * 2");
+    assert_eq!(error.span().map(|span| span.start), Some(4));
+    assert!(error.to_string().starts_with("invalid syntax, expected"));
+}
+
+#[test]
+fn parse_error_expected() {
+    // Keywords are reported as a whole instead of the letters they start with.
+    let mut jit = JIT::<f32, NoFunctions>::default();
+    jit.set_syntax(Syntax::Evalexpr);
+    let Err(JITError::ParseError(parse_error)) = jit.compile_dynamic("x.y", &["x", "y"]) else {
+        panic!("Expected ParseError");
+    };
+    assert_eq!(parse_error.location.offset, 1);
+    let mut expected: Vec<&str> = parse_error.expected.tokens().collect();
+    expected.sort_unstable();
+    assert_eq!(
+        expected,
+        [
+            "\"!=\"", "\"%\"", "\"&&\"", "\"(\"", "\"*\"", "\"+\"", "\"-\"", "\"/\"", "\"::\"",
+            "\";\"", "\"<\"", "\"<=\"", "\"=\"", "\"==\"", "\">\"", "\">=\"", "\"^\"", "\"||\"",
+            "EOF"
+        ]
+    );
+    assert_eq!(
+        error("if x { 1 }").to_string(),
+        "invalid syntax, expected else"
+    );
+}
+
+#[test]
+fn render_unknown_function() {
+    let expression = "x + foo(2)";
+    let result = compile_expression!(expression, (x) -> f32);
+    let Err(error) = result else {
+        panic!("Compilation succeeded");
+    };
+    assert_eq!(
+        error.render(expression),
+        "error: unknown function `foo`\n --> 1:5\n  |\n1 | x + foo(2)\n  |     ^^^^^^"
+    );
+}
+
+#[test]
+fn render_non_ascii() {
+    let render = |source: &str, spans: &[Span]| {
+        diagnostic::render(source, "m", spans)
+            .lines()
+            .last()
+            .map(str::to_owned)
+    };
+    assert_eq!(
+        render("ééé + x", &[Span::new(0, 2), Span::new(7, 8)]).as_deref(),
+        Some("  | ^   ^")
+    );
+    assert_eq!(
+        render("éé + x", &[Span::new(0, 4), Span::new(7, 8)]).as_deref(),
+        Some("  | ^^   ^")
+    );
+    // An empty span marks the whole character it points at.
+    assert_eq!(
+        render("é + x", &[Span::new(0, 0), Span::new(5, 6)]).as_deref(),
+        Some("  | ^   ^")
+    );
+}
+
+#[test]
+fn render_multiple_spans() {
+    let expression = "a + x * b";
+    assert_eq!(
+        error(expression).render(expression),
+        "error: use of uninitialized variables `a`, `b`\n --> 1:1\n  |\n1 | a + x * b\n  | ^       ^"
+    );
+}
+
+#[test]
+fn render_end_of_input() {
+    let expression = "x +";
+    assert_eq!(
+        error(expression).render(expression).lines().last(),
+        Some("  |    ^")
+    );
+}
+
+#[test]
+fn errors_without_location() {
+    let error = JITError::DuplicateFunction("f".to_owned());
+    assert_eq!(error.span().map(|span| span.start), None);
+    assert_eq!(
+        error.render("x"),
+        "error: a function named `f` was already added"
+    );
+}
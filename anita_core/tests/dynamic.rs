@@ -0,0 +1,53 @@
+use anita_core::{
+    function_manager::NoFunctions,
+    jit::{compiled_function::CallError, JIT},
+};
+
+#[test]
+fn runtime_parameters() {
+    let parameters: Vec<String> = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+    let parameters: Vec<&str> = parameters.iter().map(String::as_str).collect();
+    let function = JIT::<f32, NoFunctions>::default()
+        .compile_dynamic("a * b - c", &parameters)
+        .expect("Compilation failed");
+    assert_eq!(function.parameters(), ["a", "b", "c"]);
+    assert_eq!(function.call(&[2.0, 3.0, 1.0]), Ok(5.0));
+}
+
+#[test]
+fn no_parameters() {
+    let function = JIT::<f64, NoFunctions>::default()
+        .compile_dynamic("x = 2; x ^ 10", &[])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[]), Ok(1024.0));
+}
+
+#[test]
+fn argument_count_mismatch() {
+    let function = JIT::<f32, NoFunctions>::default()
+        .compile_dynamic("x + y", &["x", "y"])
+        .expect("Compilation failed");
+    assert_eq!(
+        function.call(&[1.0]),
+        Err(CallError::ArgumentCountMismatch {
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(
+        function.call(&[1.0, 2.0, 3.0]),
+        Err(CallError::ArgumentCountMismatch {
+            expected: 2,
+            found: 3
+        })
+    );
+}
+
+#[test]
+fn call_unchecked() {
+    let function = JIT::<f32, NoFunctions>::default()
+        .compile_dynamic("x - y", &["x", "y"])
+        .expect("Compilation failed");
+    let arguments = [4.0, 1.5];
+    assert_eq!(unsafe { function.call_unchecked(arguments.as_ptr()) }, 2.5);
+}
@@ -14,3 +14,165 @@ fn whitespace() {
     let expression = "x  ";
     let _function = compile_expression!(expression, (x) -> f32).expect("Compilation failed");
 }
+
+#[test]
+fn conditional_syntax() {
+    use anita_core::jit::frontend::parser;
+
+    let function_syntax = parser::expression("if(x, 1, 2)").expect("Parsing failed");
+    let ternary = parser::expression("x ? 1 : 2").expect("Parsing failed");
+    let block = parser::expression("if x { 1 } else { 2 }").expect("Parsing failed");
+    assert!(function_syntax.same_structure(&ternary));
+    assert!(function_syntax.same_structure(&block));
+
+    let nested_ternary = parser::expression("a ? 1 : b ? 2 : 3").expect("Parsing failed");
+    let nested_block =
+        parser::expression("if a { 1 } else if b { 2 } else { 3 }").expect("Parsing failed");
+    assert!(nested_ternary.same_structure(&nested_block));
+
+    let assigned = parser::expression("y = x && z ? 1 : 2").expect("Parsing failed");
+    let parenthesized = parser::expression("y = ((x && z) ? 1 : 2)").expect("Parsing failed");
+    assert!(assigned.same_structure(&parenthesized));
+
+    assert!(parser::expression("iffy(x)").is_ok());
+    assert!(parser::expression("if_x + 1").is_ok());
+}
+
+#[test]
+fn conditional_operators() {
+    let function =
+        compile_expression!("x ? y + 1 : y - 1", (x, y) -> f32).expect("Compilation failed");
+    assert_eq!(function(1.0, 1.0), 2.0);
+    assert_eq!(function(0.0, 1.0), 0.0);
+    let function = compile_expression!("if x { y = 2 } else { y = 3 }; y", (x) -> f32)
+        .expect("Compilation failed");
+    assert_eq!(function(-1.0), 2.0);
+    assert_eq!(function(0.0), 3.0);
+}
+
+#[test]
+fn consistent_truthiness() {
+    let subnormal = f32::MIN_POSITIVE / 2.0;
+    for expression in ["x ? 1 : 0", "!!x", "x && 1", "0 || x", "if(x, 1, 0)"] {
+        let function = compile_expression!(expression, (x) -> f32).expect("Compilation failed");
+        assert_eq!(function(subnormal), 1.0, "{expression}");
+        assert_eq!(function(f32::NAN), 1.0, "{expression}");
+        assert_eq!(function(-2.0), 1.0, "{expression}");
+        assert_eq!(function(0.0), 0.0, "{expression}");
+        assert_eq!(function(-0.0), 0.0, "{expression}");
+    }
+}
+
+#[test]
+fn numeric_literals() {
+    use anita_core::jit::frontend::{parser, Expr};
+
+    let cases: [(&str, f64); 16] = [
+        ("42", 42.0),
+        ("1.5", 1.5),
+        ("2.", 2.0),
+        (".5", 0.5),
+        ("-.25", -0.25),
+        ("6.674e-11", 6.674e-11),
+        ("1E3", 1000.0),
+        ("2.5e+2", 250.0),
+        (".5e1", 5.0),
+        ("1_000_000", 1_000_000.0),
+        ("1_000.000_1", 1_000.000_1),
+        ("0xff", 255.0),
+        ("0XFF_FF", 65535.0),
+        ("0x1.8p3", 12.0),
+        ("0x.8", 0.5),
+        ("-0x10p-2", -4.0),
+    ];
+    for (literal, expected) in cases {
+        let parsed = parser::expression(literal).expect("Parsing failed");
+        assert_eq!(parsed, Expr::Const { value: expected }, "{literal}");
+    }
+
+    for invalid in ["1e", "0x", "0xg", "._5", "1e+"] {
+        assert!(parser::expression(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn numeric_literals_in_expressions() {
+    let function =
+        compile_expression!("x * 1e3 + 0x10 - .5", (x) -> f32).expect("Compilation failed");
+    assert_eq!(function(2.0), 2015.5);
+}
+
+#[test]
+fn full_precision_literals() {
+    use anita_core::jit::frontend::{parser, Expr};
+
+    assert_eq!(
+        parser::expression("0.1").expect("Parsing failed"),
+        Expr::Const { value: 0.1 }
+    );
+    assert_eq!(
+        parser::expression("6.02214076e23").expect("Parsing failed"),
+        Expr::Const {
+            value: 6.02214076e23
+        }
+    );
+
+    let function = compile_expression!("x + 0.1", (x) -> f64).expect("Compilation failed");
+    assert_eq!(function(0.0), 0.1);
+    let function = compile_expression!("x + 0.1", (x) -> f32).expect("Compilation failed");
+    assert_eq!(function(0.0), 0.1_f32);
+}
+
+#[test]
+fn structural_equality() {
+    use anita_core::jit::frontend::parser;
+
+    let spaced = parser::expression("f(x)  +  y").expect("Parsing failed");
+    let compact = parser::expression("f(x)+y").expect("Parsing failed");
+    assert_ne!(spaced, compact);
+    assert!(spaced.same_structure(&compact));
+    let other = parser::expression("f(x)+z").expect("Parsing failed");
+    assert!(!spaced.same_structure(&other));
+    let other = parser::expression("f(x)-y").expect("Parsing failed");
+    assert!(!spaced.same_structure(&other));
+}
+
+#[test]
+fn display_round_trip() {
+    use anita_core::jit::frontend::parser;
+
+    for expression in [
+        "a + b * c",
+        "(a + b) * c",
+        "a - (b - c)",
+        "a - b - c",
+        "a / (b * c)",
+        "2 ^ 3 ^ 2",
+        "2 ^ (3 ^ 2)",
+        "-x ^ 2",
+        "-(x ^ 2)",
+        "x ^ -2",
+        "!(a && b) || c",
+        "a == (b < c)",
+        "y = x + 1; z = y * 2; y - z",
+        "(a; b) + c",
+        "a ? b : c ? d : e",
+        "(a ? b : c) ? d : e",
+        "if a { y = 1 } else { y = 2 }; y",
+        "max(a, b - 1) * sin(-x)",
+        "x * 1e-50 + 0.1 + 0x10",
+        "-(-2)",
+        "-(2) * x",
+    ] {
+        let parsed = parser::expression(expression).expect("Parsing failed");
+        let printed = parsed.to_string();
+        let reparsed = parser::expression(&printed).expect("Parsing failed");
+        assert!(
+            parsed.same_structure(&reparsed),
+            "{expression} was printed as {printed}"
+        );
+    }
+
+    let printed = parser::expression("((a + b)) * (c)").expect("Parsing failed");
+    assert_eq!(printed.to_string(), "(a + b) * c");
+}
@@ -0,0 +1,121 @@
+use std::fmt::Debug;
+
+use anita_core::{
+    compile_expression,
+    function_manager::NoFunctions,
+    jit::{frontend::parser, types::AnitaType, JIT},
+};
+
+/// Evaluates `expression` with and without constant folding and checks that both agree.
+fn evaluate<T: AnitaType + Copy + PartialEq + Debug>(expression: &str, x: T) -> T {
+    let results: Vec<T> = [true, false]
+        .into_iter()
+        .map(|optimize| {
+            let mut jit = JIT::<T, NoFunctions>::default();
+            jit.set_optimize(optimize);
+            let function = jit
+                .compile_dynamic(expression, &["x"])
+                .expect("Compilation failed");
+            function.call(&[x]).unwrap()
+        })
+        .collect();
+    assert_eq!(results[0], results[1], "folding changed `{expression}`");
+    results[0]
+}
+
+#[test]
+fn arithmetic() {
+    let function =
+        compile_expression!("x * 3 - 4 / 2 + x % 5", (x) -> i32).expect("Compilation failed");
+    assert_eq!(function(7), 21 - 2 + 2);
+    assert_eq!(evaluate::<i64>("x / 2", -7), -3);
+    assert_eq!(evaluate::<i64>("x % 3", -7), -1);
+    assert_eq!(evaluate::<u32>("x / 2", 7), 3);
+    assert_eq!(evaluate::<u64>("x % 4", 7), 3);
+    assert_eq!(evaluate::<i32>("x ^ 3", -3), -27);
+    assert_eq!(evaluate::<u64>("x ^ 40", 2), 1 << 40);
+}
+
+#[test]
+fn wrapping() {
+    assert_eq!(evaluate::<i32>("x + 1", i32::MAX), i32::MIN);
+    assert_eq!(evaluate::<u32>("x - 1", 0), u32::MAX);
+    assert_eq!(evaluate::<u32>("-x", 1), u32::MAX);
+    assert_eq!(evaluate::<i64>("x * x", 1 << 32), 0);
+    assert_eq!(evaluate::<u64>("x * 2", u64::MAX), u64::MAX - 1);
+    assert_eq!(evaluate::<i32>("x ^ 33", 2), 0);
+}
+
+#[test]
+fn division_by_zero() {
+    assert_eq!(evaluate::<i32>("x / 0", 7), 0);
+    assert_eq!(evaluate::<i32>("x % 0", -7), -7);
+    assert_eq!(evaluate::<u64>("x / (x - x)", 7), 0);
+    assert_eq!(evaluate::<u32>("x % (x - 7)", 7), 7);
+    assert_eq!(evaluate::<i64>("x / -1", i64::MIN), i64::MIN);
+    assert_eq!(evaluate::<i64>("x % -1", i64::MIN), 0);
+    assert_eq!(evaluate::<i32>("x ^ -1", 2), 0);
+    assert_eq!(evaluate::<i32>("x ^ -3", -1), -1);
+    assert_eq!(evaluate::<i32>("x ^ -2", 0), 0);
+}
+
+#[test]
+fn literals() {
+    assert_eq!(evaluate::<i32>("x + 2.7", 0), 2);
+    assert_eq!(evaluate::<i32>("x + -2.7", 0), -2);
+    assert_eq!(evaluate::<u32>("x + -1", 0), u32::MAX);
+    assert_eq!(evaluate::<i32>("x + 4294967297", 0), 1);
+    assert_eq!(evaluate::<u64>("x + 0x1p63", 0), 1 << 63);
+    assert_eq!(evaluate::<i64>("x + 1e15", 0), 1_000_000_000_000_000);
+}
+
+#[test]
+fn full_precision_literals() {
+    assert_eq!(evaluate::<u64>("18446744073709551615", 0), u64::MAX);
+    assert_eq!(evaluate::<u64>("x + 18446744073709551615", 1), 0);
+    assert_eq!(evaluate::<u64>("x + 0xffff_ffff_ffff_ffff", 0), u64::MAX);
+    assert_eq!(evaluate::<i64>("9223372036854775807", 0), i64::MAX);
+    assert_eq!(evaluate::<i64>("x - 9223372036854775807", -1), i64::MIN);
+    assert_eq!(
+        evaluate::<i64>("9007199254740993", 0),
+        9_007_199_254_740_993
+    );
+    assert_eq!(
+        evaluate::<i64>("x + -9007199254740993", 0),
+        -9_007_199_254_740_993
+    );
+    // Wider literals still wrap into the range of the type.
+    assert_eq!(evaluate::<u32>("x + 18446744073709551615", 0), u32::MAX);
+
+    let function = compile_expression!("x + 18446744073709551615", (x: u64) -> u64)
+        .expect("Compilation failed");
+    assert_eq!(function(1), 0);
+    let function = compile_expression!("x * 0 + 9223372036854775807", (x: i64) -> i64)
+        .expect("Compilation failed");
+    assert_eq!(function(3), i64::MAX);
+
+    let parsed = parser::expression("x + 18446744073709551615").expect("Parsing failed");
+    assert_eq!(parsed.to_string(), "x + 18446744073709551615");
+}
+
+#[test]
+fn comparisons() {
+    assert_eq!(evaluate::<i32>("(x > -1) + (x < 1) * 2", -1), 2);
+    assert_eq!(evaluate::<u32>("x > 1", u32::MAX), 1);
+    assert_eq!(evaluate::<u64>("x >= 0 && x <= 0", 0), 1);
+    assert_eq!(evaluate::<i64>("x == 3 || !x", 0), 1);
+    assert_eq!(evaluate::<i32>("x != 0 ? 10 / x : -1", 0), -1);
+    assert_eq!(evaluate::<i32>("if x { 0.5 } else { 2 }", 3), 0);
+}
+
+#[test]
+fn batch_evaluation() {
+    let function = anita_core::compile_batch_expression!("x * y + 1", (x, y) -> i64)
+        .expect("Compilation failed");
+    let x: Vec<i64> = (0..10).collect();
+    let y: Vec<i64> = (0..10).map(|y| -y).collect();
+    let mut output = vec![0; 10];
+    function.call(&[&x, &y], &mut output).unwrap();
+    let expected: Vec<i64> = (0..10).map(|x| 1 - x * x).collect();
+    assert_eq!(output, expected);
+}
@@ -0,0 +1,268 @@
+use std::f64::consts::PI;
+
+use anita_core::jit::{
+    types::{Interval, IntervalFunctions},
+    JIT,
+};
+
+fn evaluate(expression: &str, x: Interval<f64>) -> Interval<f64> {
+    JIT::<Interval<f64>, IntervalFunctions<f64>>::default()
+        .compile_dynamic(expression, &["x"])
+        .expect("Compilation failed")
+        .call(&[x])
+        .unwrap()
+}
+
+fn assert_contains(interval: Interval<f64>, value: f64) {
+    assert!(
+        interval.lower <= value && value <= interval.upper,
+        "{interval:?} does not contain {value}"
+    );
+}
+
+/// Asserts that `actual` contains `expected` and is at most `tolerance` wider on each side.
+fn assert_encloses(actual: Interval<f64>, expected: Interval<f64>, tolerance: f64) {
+    assert!(
+        actual.lower <= expected.lower
+            && actual.upper >= expected.upper
+            && expected.lower - actual.lower <= tolerance
+            && actual.upper - expected.upper <= tolerance,
+        "{actual:?} does not tightly enclose {expected:?}"
+    );
+}
+
+#[test]
+fn arithmetic() {
+    let x = Interval::new(-1.0, 2.0);
+    assert_encloses(evaluate("x + 1", x), Interval::new(0.0, 3.0), 1e-12);
+    assert_encloses(evaluate("1 - x", x), Interval::new(-1.0, 2.0), 1e-12);
+    assert_encloses(evaluate("x * 3", x), Interval::new(-3.0, 6.0), 1e-12);
+    assert_encloses(evaluate("x * -x", x), Interval::new(-4.0, 2.0), 1e-12);
+    assert_encloses(evaluate("-x", x), Interval::new(-2.0, 1.0), 0.0);
+    assert_encloses(evaluate("x % 1.5", x), Interval::new(-1.0, 1.5), 1e-12);
+    // A number multiplied by itself is not negative.
+    assert_encloses(evaluate("x * x", x), Interval::new(0.0, 4.0), 1e-12);
+    assert_encloses(evaluate("x ^ 2", x), Interval::new(0.0, 4.0), 1e-12);
+}
+
+#[test]
+fn division() {
+    let x = Interval::new(1.0, 2.0);
+    assert_encloses(evaluate("1 / x", x), Interval::new(0.5, 1.0), 1e-12);
+    assert_encloses(evaluate("x / -x", x), Interval::new(-2.0, -0.5), 1e-12);
+    assert_eq!(
+        evaluate("1 / (x - 1)", x),
+        Interval::new(f64::NEG_INFINITY, f64::INFINITY)
+    );
+}
+
+#[test]
+fn outward_rounding() {
+    let x = Interval::point(0.0);
+    // `0.1` is not representable, so it is enclosed by its neighbours.
+    let tenth = evaluate("0.1", x);
+    assert!(tenth.lower < tenth.upper);
+    assert_contains(tenth, 0.1);
+    let sum = evaluate("0.1 + 0.2", x);
+    assert!(sum.lower < 0.3 && 0.3 < sum.upper);
+    // Exact results are still rounded outward.
+    let sum = evaluate("x + 1", x);
+    assert!(sum.lower < 1.0 && 1.0 < sum.upper);
+    // Integers are exact.
+    assert_eq!(evaluate("3", x), Interval::point(3.0));
+    let tiny = evaluate("x * 1e-300 * 1e-300", Interval::point(1.0));
+    assert!(tiny.lower < 0.0 && 0.0 < tiny.upper);
+    let infinite = evaluate("x + 1", Interval::new(f64::NEG_INFINITY, f64::INFINITY));
+    assert_eq!(infinite, Interval::new(f64::NEG_INFINITY, f64::INFINITY));
+}
+
+#[test]
+fn comparisons() {
+    let x = Interval::new(0.0, 2.0);
+    assert_eq!(evaluate("x < 3", x), Interval::point(1.0));
+    assert_eq!(evaluate("x > 3", x), Interval::point(0.0));
+    assert_eq!(evaluate("x < 1", x), Interval::new(0.0, 1.0));
+    assert_eq!(evaluate("x >= 0", x), Interval::point(1.0));
+    assert_eq!(evaluate("x == 3", x), Interval::point(0.0));
+    assert_eq!(evaluate("x == 1", x), Interval::new(0.0, 1.0));
+    assert_eq!(evaluate("x != -1", x), Interval::point(1.0));
+    assert_eq!(evaluate("!(x < 1)", x), Interval::new(0.0, 1.0));
+    assert_eq!(evaluate("!(x < 3)", x), Interval::point(0.0));
+    assert_eq!(evaluate("x < 1 && x >= 0", x), Interval::new(0.0, 1.0));
+    assert_eq!(evaluate("x < 1 && x > 3", x), Interval::point(0.0));
+    assert_eq!(evaluate("x < 1 || x < 3", x), Interval::point(1.0));
+    assert_eq!(evaluate("x ? 1 : 0", x), Interval::new(0.0, 1.0));
+    assert_eq!(
+        evaluate("x ? 1 : 0", Interval::point(2.0)),
+        Interval::point(1.0)
+    );
+}
+
+#[test]
+fn conditionals() {
+    let x = Interval::new(0.0, 2.0);
+    assert_encloses(evaluate("x < 3 ? x : -x", x), x, 0.0);
+    assert_encloses(evaluate("x > 3 ? x : -x", x), Interval::new(-2.0, 0.0), 0.0);
+    // Undecided conditions result in the union of both branches.
+    assert_encloses(evaluate("x < 1 ? x : 10", x), Interval::new(0.0, 10.0), 0.0);
+    assert_encloses(
+        evaluate("y = 5; if x < 1 { y = -5 } else { y = y }; y", x),
+        Interval::new(-5.0, 5.0),
+        0.0,
+    );
+    assert_encloses(
+        evaluate("y = 5; if x < 3 { y = -5 } else { y = y }; y", x),
+        Interval::point(-5.0),
+        0.0,
+    );
+}
+
+#[test]
+fn functions() {
+    let x = Interval::new(1.0, 4.0);
+    assert_encloses(evaluate("sqrt(x)", x), Interval::new(1.0, 2.0), 1e-12);
+    assert_encloses(
+        evaluate("sqrt(x - 2)", x),
+        Interval::new(0.0, 2f64.sqrt()),
+        1e-12,
+    );
+    assert_encloses(evaluate("ln(x)", x), Interval::new(0.0, 4f64.ln()), 1e-12);
+    assert_encloses(
+        evaluate("exp(-x)", x),
+        Interval::new((-4f64).exp(), (-1f64).exp()),
+        1e-12,
+    );
+    assert_encloses(evaluate("acos(x - 2)", x), Interval::new(0.0, PI), 1e-12);
+    assert_encloses(evaluate("abs(x - 3)", x), Interval::new(0.0, 2.0), 1e-12);
+    assert_encloses(
+        evaluate("cosh(x - 2)", x),
+        Interval::new(1.0, 2f64.cosh()),
+        1e-12,
+    );
+    assert_encloses(evaluate("min(x, 2)", x), Interval::new(1.0, 2.0), 0.0);
+    assert_encloses(evaluate("floor(x / 3)", x), Interval::new(0.0, 1.0), 1e-12);
+    assert_encloses(
+        evaluate("atan2(x, -1)", x),
+        Interval::new(PI - 4f64.atan(), PI - 1f64.atan()),
+        1e-12,
+    );
+    assert_encloses(
+        evaluate("atan2(x - 2, -1)", x),
+        Interval::new(-PI, PI),
+        1e-12,
+    );
+    assert_eq!(evaluate("is_nan(x)", x), Interval::point(0.0));
+    assert_eq!(
+        evaluate("is_finite(x)", Interval::new(0.0, f64::INFINITY)),
+        Interval::new(0.0, 1.0)
+    );
+    assert!(evaluate("sqrt(x - 5)", x).lower.is_nan());
+}
+
+#[test]
+fn trigonometry() {
+    // Extrema inside the interval are included.
+    assert_encloses(
+        evaluate("sin(x)", Interval::new(1.0, 2.0)),
+        Interval::new(1f64.sin(), 1.0),
+        1e-12,
+    );
+    assert_encloses(
+        evaluate("cos(x)", Interval::new(3.0, 4.0)),
+        Interval::new(-1.0, 3f64.cos().max(4f64.cos())),
+        1e-12,
+    );
+    assert_encloses(
+        evaluate("sin(x)", Interval::new(0.0, 1.0)),
+        Interval::new(0.0, 1f64.sin()),
+        1e-12,
+    );
+    assert_eq!(
+        evaluate("cos(x)", Interval::new(0.0, 10.0)),
+        Interval::new(-1.0, 1.0)
+    );
+    assert_eq!(
+        evaluate("tan(x)", Interval::new(1.0, 2.0)),
+        Interval::new(f64::NEG_INFINITY, f64::INFINITY)
+    );
+    assert_encloses(
+        evaluate("tan(x)", Interval::new(-1.0, 1.0)),
+        Interval::new(-(1f64.tan()), 1f64.tan()),
+        1e-12,
+    );
+}
+
+#[test]
+fn powers() {
+    let x = Interval::new(-2.0, 3.0);
+    assert_encloses(evaluate("x ^ 3", x), Interval::new(-8.0, 27.0), 1e-12);
+    assert_encloses(evaluate("pow(x, 2)", x), Interval::new(0.0, 9.0), 1e-12);
+    assert_encloses(evaluate("pow(x, 3)", x), Interval::new(-8.0, 27.0), 1e-12);
+    assert_eq!(
+        evaluate("pow(x, -1)", x),
+        Interval::new(f64::NEG_INFINITY, f64::INFINITY)
+    );
+    assert_encloses(
+        evaluate("pow(x, -2)", Interval::new(1.0, 2.0)),
+        Interval::new(0.25, 1.0),
+        1e-12,
+    );
+    // Fractional exponents are restricted to non-negative bases.
+    assert_encloses(
+        evaluate("pow(x, 0.5)", x),
+        Interval::new(0.0, 3f64.sqrt()),
+        1e-12,
+    );
+    assert_encloses(evaluate("2 ^ x", x), Interval::new(0.25, 8.0), 1e-12);
+}
+
+#[test]
+fn large_integer_powers() {
+    // Correctly rounded references of `1.000987488 ^ n`.
+    for (x, expression, expected) in [
+        (1.000987488, "x ^ -98343", 7.004436979197978e-43),
+        (1.000987488, "pow(x, -98343)", 7.004436979197978e-43),
+        (1.000987488, "x ^ 98343", 1.4276664962078108e42),
+        (-1.000987488, "x ^ 98343", -1.4276664962078108e42),
+        (-1.000987488, "pow(x, -98343)", -7.004436979197978e-43),
+    ] {
+        assert_contains(evaluate(expression, Interval::point(x)), expected);
+    }
+}
+
+#[test]
+fn single_precision() {
+    let function = JIT::<Interval<f32>, IntervalFunctions<f32>>::default()
+        .compile_dynamic("x / 3 + sqrt(x)", &["x"])
+        .expect("Compilation failed");
+    let result = function.call(&[Interval::new(1.0, 4.0)]).unwrap();
+    assert!(result.lower < 1.0 + 1.0 / 3.0 && result.lower > 1.33);
+    assert!(result.upper > 4.0 / 3.0 + 2.0 && result.upper < 3.34);
+    // The bounds of `0.1` are the neighbouring `f32` values.
+    let function = JIT::<Interval<f32>, IntervalFunctions<f32>>::default()
+        .compile_dynamic("0.1", &["x"])
+        .expect("Compilation failed");
+    let tenth = function.call(&[Interval::point(0.0)]).unwrap();
+    assert!((tenth.lower as f64) < 0.1 && 0.1 < (tenth.upper as f64));
+}
+
+#[test]
+fn batch() {
+    let mut jit = JIT::<Interval<f64>, IntervalFunctions<f64>>::default();
+    let code_ptr = jit
+        .compile_batch("x < 1 ? x * x : sqrt(x)", &["x"])
+        .expect("Compilation failed");
+    // SAFETY: batch functions have exactly this signature.
+    let function = unsafe {
+        std::mem::transmute::<
+            *const u8,
+            extern "C" fn(*const *const Interval<f64>, *mut Interval<f64>, usize),
+        >(code_ptr)
+    };
+    let input = [Interval::new(-1.0, 0.5), Interval::new(0.0, 4.0)];
+    let mut output = [Interval::default(); 2];
+    function([input.as_ptr()].as_ptr(), output.as_mut_ptr(), input.len());
+    assert_encloses(output[0], Interval::new(0.0, 1.0), 1e-12);
+    // `[0, 4] < 1` is undecided.
+    assert_encloses(output[1], Interval::new(0.0, 16.0), 1e-12);
+}
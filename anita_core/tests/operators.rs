@@ -57,6 +57,30 @@ fn assignment() {
     assert_eq!(result, 2.0 + 2.0);
 }
 
+#[test]
+fn compound_assignment() {
+    let func = compile_expression!("acc = 1; acc += x * 2; acc *= 3; acc -= 1; acc", (x) -> f32)
+        .expect("Compilation failed");
+    assert_eq!(func(2.0), 14.0);
+    let func =
+        compile_expression!("x /= 2; x %= 3; x ^= 2; x", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(10.0), 4.0);
+    let func =
+        compile_expression!("y = x; y += y -= 1; y", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(3.0), 5.0);
+}
+
+#[test]
+fn increment() {
+    let func = compile_expression!("y = x; y++; ++y; for i in 0..3 { y-- }; y", (x) -> f32)
+        .expect("Compilation failed");
+    assert_eq!(func(2.0), 1.0);
+    // Only whole statements increment, otherwise `--` is a double negation.
+    let func = compile_expression!("x--1 + --x", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(2.0), 5.0);
+    assert!(compile_expression!("x++ + 1", (x) -> f32).is_err());
+}
+
 #[test]
 fn chain() {
     let func =
@@ -71,3 +95,76 @@ fn neg() {
     let result = func(2.0);
     assert_eq!(result, -2.0);
 }
+
+#[test]
+fn logical_and() {
+    let func = compile_expression!("x && y", (x, y) -> f32).expect("Compilation failed");
+    assert_eq!(func(2.0, -1.0), 1.0);
+    assert_eq!(func(0.0, 1.0), 0.0);
+    assert_eq!(func(1.0, 0.0), 0.0);
+}
+
+#[test]
+fn logical_or() {
+    let func = compile_expression!("x || y", (x, y) -> f32).expect("Compilation failed");
+    assert_eq!(func(0.0, 3.0), 1.0);
+    assert_eq!(func(2.0, 0.0), 1.0);
+    assert_eq!(func(0.0, 0.0), 0.0);
+}
+
+#[test]
+fn short_circuit() {
+    let func =
+        compile_expression!("y = 0; x && (y = 2); y", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(0.0), 0.0);
+    assert_eq!(func(1.0), 2.0);
+    let func =
+        compile_expression!("y = 0; x || (y = 2); y", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(0.0), 2.0);
+    assert_eq!(func(1.0), 0.0);
+}
+
+#[test]
+fn lazy_if() {
+    let func = compile_expression!("y = 0; z = if(x, y = 1, y - 1); y + z", (x) -> f32)
+        .expect("Compilation failed");
+    assert_eq!(func(1.0), 2.0);
+    assert_eq!(func(0.0), -1.0);
+    assert_eq!(func(f32::MIN_POSITIVE / 2.0), 2.0);
+}
+
+#[test]
+fn comparisons_in_arithmetic() {
+    let func = compile_expression!("(x > 1) + 2", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(1.5), 3.0);
+    assert_eq!(func(0.5), 2.0);
+    let func =
+        compile_expression!("(x > 1) * (x < 3) * x - !x", (x) -> f64).expect("Compilation failed");
+    assert_eq!(func(2.0), 2.0);
+    assert_eq!(func(0.0), -1.0);
+    let func = compile_expression!("y = x >= 2; y + y", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(2.0), 2.0);
+}
+
+#[test]
+fn conditions_of_comparisons() {
+    let func = compile_expression!("(x > 1 ? x < 3 : x == 0) ? 10 : 20", (x) -> f32)
+        .expect("Compilation failed");
+    assert_eq!(func(2.0), 10.0);
+    assert_eq!(func(4.0), 20.0);
+    assert_eq!(func(0.0), 10.0);
+    let func =
+        compile_expression!("(x > 1 ? 5 : x < -1) + 1", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(2.0), 6.0);
+    assert_eq!(func(-2.0), 2.0);
+    assert_eq!(func(0.0), 1.0);
+    let func = compile_expression!("x > 1 ? x < 3 : 10", (x) -> f32).expect("Compilation failed");
+    assert_eq!(func(2.0), 1.0);
+    assert_eq!(func(4.0), 0.0);
+    assert_eq!(func(0.0), 10.0);
+    let func =
+        compile_expression!("x > 0 && x < 1 || x > 5", (x) -> f64).expect("Compilation failed");
+    assert_eq!(func(0.5), 1.0);
+    assert_eq!(func(3.0), 0.0);
+    assert_eq!(func(6.0), 1.0);
+}
@@ -0,0 +1,143 @@
+use anita_core::{
+    compile_expression,
+    function_manager::NoFunctions,
+    jit::{
+        frontend::{
+            optimizer::{optimize, optimize_assuming_finite},
+            parser, Expr,
+        },
+        JITError, JIT,
+    },
+};
+
+fn optimized(expression: &str) -> Expr {
+    optimize::<f32>(parser::expression(expression).expect("Parsing failed"))
+}
+
+fn parsed(expression: &str) -> Expr {
+    parser::expression(expression).expect("Parsing failed")
+}
+
+#[track_caller]
+fn assert_same(actual: Expr, expected: Expr) {
+    assert!(
+        actual.same_structure(&expected),
+        "{actual} is not {expected}"
+    );
+}
+
+#[test]
+fn constant_folding() {
+    assert_same(optimized("2 * 3 + x"), parsed("6 + x"));
+    assert_eq!(optimized("2 ^ 10"), Expr::Const { value: 1024.0 });
+    assert_eq!(optimized("7 % 4 - 1"), Expr::Const { value: 2.0 });
+    assert_eq!(optimized("-(2 + 3)"), Expr::Const { value: -5.0 });
+}
+
+#[test]
+fn identities() {
+    assert_same(optimized("x * 1"), parsed("x"));
+    assert_same(optimized("1 * x"), parsed("x"));
+    assert_same(optimized("x + 0"), parsed("x"));
+    assert_same(optimized("x - (2 - 2)"), parsed("x"));
+    assert_same(optimized("x / 1"), parsed("x"));
+    assert_same(optimized("-(-x)"), parsed("x"));
+    assert_same(optimized("0 - x"), parsed("-x"));
+    // Both are NaN for infinite `x`.
+    assert_same(optimized("x - x"), parsed("x - x"));
+    assert_same(optimized("x * 0"), parsed("x * 0"));
+}
+
+#[test]
+fn finite_identities() {
+    let optimized = |expression| optimize_assuming_finite::<f32>(parsed(expression));
+    assert_eq!(optimized("x - x"), Expr::Const { value: 0.0 });
+    assert_same(optimized("(x + 1) - (x + 1) + y"), parsed("y"));
+    assert_same(optimized("(y = x) - (y = x)"), parsed("(y = x) - (y = x)"));
+
+    let function = JIT::<f64, NoFunctions>::default()
+        .compile_dynamic("x - x", &["x"])
+        .expect("Compilation failed");
+    assert!(function.call(&[f64::INFINITY]).unwrap().is_nan());
+    let mut jit = JIT::<f64, NoFunctions>::default();
+    jit.set_assume_finite(true);
+    let function = jit
+        .compile_dynamic("x - x", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[f64::INFINITY]), Ok(0.0));
+}
+
+#[test]
+fn impure_expressions_are_kept() {
+    assert_same(optimized("(y = x) - (y = x)"), parsed("(y = x) - (y = x)"));
+    assert_same(optimized("y = 2; x"), parsed("y = 2; x"));
+    assert_same(optimized("2 + 3; x"), parsed("x"));
+}
+
+#[test]
+fn power_strength_reduction() {
+    assert_same(optimized("x ^ 2"), parsed("x * x"));
+    assert_same(optimized("x ^ 3"), parsed("x * x * x"));
+    assert_same(optimized("x ^ 1"), parsed("x"));
+    assert_eq!(optimized("x ^ 0"), Expr::Const { value: 1.0 });
+    assert_same(optimized("x ^ -2"), parsed("1 / (x * x)"));
+    assert_same(optimized("x ^ 2.5"), parsed("x ^ 2.5"));
+    assert_same(optimized("x ^ 5"), parsed("x ^ 5"));
+}
+
+#[test]
+fn f64_folding_keeps_precision() {
+    let folded = optimize::<f64>(parsed("1 / 3"));
+    assert_eq!(folded, Expr::Const { value: 1.0 / 3.0 });
+    let folded = optimize::<f64>(parsed("0.1 + 0.2"));
+    assert_eq!(folded, Expr::Const { value: 0.1 + 0.2 });
+}
+
+#[test]
+fn f32_folding_uses_f32_arithmetic() {
+    assert_eq!(
+        optimized("0.1 + 0.2"),
+        Expr::Const {
+            value: f64::from(0.1_f32 + 0.2_f32)
+        }
+    );
+    assert_eq!(optimized("1e-50 ? x : 2"), Expr::Const { value: 2.0 });
+    assert_same(optimize::<f64>(parsed("1e-50 ? x : 2")), parsed("x"));
+}
+
+#[test]
+fn optimization_can_be_disabled() {
+    let mut jit = JIT::<f32, NoFunctions>::default();
+    jit.set_optimize(false);
+    let function = jit
+        .compile_dynamic("x ^ 3 + 2 * 3", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[2.0]), Ok(14.0));
+}
+
+#[test]
+fn zero_dividend_keeps_nan() {
+    assert_same(optimized("0 / x"), parsed("0 / x"));
+    assert_eq!(optimize::<i32>(parsed("0 / x")), Expr::Const { value: 0.0 });
+
+    let optimized = JIT::<f64, NoFunctions>::default()
+        .compile_dynamic("0 / x", &["x"])
+        .expect("Compilation failed");
+    let mut jit = JIT::<f64, NoFunctions>::default();
+    jit.set_optimize(false);
+    let unoptimized = jit
+        .compile_dynamic("0 / x", &["x"])
+        .expect("Compilation failed");
+    assert!(optimized.call(&[0.0]).unwrap().is_nan());
+    assert!(unoptimized.call(&[0.0]).unwrap().is_nan());
+    assert_eq!(optimized.call(&[2.0]), unoptimized.call(&[2.0]));
+}
+
+#[test]
+fn uninitialized_variables_are_reported_before_optimization() {
+    let result = compile_expression!("a - a + x", (x) -> f32);
+    assert!(matches!(
+        result,
+        Err(JITError::UseOfUninitializedVariables(_))
+    ));
+}
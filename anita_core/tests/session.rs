@@ -0,0 +1,59 @@
+use anita_core::{
+    function_manager::NoFunctions,
+    jit::{session::Session, JITError},
+};
+
+#[test]
+fn multiple_expressions() {
+    let mut session = Session::<f32, NoFunctions>::default();
+    session
+        .add("area", "a * b", &["a", "b"])
+        .expect("Compilation failed")
+        .add("square", "x ^ 2", &["x"])
+        .expect("Compilation failed");
+    let module = session.finish();
+    let area = module.get("area").expect("Missing function");
+    let square = module.get("square").expect("Missing function");
+    assert_eq!(area.call(&[2.0, 3.0]), Ok(6.0));
+    assert_eq!(square.call(&[3.0]), Ok(9.0));
+    assert!(module.get("missing").is_none());
+    let mut names: Vec<&str> = module.names().collect();
+    names.sort();
+    assert_eq!(names, ["area", "square"]);
+}
+
+#[test]
+fn functions_outlive_module() {
+    let mut session = Session::<f64, NoFunctions>::default();
+    session
+        .add("f", "x + 1", &["x"])
+        .expect("Compilation failed");
+    let function = session.finish().get("f").expect("Missing function");
+    assert_eq!(function.call(&[1.0]), Ok(2.0));
+}
+
+#[test]
+fn failed_expression_keeps_session_usable() {
+    let mut session = Session::<f32, NoFunctions>::default();
+    assert!(matches!(
+        session.add("broken", "x +", &["x"]),
+        Err(JITError::ParseError(_))
+    ));
+    assert!(matches!(
+        session.add("unknown", "foo(x)", &["x"]),
+        Err(JITError::TranslatorError(_))
+    ));
+    session
+        .add("ok", "x * 2", &["x"])
+        .expect("Compilation failed");
+    assert!(matches!(
+        session.add("ok", "x", &["x"]),
+        Err(JITError::DuplicateFunction(_))
+    ));
+    let module = session.finish();
+    assert_eq!(
+        module.get("ok").expect("Missing function").call(&[4.0]),
+        Ok(8.0)
+    );
+    assert!(module.get("broken").is_none());
+}
@@ -0,0 +1,76 @@
+use anita_core::{
+    compile_batch_expression,
+    function_manager::NoFunctions,
+    jit::{JITError, JIT},
+};
+
+fn uninitialized(expression: &str, parameters: &[&str]) -> Vec<String> {
+    match JIT::<f32, NoFunctions>::default().compile_dynamic(expression, parameters) {
+        Ok(_) => Vec::new(),
+        Err(JITError::UseOfUninitializedVariables(variables)) => {
+            variables.iter().map(|(name, _)| name.clone()).collect()
+        }
+        Err(e) => panic!("Expected JITError::UseOfUninitializedVariables but got: {e:?}"),
+    }
+}
+
+#[test]
+fn read_before_assignment() {
+    assert_eq!(uninitialized("x = y; y = 1", &[]), ["y"]);
+    assert_eq!(uninitialized("y = y + 1", &[]), ["y"]);
+    assert_eq!(uninitialized("y + (y = 1)", &[]), ["y"]);
+    assert!(uninitialized("(y = 1) + y", &[]).is_empty());
+    assert!(uninitialized("y = 1; x = y; x", &[]).is_empty());
+}
+
+#[test]
+fn compound_assignment() {
+    assert_eq!(uninitialized("acc += x", &["x"]), ["acc"]);
+    assert_eq!(uninitialized("acc = x; y *= acc; y", &["x"]), ["y"]);
+    assert_eq!(
+        uninitialized("x > 0 && (acc = 1); acc ^= 2", &["x"]),
+        ["acc"]
+    );
+    assert!(uninitialized("acc = 0; acc += x; acc -= 1; acc", &["x"]).is_empty());
+    assert!(uninitialized("x /= 2; x %= 3", &["x"]).is_empty());
+    assert_eq!(uninitialized("n++; n", &[]), ["n"]);
+}
+
+#[test]
+fn conditional_assignment() {
+    assert_eq!(uninitialized("x > 0 && (y = 1); y", &["x"]), ["y"]);
+    assert_eq!(uninitialized("x > 0 || (y = 1); y", &["x"]), ["y"]);
+    assert_eq!(uninitialized("if x { y = 1 } else { 0 }; y", &["x"]), ["y"]);
+    assert_eq!(uninitialized("x ? 0 : (y = 1); y", &["x"]), ["y"]);
+    assert!(uninitialized("(y = 1) && x; y", &["x"]).is_empty());
+    assert!(uninitialized("if x { y = 1 } else { y = 2 }; y", &["x"]).is_empty());
+    assert!(uninitialized("x ? (y = 1) : (y = 2); y", &["x"]).is_empty());
+    assert!(uninitialized("if (y = x) { 1 } else { 2 }; y", &["x"]).is_empty());
+}
+
+#[test]
+fn reported_once_per_variable() {
+    let result = JIT::<f32, NoFunctions>::default().compile_dynamic("a + b + a", &[]);
+    let Err(JITError::UseOfUninitializedVariables(variables)) = result else {
+        panic!("Expected JITError::UseOfUninitializedVariables");
+    };
+    let spans: Vec<(&str, usize)> = variables
+        .iter()
+        .map(|(name, span)| (name.as_str(), span.start))
+        .collect();
+    assert_eq!(spans, [("a", 0), ("b", 4)]);
+}
+
+#[test]
+fn unoptimized_and_batch_analysis() {
+    let mut jit = JIT::<f32, NoFunctions>::default();
+    jit.set_optimize(false);
+    assert!(matches!(
+        jit.compile_dynamic("x = y; y = 1", &["x"]),
+        Err(JITError::UseOfUninitializedVariables(_))
+    ));
+    assert!(matches!(
+        compile_batch_expression!("x > 0 && (y = 1); y", (x) -> f32),
+        Err(JITError::UseOfUninitializedVariables(_))
+    ));
+}
@@ -50,6 +50,7 @@ struct NameAttribute {
 
 mod keyword {
     syn::custom_keyword!(name);
+    syn::custom_keyword!(derivative);
 }
 
 impl Parse for NameAttribute {
@@ -64,9 +65,32 @@ impl Parse for NameAttribute {
     }
 }
 
+/// `#[derivative("partial", ..)]` with one partial derivative per function argument.
+struct DerivativeAttribute {
+    span: proc_macro2::Span,
+    partials: Vec<LitStr>,
+}
+
+impl Parse for DerivativeAttribute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let _pound = input.parse::<Token![#]>()?;
+        let attribute_inner;
+        let bracket = bracketed!(attribute_inner in input);
+        let _derivative_attribute = attribute_inner.parse::<keyword::derivative>()?;
+        let partials_block;
+        let _par = parenthesized!(partials_block in attribute_inner);
+        let partials = parse_all(&partials_block)?;
+        Ok(Self {
+            span: bracket.span.join(),
+            partials,
+        })
+    }
+}
+
 struct Function {
     fn_item: ItemFn,
     alias: Option<LitStr>,
+    derivative: Option<DerivativeAttribute>,
     ident: Ident,
     arguments: Vec<PatType>,
     return_type: ReturnType,
@@ -78,7 +102,15 @@ impl Parse for Function {
         let _attrs = forked.call(Attribute::parse_outer);
         let fn_item = forked.parse()?;
 
-        let name_attribute: Option<NameAttribute> = input.parse().ok();
+        let mut name_attribute: Option<NameAttribute> = None;
+        let mut derivative = None;
+        while input.peek(Token![#]) {
+            if input.fork().parse::<NameAttribute>().is_ok() {
+                name_attribute = Some(input.parse()?);
+            } else {
+                derivative = Some(input.parse::<DerivativeAttribute>()?);
+            }
+        }
         let _fn = input.parse::<Token![fn]>()?;
         let ident = input.parse()?;
         let arguments_block;
@@ -90,6 +122,7 @@ impl Parse for Function {
         Ok(Self {
             fn_item,
             alias: name_attribute.map(|a| a.alias),
+            derivative,
             ident,
             arguments,
             return_type,
@@ -167,6 +200,69 @@ impl ToTokens for FunctionSignature {
         });
     }
 }
+struct FunctionDerivatives {
+    crate_name: proc_macro2::TokenStream,
+    ident: LitStr,
+    parameters: Vec<LitStr>,
+    partials: Vec<LitStr>,
+}
+
+impl FunctionDerivatives {
+    fn try_from_function(
+        value: &Function,
+        crate_name: proc_macro2::TokenStream,
+    ) -> syn::Result<Option<Self>> {
+        let Some(derivative) = &value.derivative else {
+            return Ok(None);
+        };
+        if derivative.partials.len() != value.arguments.len() {
+            return Err(syn::Error::new(
+                derivative.span,
+                format!(
+                    "Expected {} partial derivatives, one for each argument",
+                    value.arguments.len()
+                ),
+            ));
+        }
+        let parameters = value
+            .arguments
+            .iter()
+            .map(|argument| match argument.pat.as_ref() {
+                syn::Pat::Ident(pat) => Ok(LitStr::new(&pat.ident.to_string(), pat.ident.span())),
+                pat => Err(syn::Error::new_spanned(
+                    pat,
+                    "Functions with derivatives must have named arguments",
+                )),
+            })
+            .collect::<syn::Result<Vec<LitStr>>>()?;
+        let ident = match value.alias {
+            Some(ref alias) => alias.clone(),
+            None => LitStr::new(&value.ident.to_string(), value.ident.span()),
+        };
+        Ok(Some(Self {
+            crate_name,
+            ident,
+            parameters,
+            partials: derivative.partials.clone(),
+        }))
+    }
+}
+
+impl ToTokens for FunctionDerivatives {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let crate_name = &self.crate_name;
+        let ident = &self.ident;
+        let parameters = &self.parameters;
+        let partials = &self.partials;
+        tokens.extend(quote! {
+            #ident => Some(#crate_name::Derivatives {
+                parameters: &[#(#parameters,)*],
+                partials: &[#(#partials,)*],
+            })
+        });
+    }
+}
+
 struct FunctionSymbol {
     ident: Ident,
     alias: LitStr,
@@ -199,6 +295,7 @@ struct FunctionManager {
     crate_name: proc_macro2::TokenStream,
     ident: Ident,
     function_signatures: Vec<FunctionSignature>,
+    function_derivatives: Vec<FunctionDerivatives>,
     function_symbols: Vec<FunctionSymbol>,
 }
 
@@ -210,11 +307,19 @@ impl FunctionManager {
             .iter()
             .map(|f| FunctionSignature::try_from_function(f, crate_name.clone()))
             .collect::<syn::Result<Vec<FunctionSignature>>>()?;
+        let function_derivatives = impl_block
+            .functions
+            .iter()
+            .filter_map(|f| {
+                FunctionDerivatives::try_from_function(f, crate_name.clone()).transpose()
+            })
+            .collect::<syn::Result<Vec<FunctionDerivatives>>>()?;
         let function_symbols = impl_block.functions.iter().map(|f| f.into()).collect();
         Ok(Self {
             crate_name,
             ident,
             function_signatures,
+            function_derivatives,
             function_symbols,
         })
     }
@@ -225,6 +330,7 @@ impl ToTokens for FunctionManager {
         let ident = &self.ident;
         let function_symbols = &self.function_symbols;
         let function_signatures = &self.function_signatures;
+        let function_derivatives = &self.function_derivatives;
         let crate_name = &self.crate_name;
         tokens.extend(quote! {
             impl #crate_name::FunctionManager for #ident {
@@ -241,6 +347,13 @@ impl ToTokens for FunctionManager {
                         _ => None
                     }
                 }
+
+                fn function_derivatives(identifier: &str) -> Option<#crate_name::Derivatives> {
+                    match identifier {
+                        #(#function_derivatives,)*
+                        _ => None
+                    }
+                }
             }
         });
     }
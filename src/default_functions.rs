@@ -12,176 +12,200 @@ impl DefaultFunctions {}
 #[function_manager]
 impl DefaultFunctions {
     #[name = "min"]
+    #[derivative("x <= y", "x > y")]
     fn internal_min(x: f32, y: f32) -> f32 {
         x.min(y)
     }
 
     #[name = "max"]
+    #[derivative("x >= y", "x < y")]
     fn internal_max(x: f32, y: f32) -> f32 {
         x.max(y)
     }
 
     #[name = "floor"]
+    #[derivative("0")]
     fn internal_floor(x: f32) -> f32 {
         x.floor()
     }
 
     #[name = "round"]
+    #[derivative("0")]
     fn internal_round(x: f32) -> f32 {
         x.round()
     }
 
     #[name = "ceil"]
+    #[derivative("0")]
     fn internal_ceil(x: f32) -> f32 {
         x.ceil()
     }
 
     #[name = "is_nan"]
+    #[derivative("0")]
     fn internal_is_nan(x: f32) -> f32 {
         x.is_nan() as u8 as f32
     }
 
     #[name = "is_finite"]
+    #[derivative("0")]
     fn internal_is_finite(x: f32) -> f32 {
         x.is_finite() as u8 as f32
     }
 
     #[name = "is_infinite"]
+    #[derivative("0")]
     fn internal_is_infinite(x: f32) -> f32 {
         x.is_infinite() as u8 as f32
     }
 
     #[name = "is_normal"]
+    #[derivative("0")]
     fn internal_is_normal(x: f32) -> f32 {
         x.is_normal() as u8 as f32
     }
 
     #[name = "pow"]
+    #[derivative("x * pow(a, x - 1)", "pow(a, x) * ln(a)")]
     fn internal_pow(a: f32, x: f32) -> f32 {
         a.powf(x)
     }
 
     #[name = "mod"]
+    #[derivative("1", "(x % y - x) / y")]
     fn internal_mod(x: f32, y: f32) -> f32 {
         x % y
     }
 
     #[name = "ln"]
+    #[derivative("1 / x")]
     fn internal_ln(x: f32) -> f32 {
         x.ln()
     }
 
     #[name = "log2"]
+    #[derivative("1 / (x * ln(2))")]
     fn internal_log2(x: f32) -> f32 {
         x.log2()
     }
 
     #[name = "log10"]
+    #[derivative("1 / (x * ln(10))")]
     fn internal_log10(x: f32) -> f32 {
         x.log10()
     }
 
     #[name = "exp"]
+    #[derivative("exp(x)")]
     fn internal_exp(x: f32) -> f32 {
         x.exp()
     }
 
     #[name = "exp2"]
+    #[derivative("exp2(x) * ln(2)")]
     fn internal_exp2(x: f32) -> f32 {
         x.exp2()
     }
 
     #[name = "cos"]
+    #[derivative("-sin(x)")]
     fn internal_cos(x: f32) -> f32 {
         x.cos()
     }
 
     #[name = "acos"]
+    #[derivative("-1 / sqrt(1 - x * x)")]
     fn internal_acos(x: f32) -> f32 {
         x.acos()
     }
 
     #[name = "cosh"]
+    #[derivative("sinh(x)")]
     fn internal_cosh(x: f32) -> f32 {
         x.cosh()
     }
 
     #[name = "acosh"]
+    #[derivative("1 / sqrt(x * x - 1)")]
     fn internal_acosh(x: f32) -> f32 {
         x.acosh()
     }
 
     #[name = "sin"]
+    #[derivative("cos(x)")]
     fn internal_sin(x: f32) -> f32 {
         x.sin()
     }
 
     #[name = "asin"]
+    #[derivative("1 / sqrt(1 - x * x)")]
     fn internal_asin(x: f32) -> f32 {
         x.asin()
     }
 
     #[name = "sinh"]
+    #[derivative("cosh(x)")]
     fn internal_sinh(x: f32) -> f32 {
         x.sinh()
     }
 
     #[name = "asinh"]
+    #[derivative("1 / sqrt(x * x + 1)")]
     fn internal_asinh(x: f32) -> f32 {
         x.asinh()
     }
 
     #[name = "tan"]
+    #[derivative("1 + tan(x) ^ 2")]
     fn internal_tan(x: f32) -> f32 {
         x.tan()
     }
 
     #[name = "atan"]
+    #[derivative("1 / (1 + x * x)")]
     fn internal_atan(x: f32) -> f32 {
         x.atan()
     }
 
     #[name = "atan2"]
+    #[derivative("y / (x * x + y * y)", "-x / (x * x + y * y)")]
     fn internal_atan2(x: f32, y: f32) -> f32 {
         x.atan2(y)
     }
 
     #[name = "tanh"]
+    #[derivative("1 - tanh(x) ^ 2")]
     fn internal_tanh(x: f32) -> f32 {
         x.tanh()
     }
 
     #[name = "atanh"]
+    #[derivative("1 / (1 - x * x)")]
     fn internal_atanh(x: f32) -> f32 {
         x.atanh()
     }
 
     #[name = "sqrt"]
+    #[derivative("0.5 / sqrt(x)")]
     fn internal_sqrt(x: f32) -> f32 {
         x.sqrt()
     }
 
     #[name = "cbrt"]
+    #[derivative("1 / (3 * cbrt(x) ^ 2)")]
     fn internal_cbrt(x: f32) -> f32 {
         x.cbrt()
     }
 
     #[name = "abs"]
+    #[derivative("(x > 0) - (x < 0)")]
     fn internal_abs(x: f32) -> f32 {
         x.abs()
     }
 
     #[name = "hypot"]
+    #[derivative("x / hypot(x, y)", "y / hypot(x, y)")]
     fn internal_hypot(x: f32, y: f32) -> f32 {
         x.hypot(y)
     }
-
-    #[name = "if"]
-    fn internal_if(cond: f32, a: f32, b: f32) -> f32 {
-        if cond.is_normal() && cond != 0.0 {
-            a
-        } else {
-            b
-        }
-    }
 }
@@ -1,6 +1,9 @@
+pub use anita_core::compile_batch_expression;
 pub use anita_core::compile_expression;
 pub use anita_core::cranelift;
-pub use anita_core::function_manager::FunctionManager;
+pub use anita_core::function_manager::{Derivatives, FunctionManager};
+pub use anita_core::interpret_expression;
+pub use anita_core::interpreter;
 pub use anita_core::jit;
 pub use anita_macros;
 
@@ -0,0 +1,29 @@
+use anita::{
+    default_functions::DefaultFunctions,
+    jit::{
+        frontend::{derivative::differentiate, parser},
+        JIT,
+    },
+};
+
+/// Compares the compiled derivative of `expression` by `x` with a central difference at `x`.
+pub fn assert_matches_difference(expression: &str, x: f32) {
+    let compile = |expression: &str| {
+        JIT::<f32, DefaultFunctions>::default()
+            .compile_dynamic(expression, &["x"])
+            .unwrap_or_else(|error| panic!("{expression}: {error}"))
+    };
+    let expr = parser::expression(expression).expect("Parsing failed");
+    let derivative = differentiate::<f32, DefaultFunctions>(&expr, "x")
+        .unwrap_or_else(|error| panic!("{expression}: {error}"))
+        .to_string();
+    let (function, compiled) = (compile(expression), compile(&derivative));
+    let h = 1e-2;
+    let difference =
+        (function.call(&[x + h]).unwrap() - function.call(&[x - h]).unwrap()) / (2.0 * h);
+    let result = compiled.call(&[x]).unwrap();
+    assert!(
+        (result - difference).abs() <= 1e-2 * difference.abs().max(1.0),
+        "d/dx {expression} = {derivative} is {result} at {x}, expected {difference}"
+    );
+}
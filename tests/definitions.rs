@@ -0,0 +1,193 @@
+mod common;
+
+use anita::{
+    compile_expression,
+    default_functions::DefaultFunctions,
+    jit::{
+        frontend::{definitions::DefinitionError, parser, Expr},
+        types::{Complex, ComplexFunctions, Vec2},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+use common::assert_matches_difference;
+
+fn evaluate(expression: &str, x: f64, y: f64) -> f64 {
+    JIT::<f64, DefaultFunctions>::default()
+        .compile_dynamic(expression, &["x", "y"])
+        .unwrap_or_else(|error| panic!("{expression}: {error}"))
+        .call(&[x, y])
+        .unwrap()
+}
+
+fn definition_error(expression: &str) -> DefinitionError {
+    match JIT::<f64, DefaultFunctions>::default().compile_dynamic(expression, &["x"]) {
+        Err(JITError::DefinitionError(error)) => error,
+        Err(error) => panic!("{expression}: {error}"),
+        Ok(_) => panic!("{expression} compiled"),
+    }
+}
+
+#[test]
+fn calls() {
+    assert_eq!(evaluate("sq(a) = a * a; sq(x) + sq(y)", 3.0, 4.0), 25.0);
+    assert_eq!(evaluate("f(a, b) = a - b; f(1, f(x, y))", 5.0, 2.0), -2.0);
+    assert_eq!(evaluate("one() = 1; one() + x", 2.0, 0.0), 3.0);
+    assert_eq!(
+        evaluate("f(a) = (b = a * 2; b + 1); f(x + 1) * y", 1.0, 3.0),
+        15.0
+    );
+    // Later definitions see earlier ones and definitions shadow functions and each other.
+    assert_eq!(
+        evaluate("f(a) = a + 1; g(a) = f(a) * 2; g(x)", 1.0, 0.0),
+        4.0
+    );
+    assert_eq!(evaluate("sin(a) = a; sin(x)", 2.0, 0.0), 2.0);
+    assert_eq!(evaluate("f(a) = a; f(a) = a + 1; f(x)", 2.0, 0.0), 3.0);
+    // A definition is only visible in the rest of its chain.
+    assert_eq!(
+        evaluate("s = (f(a) = a * 2; f(x)); f(a) = a; s + f(x)", 1.0, 0.0),
+        3.0
+    );
+    let result =
+        JIT::<f64, DefaultFunctions>::default().compile_dynamic("(f(a) = a; f(x)) + f(x)", &["x"]);
+    assert!(matches!(
+        result,
+        Err(JITError::TranslatorError(
+            TranslatorError::FunctionNotFound { .. }
+        ))
+    ));
+}
+
+#[test]
+fn arguments() {
+    // Arguments are evaluated once and before the body, in order.
+    assert_eq!(
+        evaluate("f(a, b) = a * 10 + b; f(x, x = 2)", 1.0, 0.0),
+        12.0
+    );
+    assert_eq!(
+        evaluate("n = 0; f(a) = a + a; f(n = n + 1); n", 0.0, 0.0),
+        1.0
+    );
+    assert_eq!(
+        evaluate("f(a) = (a = a + 1; a * 2); f(x) + x", 1.0, 0.0),
+        5.0
+    );
+    // Variables of the body do not clash with variables around the call.
+    assert_eq!(evaluate("a = 5; f(a) = a * 2; f(x) + a", 1.0, 0.0), 7.0);
+    assert_eq!(
+        evaluate("f(n) = sum(i, 1, n, i); i = 10; f(x) + i", 3.0, 0.0),
+        16.0
+    );
+}
+
+#[test]
+fn parsing() {
+    let Ok(Expr::Chain { side, .. }) = parser::expression("f(a, b) = a * b; f(1, 2)") else {
+        panic!("Parsing failed")
+    };
+    let Expr::Function {
+        identifier,
+        parameters,
+        ..
+    } = *side
+    else {
+        panic!("Not a definition")
+    };
+    assert_eq!(
+        (identifier.as_str(), parameters),
+        ("f", vec!["a".to_owned(), "b".to_owned()])
+    );
+    for expression in [
+        "f(a) = a ^ 2; f(x)",
+        "f() = 1; g(a, b) = (h(c) = c; h(a) + b); g(f(), 2)",
+        "f(a) = (b = a; b); f(x)",
+    ] {
+        let parsed = parser::expression(expression).expect("Parsing failed");
+        let printed = parsed.to_string();
+        assert_eq!(parser::expression(&printed), Ok(parsed), "{printed}");
+    }
+    assert!(matches!(
+        parser::expression("f(x) == 1"),
+        Ok(Expr::Eq { .. })
+    ));
+    assert!(parser::expression("f(a + 1) = a").is_err());
+}
+
+#[test]
+fn errors() {
+    let error = definition_error("f(a) = f(a - 1); f(x)");
+    assert!(matches!(error, DefinitionError::Recursion { .. }));
+    assert_eq!(error.span().start, 7);
+    // A redefinition can not call the function it shadows either.
+    assert!(matches!(
+        definition_error("f(a) = a; f(a) = f(a) + 1; f(x)"),
+        DefinitionError::Recursion { .. }
+    ));
+    assert!(matches!(
+        definition_error("f(a, b) = a + b; f(x)"),
+        DefinitionError::ArityMismatch {
+            expected: 2,
+            found: 1,
+            ..
+        }
+    ));
+    assert!(matches!(
+        definition_error("f(a, a) = a; f(x, x)"),
+        DefinitionError::DuplicateParameter { .. }
+    ));
+    assert!(matches!(
+        definition_error("f(a) = a"),
+        DefinitionError::MisplacedDefinition { .. }
+    ));
+    assert!(matches!(
+        definition_error("1 + (f(a) = a); 2"),
+        DefinitionError::MisplacedDefinition { .. }
+    ));
+
+    // The body only sees its parameters.
+    let result =
+        JIT::<f64, DefaultFunctions>::default().compile_dynamic("f(a) = a + x; f(1)", &["x"]);
+    let Err(JITError::UseOfUninitializedVariables(variables)) = result else {
+        panic!("Free variable compiled")
+    };
+    assert_eq!(variables[0].0, "x");
+    assert_eq!(variables[0].1.start, 11);
+}
+
+#[test]
+fn typed() {
+    // Every call is inlined, so parameters take the types of the arguments of the call.
+    let function =
+        compile_expression!("half(a) = a / 2; half(n) + half(x)", (n: i32, x: f64) -> f64)
+            .expect("Compilation failed");
+    assert_eq!(function(4, 3.0), 3.5);
+    let function =
+        compile_expression!("f(v) = v * 2; f(vec2(x, 1)).yx + f(vec2(x))", (x: f32) -> Vec2)
+            .expect("Compilation failed");
+    assert_eq!(function(1.5), Vec2::new(5.0, 6.0));
+}
+
+#[test]
+fn complex() {
+    // `i` in a body is the imaginary unit unless it is a parameter.
+    let function = JIT::<Complex<f64>, ComplexFunctions<f64>>::default()
+        .compile_dynamic("rot(z) = z * i; f(i) = i * 2; rot(x) + f(x)", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(
+        function.call(&[Complex::new(1.0, 0.0)]),
+        Ok(Complex::new(2.0, 1.0))
+    );
+}
+
+#[test]
+fn derivatives() {
+    for (expression, x) in [
+        ("sq(a) = a * a; sq(x) + sq(x + 1)", 1.5),
+        ("f(a, b) = a * b; f(x, f(x, 3))", -0.5),
+        ("g(a) = a ^ 3; y = g(x); y * 2", 2.0),
+    ] {
+        assert_matches_difference(expression, x);
+    }
+}
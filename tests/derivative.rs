@@ -0,0 +1,199 @@
+mod common;
+
+use anita::{
+    anita_macros::function_manager,
+    compile_expression,
+    default_functions::DefaultFunctions,
+    jit::{
+        frontend::{
+            derivative::{differentiate, DifferentiationError},
+            parser, Expr, Span,
+        },
+        types::AnitaType,
+    },
+};
+
+use common::assert_matches_difference;
+
+fn derivative(expression: &str, variable: &str) -> Expr {
+    let expr = parser::expression(expression).expect("Parsing failed");
+    differentiate::<f32, DefaultFunctions>(&expr, variable).expect("Differentiation failed")
+}
+
+fn printed(expression: &str) -> String {
+    derivative(expression, "x").to_string()
+}
+
+#[test]
+fn readable_results() {
+    assert_eq!(printed("x"), "1");
+    assert_eq!(printed("y"), "0");
+    assert_eq!(printed("3 * x + y"), "3");
+    assert_eq!(printed("x * y"), "y");
+    assert_eq!(printed("sin(x)"), "cos(x)");
+    assert_eq!(printed("sin(2 * x)"), "cos(2 * x) * 2");
+    assert_eq!(printed("x ^ 3"), "3 * (x * x)");
+    assert_eq!(printed("-x"), "-1");
+    assert_eq!(printed("x > 0 ? x : -x"), "x > 0 ? 1 : -1");
+}
+
+#[test]
+fn arithmetic() {
+    for expression in [
+        "x * x * x - 2 * x",
+        "1 / x",
+        "(x + 1) / (x * x + 1)",
+        "x % 0.7",
+        "3 % x",
+        "x ^ 2.5",
+        "2 ^ x",
+        "x ^ x",
+        "-(x * 3)",
+        "x > 1 ? x * x : 2 * x",
+    ] {
+        for x in [0.45, 1.3, 1.9] {
+            assert_matches_difference(expression, x);
+        }
+    }
+}
+
+#[test]
+fn default_functions() {
+    for expression in [
+        "min(x, 2 - x)",
+        "max(x, 2 - x)",
+        "floor(x) + x",
+        "pow(x, 3)",
+        "pow(2, x)",
+        "mod(x, 0.4)",
+        "mod(3, x)",
+        "ln(x)",
+        "log2(x)",
+        "log10(x)",
+        "exp(x)",
+        "exp2(x)",
+        "cos(x)",
+        "acos(x / 3)",
+        "cosh(x)",
+        "acosh(x + 1)",
+        "sin(x)",
+        "asin(x / 3)",
+        "sinh(x)",
+        "asinh(x)",
+        "tan(x / 2)",
+        "atan(x)",
+        "atan2(x, 2)",
+        "atan2(2, x)",
+        "tanh(x)",
+        "atanh(x / 3)",
+        "sqrt(x)",
+        "cbrt(x)",
+        "abs(x - 1)",
+        "hypot(x, 2)",
+        "is_nan(x) + is_finite(x) + is_infinite(x) + is_normal(x) + round(x) + ceil(x)",
+    ] {
+        for x in [0.45, 1.3, 1.9] {
+            assert_matches_difference(expression, x);
+        }
+    }
+}
+
+#[test]
+fn assignments() {
+    assert_matches_difference("y = x * x; z = sin(y); z * y", 0.7);
+    assert_matches_difference("x = x * 2; x = x + 1; x * x", 0.7);
+    assert_matches_difference("if x > 1 { y = x } else { y = x * x }; y * 3", 0.5);
+    assert_matches_difference("if x > 1 { y = x } else { y = x * x }; y * 3", 1.5);
+    assert_eq!(printed("y = x * x; y"), "dy_dx = x + x; y = x * x; dy_dx");
+    assert_eq!(
+        printed("dy_dx = 1; y = x; y"),
+        "ddy_dx_dx = 0; dy_dx = 1; dy_dx_ = 1; y = x; dy_dx_"
+    );
+}
+
+#[test]
+fn compiles_with_macro() {
+    let derivative = derivative("a * sin(x) + x ^ 2", "x").to_string();
+    let function = compile_expression!(derivative, (x, a) -> f32, DefaultFunctions)
+        .expect("Compilation failed");
+    assert_eq!(function(0.0, 2.0), 2.0);
+}
+
+#[test]
+fn unsupported() {
+    let expr = parser::expression("x + (y = x)").expect("Parsing failed");
+    assert!(matches!(
+        differentiate::<f32, DefaultFunctions>(&expr, "x"),
+        Err(DifferentiationError::UnsupportedAssignment { .. })
+    ));
+
+    let expr = parser::expression("1 + round(x)").expect("Parsing failed");
+    assert!(differentiate::<f32, DefaultFunctions>(&expr, "x").is_ok());
+    let expr = parser::expression("sin(x)").expect("Parsing failed");
+    let error = differentiate::<f32, NoFunctions>(&expr, "x").unwrap_err();
+    assert_eq!(
+        error,
+        DifferentiationError::MissingDerivative {
+            name: "sin".to_owned(),
+            span: Span::new(0, 6)
+        }
+    );
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((0, 6))
+    );
+
+    let expr = parser::expression("2 ^ x").expect("Parsing failed");
+    assert!(matches!(
+        differentiate::<f32, NoFunctions>(&expr, "x"),
+        Err(DifferentiationError::MissingFunction { .. })
+    ));
+}
+
+struct NoFunctions;
+
+#[function_manager]
+impl NoFunctions {}
+
+struct CustomFunctions;
+
+#[function_manager]
+impl CustomFunctions {
+    #[derivative("2 * x")]
+    fn square(x: f32) -> f32 {
+        x * x
+    }
+
+    #[name = "scaled"]
+    #[derivative("factor", "x")]
+    fn internal_scaled(x: f32, factor: f32) -> f32 {
+        x * factor
+    }
+
+    #[derivative("y")]
+    fn broken(x: f32) -> f32 {
+        x
+    }
+}
+
+#[test]
+fn custom_derivatives() {
+    let expr = parser::expression("square(scaled(x, 3))").expect("Parsing failed");
+    let derivative =
+        differentiate::<f32, CustomFunctions>(&expr, "x").expect("Differentiation failed");
+    assert_eq!(derivative.to_string(), "2 * scaled(x, 3) * 3");
+
+    let expr = parser::expression("broken(x)").expect("Parsing failed");
+    assert!(matches!(
+        differentiate::<f32, CustomFunctions>(&expr, "x"),
+        Err(DifferentiationError::InvalidDerivative { .. })
+    ));
+}
+
+#[test]
+fn readme_example() {
+    assert_eq!(
+        printed("y = x ^ 2; sin(y)"),
+        "dy_dx = 2 * x; y = x * x; cos(y) * dy_dx"
+    );
+}
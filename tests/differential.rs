@@ -0,0 +1,503 @@
+//! Differential tests that evaluate randomly generated expressions with the JIT and with reference evaluators.
+//!
+//! Every expression is compared for a set of inputs. A failing expression is shrunk to a minimal one before it is reported.
+//! The seed can be changed with the `ANITA_DIFFERENTIAL_SEED` environment variable to explore other expressions.
+
+use anita::{
+    anita_macros::function_manager,
+    cranelift::prelude::isa::CallConv,
+    default_functions::DefaultFunctions,
+    interpreter::Interpreter,
+    jit::{
+        frontend::{parser, Expr, Span},
+        types::AnitaType,
+        JIT,
+    },
+    FunctionManager,
+};
+use evalexpr::{ContextWithMutableVariables, DefaultNumericTypes, HashMapContext, Value};
+
+/// Amount of generated expressions per test.
+const CASES: usize = 300;
+/// Largest depth of generated expressions.
+const DEPTH: usize = 4;
+
+const INPUTS_F32: [f32; 10] = [
+    0.0,
+    -0.0,
+    1.0,
+    -2.5,
+    0.1,
+    1e30,
+    1e-40,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    f32::NAN,
+];
+
+const INPUTS_F64: [f64; 8] = [0.0, 1.0, -2.5, 0.1, 3.75, 1e300, -1e-310, f64::NAN];
+
+const CONSTANTS: [f64; 8] = [0.0, 1.0, 2.0, 0.5, 3.25, 10.0, 0.1, 1e20];
+
+/// xorshift64*, seeded from `ANITA_DIFFERENTIAL_SEED` if it is set.
+struct Rng(u64);
+
+impl Rng {
+    fn new(default_seed: u64) -> Self {
+        let seed = std::env::var("ANITA_DIFFERENTIAL_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(default_seed);
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Names and arities of the functions of `F`.
+fn functions<F: FunctionManager>() -> Vec<(String, usize)> {
+    let mut functions: Vec<(String, usize)> = F::function_symbols()
+        .iter()
+        .filter_map(|(name, _)| {
+            let signature = F::function_signature(name, CallConv::SystemV)?;
+            Some((name.to_string(), signature.params.len()))
+        })
+        .collect();
+    functions.sort();
+    functions
+}
+
+/// Generates random expressions over the parameters `x` and `y`.
+struct Generator {
+    rng: Rng,
+    functions: Vec<(String, usize)>,
+    /// Restricts expressions to arithmetic and function calls, which evalexpr evaluates like anita.
+    arithmetic: bool,
+    locals: usize,
+}
+
+impl Generator {
+    fn new(seed: u64, functions: Vec<(String, usize)>, arithmetic: bool) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            functions,
+            arithmetic,
+            locals: 0,
+        }
+    }
+
+    fn expression(&mut self, depth: usize, scope: &[String]) -> Expr {
+        if depth == 0 || self.rng.below(5) == 0 {
+            return self.leaf(scope);
+        }
+        let depth = depth - 1;
+        let choices = match self.arithmetic {
+            true => 8,
+            false => 15,
+        };
+        let binary = |generator: &mut Self| {
+            let lhs = Box::new(generator.expression(depth, scope));
+            let rhs = Box::new(generator.expression(depth, scope));
+            (lhs, rhs)
+        };
+        match self.rng.below(choices) {
+            0 => {
+                let (lhs, rhs) = binary(self);
+                Expr::Add { lhs, rhs }
+            }
+            1 => {
+                let (lhs, rhs) = binary(self);
+                Expr::Sub { lhs, rhs }
+            }
+            2 => {
+                let (lhs, rhs) = binary(self);
+                Expr::Mul { lhs, rhs }
+            }
+            3 => {
+                let (lhs, rhs) = binary(self);
+                Expr::Div { lhs, rhs }
+            }
+            4 => {
+                let (lhs, rhs) = binary(self);
+                Expr::Exp { lhs, rhs }
+            }
+            5 => Expr::Neg {
+                value: Box::new(self.expression(depth, scope)),
+            },
+            6 | 7 => self.call(depth, scope),
+            8 => {
+                let (lhs, rhs) = binary(self);
+                Expr::Mod { lhs, rhs }
+            }
+            9 => {
+                let (lhs, rhs) = binary(self);
+                match self.rng.below(6) {
+                    0 => Expr::Eq { lhs, rhs },
+                    1 => Expr::Neq { lhs, rhs },
+                    2 => Expr::Gt { lhs, rhs },
+                    3 => Expr::Lt { lhs, rhs },
+                    4 => Expr::Geq { lhs, rhs },
+                    _ => Expr::Leq { lhs, rhs },
+                }
+            }
+            10 => {
+                let (lhs, rhs) = binary(self);
+                match self.rng.below(2) {
+                    0 => Expr::And { lhs, rhs },
+                    _ => Expr::Or { lhs, rhs },
+                }
+            }
+            11 => Expr::Not {
+                value: Box::new(self.expression(depth, scope)),
+            },
+            12 | 13 => Expr::If {
+                condition: Box::new(self.expression(depth, scope)),
+                then: Box::new(self.expression(depth, scope)),
+                otherwise: Box::new(self.expression(depth, scope)),
+            },
+            _ => {
+                // The local is only read after it is assigned.
+                let identifier = format!("t{}", self.locals);
+                self.locals += 1;
+                let value = Box::new(self.expression(depth, scope));
+                let mut inner = scope.to_vec();
+                inner.push(identifier.clone());
+                Expr::Chain {
+                    side: Box::new(Expr::Assign {
+                        identifier,
+                        value,
+                        span: Span::default(),
+                    }),
+                    ret: Box::new(self.expression(depth, &inner)),
+                }
+            }
+        }
+    }
+
+    fn leaf(&mut self, scope: &[String]) -> Expr {
+        match self.rng.below(3) {
+            0 => Expr::Const {
+                value: *self.rng.pick(&CONSTANTS),
+            },
+            _ => Expr::VariableRead {
+                identifier: self.rng.pick(scope).clone(),
+                span: Span::default(),
+            },
+        }
+    }
+
+    fn call(&mut self, depth: usize, scope: &[String]) -> Expr {
+        let (identifier, arity) = self.rng.pick(&self.functions).clone();
+        Expr::Call {
+            identifier,
+            args: (0..arity).map(|_| self.expression(depth, scope)).collect(),
+            span: Span::default(),
+        }
+    }
+}
+
+/// Numbers whose distance can be measured in units in the last place.
+trait Float: Copy + std::fmt::Debug {
+    fn is_nan(self) -> bool;
+    /// Maps the number to an integer so that neighbouring numbers differ by one and `-0` equals `0`.
+    fn ordered(self) -> i64;
+}
+
+impl Float for f32 {
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+
+    fn ordered(self) -> i64 {
+        let bits = i64::from(self.to_bits() as i32);
+        if bits < 0 {
+            i64::from(i32::MIN) - bits
+        } else {
+            bits
+        }
+    }
+}
+
+impl Float for f64 {
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+
+    fn ordered(self) -> i64 {
+        let bits = self.to_bits() as i64;
+        if bits < 0 {
+            i64::MIN - bits
+        } else {
+            bits
+        }
+    }
+}
+
+/// Whether `a` and `b` are at most `ulps` apart. NaNs are equal to each other, infinities only to themselves.
+fn close<T: Float>(a: T, b: T, ulps: u64) -> bool {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => true,
+        (false, false) => a.ordered().abs_diff(b.ordered()) <= ulps,
+        _ => false,
+    }
+}
+
+/// Candidates for a smaller failing expression: subexpressions, simpler constants and `expr` with one child shrunk.
+fn shrink_candidates(expr: &Expr) -> Vec<Expr> {
+    let mut candidates: Vec<Expr> = expr.children().into_iter().cloned().collect();
+    match expr {
+        Expr::Const { value } => candidates.extend(
+            [0.0, 1.0, value.trunc()]
+                .into_iter()
+                .filter(|simpler| simpler != value)
+                .map(|value| Expr::Const { value }),
+        ),
+        Expr::VariableRead { .. } => {}
+        _ => candidates.push(Expr::Const { value: 0.0 }),
+    }
+    for (index, child) in expr.children().into_iter().enumerate() {
+        for shrunk in shrink_candidates(child) {
+            let mut position = 0;
+            candidates.push(expr.clone().map_children(|child| {
+                position += 1;
+                match position - 1 == index {
+                    true => shrunk.clone(),
+                    false => child,
+                }
+            }));
+        }
+    }
+    candidates
+}
+
+/// Repeatedly replaces `expr` by the first of its [`shrink_candidates`] that still fails.
+fn shrink(expr: Expr, fails: impl Fn(&Expr) -> bool) -> Expr {
+    let mut current = expr;
+    'shrinking: loop {
+        for candidate in shrink_candidates(&current) {
+            if fails(&candidate) {
+                current = candidate;
+                continue 'shrinking;
+            }
+        }
+        return current;
+    }
+}
+
+/// Evaluates a prepared expression for `x` and `y` with the reference and with the tested evaluator.
+type Evaluate<'a, T> = Box<dyn Fn(T, T) -> Option<(T, T)> + 'a>;
+
+/// Returns the first input pair for which the results of `evaluate` are more than `ulps` apart.
+///
+/// Expressions that one of the evaluators rejects do not fail, so shrinking never removes the definition of a variable.
+fn mismatch<'a, T: Float>(
+    expression: &Expr,
+    inputs: &[T],
+    ulps: u64,
+    prepare: &impl Fn(&Expr) -> Option<Evaluate<'a, T>>,
+) -> Option<(T, T, T, T)> {
+    let evaluate = prepare(expression)?;
+    for &x in inputs {
+        for &y in inputs {
+            let (expected, result) = evaluate(x, y)?;
+            if !close(expected, result, ulps) {
+                return Some((x, y, expected, result));
+            }
+        }
+    }
+    None
+}
+
+/// Checks [`CASES`] generated expressions and panics with the shrunk expression on the first mismatch.
+fn check<'a, T: Float>(
+    generator: &mut Generator,
+    inputs: &[T],
+    ulps: u64,
+    prepare: impl Fn(&Expr) -> Option<Evaluate<'a, T>>,
+) {
+    let parameters = ["x".to_owned(), "y".to_owned()];
+    for _ in 0..CASES {
+        let expression = generator.expression(DEPTH, &parameters);
+        assert!(
+            prepare(&expression).is_some(),
+            "generated expression `{expression}` is rejected"
+        );
+        if mismatch(&expression, inputs, ulps, &prepare).is_none() {
+            continue;
+        }
+        let shrunk = shrink(expression.clone(), |candidate| {
+            mismatch(candidate, inputs, ulps, &prepare).is_some()
+        });
+        let (x, y, expected, result) =
+            mismatch(&shrunk, inputs, ulps, &prepare).expect("shrunk expressions still fail");
+        panic!(
+            "`{shrunk}` (shrunk from `{expression}`) evaluates to {result:?} instead of {expected:?} for x = {x:?}, y = {y:?}"
+        );
+    }
+}
+
+#[test]
+fn display_roundtrip() {
+    let mut generator = Generator::new(1, functions::<DefaultFunctions>(), false);
+    let parameters = ["x".to_owned(), "y".to_owned()];
+    for _ in 0..CASES {
+        let expression = generator.expression(DEPTH, &parameters);
+        let source = expression.to_string();
+        let parsed = parser::expression(&source).expect("Parsing failed");
+        assert!(parsed.same_structure(&expression), "{source}");
+    }
+}
+
+#[test]
+fn interpreter_matches_jit() {
+    for (seed, optimize) in [(2, true), (3, false)] {
+        let mut generator = Generator::new(seed, functions::<DefaultFunctions>(), false);
+        check(&mut generator, &INPUTS_F32, 0, |expression| {
+            let source = expression.to_string();
+            let mut jit = JIT::<f32, DefaultFunctions>::default();
+            jit.set_optimize(optimize);
+            let compiled = jit.compile_dynamic(&source, &["x", "y"]).ok()?;
+            let interpreted = Interpreter::<f32, DefaultFunctions>::default()
+                .set_optimize(optimize)
+                .compile_dynamic(&source, &["x", "y"])
+                .ok()?;
+            Some(Box::new(move |x, y| {
+                let expected = interpreted.call(&[x, y]).ok()?;
+                Some((expected, compiled.call(&[x, y]).ok()?))
+            }))
+        });
+    }
+}
+
+/// The functions of evalexpr that anita has too, in double precision.
+struct MathFunctions;
+
+#[function_manager]
+impl MathFunctions {
+    fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+
+    fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+
+    fn atan(x: f64) -> f64 {
+        x.atan()
+    }
+
+    fn sqrt(x: f64) -> f64 {
+        x.sqrt()
+    }
+
+    fn exp(x: f64) -> f64 {
+        x.exp()
+    }
+
+    fn ln(x: f64) -> f64 {
+        x.ln()
+    }
+
+    fn floor(x: f64) -> f64 {
+        x.floor()
+    }
+
+    fn ceil(x: f64) -> f64 {
+        x.ceil()
+    }
+
+    fn hypot(x: f64, y: f64) -> f64 {
+        x.hypot(y)
+    }
+
+    fn atan2(y: f64, x: f64) -> f64 {
+        y.atan2(x)
+    }
+}
+
+/// Formats `expr` in evalexpr syntax with every operation in parentheses and every number as a float.
+fn evalexpr_source(expr: &Expr) -> String {
+    let binary = |operator: &str, lhs: &Expr, rhs: &Expr| {
+        format!(
+            "({} {operator} {})",
+            evalexpr_source(lhs),
+            evalexpr_source(rhs)
+        )
+    };
+    match expr {
+        Expr::Const { value } => format!("{value:?}"),
+        Expr::VariableRead { identifier, .. } => identifier.clone(),
+        Expr::Add { lhs, rhs } => binary("+", lhs, rhs),
+        Expr::Sub { lhs, rhs } => binary("-", lhs, rhs),
+        Expr::Mul { lhs, rhs } => binary("*", lhs, rhs),
+        Expr::Div { lhs, rhs } => binary("/", lhs, rhs),
+        Expr::Exp { lhs, rhs } => binary("^", lhs, rhs),
+        Expr::Neg { value } => format!("(-{})", evalexpr_source(value)),
+        Expr::Call {
+            identifier, args, ..
+        } => {
+            let namespace = match identifier.as_str() {
+                "floor" | "ceil" => "",
+                _ => "math::",
+            };
+            let args: Vec<String> = args.iter().map(evalexpr_source).collect();
+            format!("{namespace}{identifier}({})", args.join(", "))
+        }
+        _ => unreachable!("only arithmetic is generated for evalexpr"),
+    }
+}
+
+#[test]
+fn jit_matches_evalexpr() {
+    let mut generator = Generator::new(4, functions::<MathFunctions>(), true);
+    check(&mut generator, &INPUTS_F64, 0, |expression| {
+        let compiled = JIT::<f64, MathFunctions>::default()
+            .compile_dynamic(expression.to_string(), &["x", "y"])
+            .ok()?;
+        let source = evalexpr_source(expression);
+        let reference = evalexpr::build_operator_tree::<DefaultNumericTypes>(&source).ok()?;
+        Some(Box::new(move |x, y| {
+            let mut context = HashMapContext::new();
+            context.set_value("x".to_owned(), Value::Float(x)).ok()?;
+            context.set_value("y".to_owned(), Value::Float(y)).ok()?;
+            let expected = match reference.eval_with_context(&context) {
+                Ok(Value::Float(value)) => value,
+                result => panic!("evalexpr evaluates `{source}` to {result:?}"),
+            };
+            Some((expected, compiled.call(&[x, y]).ok()?))
+        }))
+    });
+}
+
+#[test]
+fn shrinking() {
+    // Any expression containing a division is considered failing.
+    fn divides(expr: &Expr) -> bool {
+        matches!(expr, Expr::Div { .. }) || expr.children().into_iter().any(divides)
+    }
+    let expression = parser::expression("t0 = sin(x) * 2; max(t0 + 3, (y + 1) / (x - 2))")
+        .expect("Parsing failed");
+    let shrunk = shrink(expression, divides);
+    assert_eq!(shrunk.to_string(), "y / x");
+
+    assert!(close(1.0_f32, 1.0 + f32::EPSILON, 1));
+    assert!(!close(1.0_f32, 1.0 + 2.0 * f32::EPSILON, 1));
+    assert!(close(0.0_f64, -0.0, 0));
+    assert!(close(f64::NAN, -f64::NAN, 0));
+    assert!(!close(f64::NAN, f64::INFINITY, u64::MAX));
+    assert!(close(f32::MAX, f32::INFINITY, 1));
+    assert!(!close(f32::MIN_POSITIVE, -f32::MIN_POSITIVE, 1 << 20));
+}
@@ -0,0 +1,134 @@
+use anita::{
+    anita_macros::function_manager,
+    compile_batch_expression, compile_expression,
+    default_functions::DefaultFunctions,
+    jit::{
+        frontend::{derivative::differentiate, parser},
+        types::{AnitaType, Dual},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+#[test]
+fn value_and_derivative() {
+    let function =
+        compile_expression!("x ^ 3 + 2 * x", (x) -> (f64, f64)).expect("Compilation failed");
+    assert_eq!(function(2.0), (12.0, 14.0));
+    assert_eq!(function(-1.0), (-3.0, 5.0));
+
+    let function =
+        compile_expression!("y = 1 / x; y * y", (x) -> (f64, f64)).expect("Compilation failed");
+    assert_eq!(function(2.0), (0.25, -0.25));
+}
+
+#[test]
+fn functions_use_their_derivatives() {
+    let function = compile_expression!("sin(x) * exp(x)", (x) -> (f32, f32), DefaultFunctions)
+        .expect("Compilation failed");
+    for x in [-1.5f32, 0.0, 0.7, 2.0] {
+        let (value, derivative) = function(x);
+        assert!((value - x.sin() * x.exp()).abs() < 1e-5);
+        assert!((derivative - (x.cos() + x.sin()) * x.exp()).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn matches_symbolic_differentiation() {
+    for expression in [
+        "x * x * x - 2 * x",
+        "1 / x + x % 0.7",
+        "hypot(x, 2) * atan2(x, 1.5)",
+        "y = sqrt(x); ln(y) + y ^ x",
+        "x > 1 ? cos(x) : x ^ 2",
+        "max(x, 1.2) * tanh(x)",
+    ] {
+        let function = JIT::<Dual<f32>, DefaultFunctions>::default()
+            .compile_dynamic(expression, &["x"])
+            .expect("Compilation failed");
+        let expr = parser::expression(expression).expect("Parsing failed");
+        let derivative = differentiate::<f32, DefaultFunctions>(&expr, "x")
+            .expect("Differentiation failed")
+            .to_string();
+        let symbolic = JIT::<f32, DefaultFunctions>::default()
+            .compile_dynamic(&derivative, &["x"])
+            .expect("Compilation failed");
+        for x in [0.45f32, 1.3, 1.9] {
+            let result = function.call(&[Dual::variable(x)]).unwrap();
+            let expected = symbolic.call(&[x]).unwrap();
+            assert!(
+                (result.derivative - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+                "d/dx {expression} is {} at {x}, expected {expected}",
+                result.derivative
+            );
+        }
+    }
+}
+
+#[test]
+fn partial_derivatives() {
+    let function = JIT::<Dual<f64>, DefaultFunctions>::default()
+        .compile_dynamic("x * y + y ^ 2", &["x", "y"])
+        .expect("Compilation failed");
+    let (x, y) = (3.0, 5.0);
+    let d_dx = function
+        .call(&[Dual::variable(x), Dual::constant(y)])
+        .unwrap();
+    let d_dy = function
+        .call(&[Dual::constant(x), Dual::variable(y)])
+        .unwrap();
+    assert_eq!(d_dx, Dual::new(40.0, 5.0));
+    assert_eq!(d_dy, Dual::new(40.0, 13.0));
+}
+
+#[test]
+fn constant_exponents_of_negative_bases() {
+    let mut jit = JIT::<Dual<f64>, DefaultFunctions>::default();
+    jit.set_optimize(false);
+    let function = jit
+        .compile_dynamic("x ^ y", &["x", "y"])
+        .expect("Compilation failed");
+    let result = function
+        .call(&[Dual::variable(-2.0), Dual::constant(3.0)])
+        .unwrap();
+    assert_eq!(result, Dual::new(-8.0, 12.0));
+}
+
+#[test]
+fn batch_evaluation() {
+    let function =
+        compile_batch_expression!("x * x", (x) -> Dual<f64>).expect("Compilation failed");
+    let x: Vec<Dual<f64>> = (0..5).map(|x| Dual::variable(x as f64)).collect();
+    let mut output = vec![Dual::default(); x.len()];
+    function.call(&[&x], &mut output).unwrap();
+    let expected: Vec<Dual<f64>> = (0..5)
+        .map(|x| Dual::new((x * x) as f64, (2 * x) as f64))
+        .collect();
+    assert_eq!(output, expected);
+}
+
+struct UnderivableFunctions;
+
+#[function_manager]
+impl UnderivableFunctions {
+    fn square(x: f32) -> f32 {
+        x * x
+    }
+}
+
+#[test]
+fn missing_derivative() {
+    let Err(error) = compile_expression!("square(x) + 1", (x) -> (f32, f32), UnderivableFunctions)
+    else {
+        panic!("Compilation succeeded")
+    };
+    let JITError::TranslatorError(TranslatorError::MissingDerivative { name, span }) = &error
+    else {
+        panic!("unexpected error {error:?}")
+    };
+    assert_eq!(name, "square");
+    assert_eq!((span.start, span.end), (0, 9));
+
+    let function = compile_expression!("square(x) + 1", (x) -> f32, UnderivableFunctions)
+        .expect("Compilation failed");
+    assert_eq!(function(3.0), 10.0);
+}
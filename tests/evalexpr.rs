@@ -0,0 +1,226 @@
+use anita::{
+    anita_macros::function_manager,
+    default_functions::DefaultFunctions,
+    interpreter::Interpreter,
+    jit::{
+        frontend::{parser, Expr, Syntax},
+        session::Session,
+        types::AnitaType,
+        JIT,
+    },
+};
+use evalexpr::{
+    build_operator_tree, ContextWithMutableVariables, DefaultNumericTypes, HashMapContext, Value,
+};
+
+/// The `f64` functions of evalexpr that are used by the tests.
+struct MathFunctions;
+
+#[function_manager]
+impl MathFunctions {
+    fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+
+    fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+
+    fn sqrt(x: f64) -> f64 {
+        x.sqrt()
+    }
+
+    fn ln(x: f64) -> f64 {
+        x.ln()
+    }
+
+    fn exp(x: f64) -> f64 {
+        x.exp()
+    }
+
+    fn abs(x: f64) -> f64 {
+        x.abs()
+    }
+
+    fn floor(x: f64) -> f64 {
+        x.floor()
+    }
+
+    fn ceil(x: f64) -> f64 {
+        x.ceil()
+    }
+
+    fn round(x: f64) -> f64 {
+        x.round()
+    }
+
+    fn hypot(x: f64, y: f64) -> f64 {
+        x.hypot(y)
+    }
+
+    fn atan2(y: f64, x: f64) -> f64 {
+        y.atan2(x)
+    }
+}
+
+fn jit() -> JIT<f64, MathFunctions> {
+    let mut jit = JIT::default();
+    jit.set_syntax(Syntax::Evalexpr);
+    jit
+}
+
+/// Evaluates `expression` with evalexpr, with booleans as `1` and `0`.
+fn evalexpr(expression: &str, x: f64, y: f64) -> f64 {
+    let tree = build_operator_tree::<DefaultNumericTypes>(expression).expect("Parsing failed");
+    let mut context = HashMapContext::<DefaultNumericTypes>::new();
+    context.set_value("x".into(), Value::Float(x)).unwrap();
+    context.set_value("y".into(), Value::Float(y)).unwrap();
+    match tree
+        .eval_with_context_mut(&mut context)
+        .expect("Evaluation failed")
+    {
+        Value::Float(value) => value,
+        Value::Int(value) => value as f64,
+        Value::Boolean(value) => f64::from(u8::from(value)),
+        value => panic!("{expression} evaluates to {value:?}"),
+    }
+}
+
+#[test]
+fn matches_evalexpr() {
+    for expression in [
+        "-x ^ 2",
+        "x ^ -y",
+        "x * -y + 1",
+        "--x - -y",
+        "2 ^ 3 ^ 2",
+        "x - y - 1",
+        "x < y == (y > x)",
+        "x > 0 || y > 0 && x > y",
+        "!(x > 0) == false",
+        "a = x; a += y; a *= 2; a -= 1; a /= 4; a",
+        "a = x; a ^= 2; a %= 3; a",
+        "b = x > y; b ||= y > 2; b &&= true; b",
+        "math::sin(x) + math::cos(y) * math::sqrt(4)",
+        "math::hypot(x, y) + math::atan2(y, x) + floor(x) + ceil(y) + round(x)",
+        "math::ln(2) + math::exp(y) - math::abs(-x)",
+        "if(x > y, x, y)",
+        "x /* inline */ + y // trailing",
+        "x +\n  y;\nx * y",
+        ".5 * x + 5. * y + 1e-2",
+    ] {
+        let function = jit()
+            .compile_dynamic(expression, &["x", "y"])
+            .unwrap_or_else(|error| panic!("{expression}: {error}"));
+        for (x, y) in [(1.5, 2.0), (-3.0, 0.5), (4.0, -2.0)] {
+            let expected = evalexpr(expression, x, y);
+            let result = function.call(&[x, y]).unwrap();
+            assert!(
+                (result - expected).abs() <= 1e-12 * expected.abs()
+                    || (result.is_nan() && expected.is_nan()),
+                "{expression} for x = {x}, y = {y}: {result} != {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn integer_division() {
+    // evalexpr divides integer literals as integers, anita in the compiled type.
+    assert_eq!(evalexpr("7 / 2", 0.0, 0.0), 3.0);
+    let function = jit()
+        .compile_dynamic("7 / 2", &[])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[]), Ok(3.5));
+    let function = jit()
+        .compile_dynamic("x / 2", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[7.0]), Ok(3.5));
+}
+
+#[test]
+fn parsing() {
+    let parsed = parser::evalexpr("math::sin(x)").expect("Parsing failed");
+    assert!(parsed.same_structure(&parser::expression("sin(x)").expect("Parsing failed")));
+    for (evalexpr, anita) in [
+        ("-x ^ 2", "-(x ^ 2)"),
+        ("x ^ -2", "x ^ -(2)"),
+        ("a || b && c", "a || (b && c)"),
+        ("a += 1", "a = a + 1"),
+        ("a ^= b = 2", "a = a ^ (b = 2)"),
+        ("true && !false", "1 && !0"),
+        ("a == b < c", "(a == b) < c"),
+        ("truex + if(a, b, c)", "truex + (a ? b : c)"),
+    ] {
+        let parsed = parser::evalexpr(evalexpr).expect("Parsing failed");
+        let expected = parser::expression(anita).expect("Parsing failed");
+        assert!(parsed.same_structure(&expected), "{evalexpr}");
+    }
+    // The anita syntax is unchanged.
+    assert!(parser::expression("math::sin(x)").is_err());
+    assert!(parser::expression("true").is_ok_and(|expr| matches!(expr, Expr::VariableRead { .. })));
+    assert!(parser::evalexpr("x as f32").is_err());
+    assert!(parser::evalexpr("x ? 1 : 2").is_err());
+}
+
+#[test]
+fn unsupported_features() {
+    for (expression, message, offset) in [
+        (
+            "x + \"text\"",
+            "invalid syntax, expected a number (strings are not supported)",
+            5,
+        ),
+        (
+            "(x, y)",
+            "invalid syntax, expected `)` (tuples are not supported)",
+            3,
+        ),
+        (
+            "()",
+            "invalid syntax, expected an expression (empty tuples are not supported)",
+            2,
+        ),
+        (
+            "str::trim(x)",
+            "invalid syntax, expected a numeric function (string functions are not supported)",
+            12,
+        ),
+    ] {
+        let Err(error) = jit().compile_dynamic(expression, &["x", "y"]) else {
+            panic!("{expression} compiled")
+        };
+        assert_eq!(error.to_string(), message);
+        assert_eq!(
+            error.span().map(|span| span.start),
+            Some(offset),
+            "{expression}"
+        );
+    }
+
+    let Err(error) = jit().compile_dynamic("math::log(x, 2)", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "unknown function `log`");
+}
+
+#[test]
+fn interpreter_and_session() {
+    let mut interpreter = Interpreter::<f64, DefaultFunctions>::default();
+    let function = interpreter
+        .set_syntax(Syntax::Evalexpr)
+        .compile_dynamic("a = x; a += 1; -a ^ 2", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[1.0]), Ok(-4.0));
+
+    let mut session = Session::<f32, DefaultFunctions>::default();
+    session
+        .set_syntax(Syntax::Evalexpr)
+        .add("sine", "math::sin(x)", &["x"])
+        .expect("Compilation failed");
+    session.set_syntax(Syntax::Anita);
+    assert!(session.add("invalid", "math::sin(x)", &["x"]).is_err());
+    let module = session.finish();
+    let sine = module.get("sine").expect("Function not found");
+    assert_eq!(sine.call(&[0.0]), Ok(0.0));
+}
@@ -3,7 +3,10 @@ use core::f32;
 use anita::anita_macros::function_manager;
 use anita::default_functions::DefaultFunctions;
 use anita::jit::types::AnitaType;
-use anita::{compile_expression, jit::JITError};
+use anita::{
+    compile_batch_expression, compile_expression,
+    jit::{JITError, TranslatorError},
+};
 
 #[test]
 fn owned_input() {
@@ -18,7 +21,7 @@ fn empty_input() {
     let expr = "";
     let result = compile_expression!(expr, (x) -> f32);
     match result {
-        Err(JITError::ParseError(_)) => {},
+        Err(JITError::ParseError(_)) => {}
         Ok(_) => panic!("Test failed by succeeding"),
         Err(e) => panic!("Expected JITError::RootEvaluatesInNoValue but got: {:?}", e),
     }
@@ -31,10 +34,10 @@ fn use_of_uninitialized_variables() {
     match result {
         Err(JITError::UseOfUninitializedVariables(vars)) => {
             vars.iter()
-                .for_each(|var| assert!(expected_unitialized.contains(&var.as_ref())));
+                .for_each(|(var, _)| assert!(expected_unitialized.contains(&var.as_ref())));
             expected_unitialized
                 .iter()
-                .for_each(|var| assert!(vars.contains(&(*var).to_owned())));
+                .for_each(|var| assert!(vars.iter().any(|(name, _)| name == var)));
         }
         Ok(_) => panic!("Test failed by succeeding"),
         Err(e) => panic!("Expected JITError::RootEvaluatesInNoValue but got: {:?}", e),
@@ -93,3 +96,67 @@ fn custom_function_manager() {
     let result = func(1.0);
     assert_eq!(result, f32::tanh(1.0));
 }
+
+#[test]
+fn batch_function_calls() {
+    let expression = "tanh(a * x^3) + sin(x)";
+    let function = compile_batch_expression!(expression, (x, a) -> f32, DefaultFunctions)
+        .expect("Compilation failed");
+    let x: Vec<f32> = (0..10).map(|i| i as f32 * 0.3 - 1.0).collect();
+    let a = [0.7; 10];
+    let mut output = [0.0; 10];
+    function.call(&[&x, &a], &mut output).expect("Call failed");
+    for (i, result) in output.into_iter().enumerate() {
+        assert_eq!(
+            result,
+            // The optimizer expands `x^3` into multiplications.
+            f32::tanh(0.7 * (x[i] * x[i] * x[i])) + f32::sin(x[i])
+        );
+    }
+}
+
+#[test]
+fn arity_mismatch() {
+    for (expression, expected, found) in [("sin(x, x)", 1, 2), ("atan2(x)", 2, 1), ("max()", 2, 0)]
+    {
+        let result = compile_expression!(expression, (x) -> f32, DefaultFunctions);
+        match result {
+            Err(JITError::TranslatorError(TranslatorError::ArityMismatch {
+                expected: e,
+                found: f,
+                span,
+                ..
+            })) => {
+                assert_eq!((e, f), (expected, found), "{expression}");
+                assert_eq!(&expression[span.start..span.end], expression);
+            }
+            Ok(_) => panic!("Test failed by succeeding"),
+            Err(e) => panic!("Expected TranslatorError::ArityMismatch but got: {:?}", e),
+        }
+    }
+    let Err(error) = compile_expression!("1 + atan2(x)", (x) -> f32, DefaultFunctions) else {
+        panic!("Test failed by succeeding");
+    };
+    assert_eq!(
+        error.to_string(),
+        "function `atan2` takes 2 arguments but 1 was given"
+    );
+}
+
+#[test]
+fn signature_mismatch() {
+    let result = compile_expression!("x + sin(x)", (x) -> f64, DefaultFunctions);
+    match result {
+        Err(JITError::TranslatorError(TranslatorError::SignatureMismatch {
+            name, span, ..
+        })) => {
+            assert_eq!(name, "sin");
+            assert_eq!((span.start, span.end), (4, 10));
+        }
+        Ok(_) => panic!("Test failed by succeeding"),
+        Err(e) => panic!(
+            "Expected TranslatorError::SignatureMismatch but got: {:?}",
+            e
+        ),
+    }
+}
@@ -0,0 +1,242 @@
+use anita::{
+    anita_macros::function_manager,
+    default_functions::DefaultFunctions,
+    interpret_expression,
+    interpreter::{Backend, Interpreter},
+    jit::{
+        compiled_function::{CallError, CompiledFunction},
+        types::{AnitaType, ValueType},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+const VALUES: [f32; 9] = [
+    0.0,
+    -0.0,
+    1.0,
+    -2.5,
+    7.25,
+    1e-40,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    f32::NAN,
+];
+
+fn same(a: f32, b: f32) -> bool {
+    a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan())
+}
+
+/// Asserts that the interpreter and the JIT agree on `expression` for all pairs of [`VALUES`].
+fn assert_matches_jit(expression: &str) {
+    let compiled = JIT::<f32, DefaultFunctions>::default()
+        .compile_dynamic(expression, &["x", "y"])
+        .expect("Compilation failed");
+    let interpreted = Interpreter::<f32, DefaultFunctions>::default()
+        .compile_dynamic(expression, &["x", "y"])
+        .expect("Compilation failed");
+    for x in VALUES {
+        for y in VALUES {
+            let expected = compiled.call(&[x, y]).unwrap();
+            let result = interpreted.call(&[x, y]).unwrap();
+            assert!(
+                same(result, expected),
+                "{expression} for x = {x}, y = {y}: {result} != {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn matches_jit() {
+    for expression in [
+        "x + y * 2 - x / y",
+        "x % y",
+        "-x % 3",
+        "x ^ y",
+        "x ^ 3 + y ^ -2",
+        "x == y",
+        "x != y",
+        "x < y || x >= 2",
+        "x > y && y <= 1",
+        "!x",
+        "!(x == y) + (x ? 1 : 2)",
+        "x > y ? x : y",
+        "a = x; b = a * y; a + b",
+        "if x > 0 { z = 1 } else { z = 2 }; z * y",
+        "(y = x) + y",
+        "x ? y < 1 : 0",
+        "0.1 + 0.2 + x",
+        "x as f32",
+        "sq(a) = a * a; f(a) = (a = a * 2; a + 1); sq(f(x)) + f(y)",
+    ] {
+        assert_matches_jit(expression);
+    }
+}
+
+#[test]
+fn functions() {
+    for expression in [
+        "sin(x) + cos(y)",
+        "max(x, y) - min(x, y)",
+        "sqrt(abs(x)) * exp(-y)",
+        "atan2(x, y) + floor(x) + is_nan(y)",
+        "ln(x) + log2(y) + hypot(x, y)",
+        "pow(x, y) - mod(x, y) + tanh(x)",
+    ] {
+        assert_matches_jit(expression);
+    }
+}
+
+#[test]
+fn errors() {
+    let interpreter = Interpreter::<f32, DefaultFunctions>::default();
+    let Err(error) = interpreter.compile_dynamic("x + y", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "use of uninitialized variable `y`");
+
+    let Err(error) = interpreter.compile_dynamic("x ? unknown(x) : 0", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "unknown function `unknown`");
+
+    let Err(error) = interpreter.compile_dynamic("sin(x, x)", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(
+        error.to_string(),
+        "function `sin` takes 1 argument but 2 were given"
+    );
+
+    let Err(error) = interpreter.compile_dynamic("x as i32", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert!(matches!(
+        error,
+        JITError::TranslatorError(TranslatorError::UnsupportedCast {
+            to: ValueType::I32,
+            ..
+        })
+    ));
+
+    let Err(error) = interpreter.compile_dynamic("2i", &[]) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(
+        error.to_string(),
+        "imaginary numbers require a complex type"
+    );
+
+    let Err(error) = interpreter.compile_dynamic("x.y", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((2, 3))
+    );
+
+    let Err(error) =
+        Interpreter::<f64, DefaultFunctions>::default().compile_dynamic("sin(x)", &["x"])
+    else {
+        panic!("Compilation succeeded")
+    };
+    assert!(matches!(
+        error,
+        JITError::TranslatorError(TranslatorError::SignatureMismatch { .. })
+    ));
+}
+
+struct ManyParameters;
+
+#[function_manager]
+impl ManyParameters {
+    fn sum6(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> f64 {
+        a + b + c + d + e + f
+    }
+
+    fn sum7(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64, g: f64) -> f64 {
+        a + b + c + d + e + f + g
+    }
+}
+
+#[test]
+fn arity() {
+    let interpreter = Interpreter::<f64, ManyParameters>::default();
+    let function = interpreter
+        .compile_dynamic("sum6(x, 2, 3, 4, 5, x)", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[1.0]), Ok(16.0));
+
+    let Err(error) = interpreter.compile_dynamic("sum7(x, x, x, x, x, x, x)", &["x"]) else {
+        panic!("Compilation succeeded")
+    };
+    assert!(matches!(
+        error,
+        JITError::TranslatorError(TranslatorError::UnsupportedArity { arity: 7, .. })
+    ));
+}
+
+#[test]
+fn dynamic_functions() {
+    let function = Interpreter::<f64, DefaultFunctions>::default()
+        .compile_dynamic("a * b - c", &["a", "b", "c"])
+        .expect("Compilation failed");
+    assert_eq!(function.parameters(), ["a", "b", "c"]);
+    assert_eq!(function.call(&[2.0, 3.0, 1.0]), Ok(5.0));
+    assert_eq!(
+        function.call(&[1.0]),
+        Err(CallError::ArgumentCountMismatch {
+            expected: 3,
+            found: 1
+        })
+    );
+    let cloned = function.clone();
+    drop(function);
+    let arguments = [4.0, 0.5, 1.0];
+    assert_eq!(unsafe { cloned.call_unchecked(arguments.as_ptr()) }, 1.0);
+
+    // A repeated parameter takes the last argument, like in compiled code.
+    let function = Interpreter::<f32, DefaultFunctions>::default()
+        .compile_dynamic("x * 2", &["x", "x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[1.0, 3.0]), Ok(6.0));
+
+    let mut interpreter = Interpreter::<f32, DefaultFunctions>::default();
+    let function = interpreter
+        .set_optimize(false)
+        .compile_dynamic("x - x", &["x"])
+        .expect("Compilation failed");
+    assert!(function.call(&[f32::INFINITY]).unwrap().is_nan());
+}
+
+#[test]
+fn backends() {
+    assert!(JIT::<f32, DefaultFunctions>::try_default().is_ok());
+    assert_eq!(Backend::detect(), Backend::Jit);
+
+    for backend in [Backend::Jit, Backend::Interpreter] {
+        let function = backend
+            .compile_dynamic::<f32, DefaultFunctions, _>("max(x, 1) ^ 2", &["x"])
+            .expect("Compilation failed");
+        assert_eq!(function.call(&[3.0]), Ok(9.0));
+    }
+
+    let function = Interpreter::<f32, DefaultFunctions>::default()
+        .compile_dynamic("x + 1", &["x"])
+        .expect("Compilation failed");
+    let function = CompiledFunction::interpreted(move |x: f32| function.call(&[x]).unwrap());
+    assert_eq!(function(1.5), 2.5);
+    std::thread::spawn(move || assert_eq!(function(2.0), 3.0))
+        .join()
+        .unwrap();
+
+    let function = interpret_expression!("max(x, y) * 2", (x, y) -> f32, DefaultFunctions)
+        .expect("Compilation failed");
+    assert_eq!(function(1.0, 3.0), 6.0);
+    let function = interpret_expression!("x - 1", (x) -> f64).expect("Compilation failed");
+    assert_eq!(function(0.5), -0.5);
+    assert!(matches!(
+        interpret_expression!("y", (x) -> f32),
+        Err(JITError::UseOfUninitializedVariables(_))
+    ));
+}
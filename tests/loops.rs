@@ -0,0 +1,246 @@
+mod common;
+
+use anita::{
+    compile_batch_expression, compile_expression,
+    default_functions::DefaultFunctions,
+    interpreter::Interpreter,
+    jit::{
+        frontend::{parser, Expr, LoopKind},
+        types::{Complex, ComplexFunctions, Interval, IntervalFunctions, Vec2},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+use common::assert_matches_difference;
+
+fn evaluate(expression: &str, x: f64) -> f64 {
+    JIT::<f64, DefaultFunctions>::default()
+        .compile_dynamic(expression, &["x"])
+        .unwrap_or_else(|error| panic!("{expression}: {error}"))
+        .call(&[x])
+        .unwrap()
+}
+
+#[test]
+fn big_operators() {
+    assert_eq!(evaluate("sum(i, 1, 4, i)", 0.0), 10.0);
+    assert_eq!(evaluate("sum(i, 1, x, i ^ 2)", 3.0), 14.0);
+    assert_eq!(evaluate("prod(k, 1, x, k)", 5.0), 120.0);
+    assert_eq!(evaluate("sum(i, 0.5, 2, i)", 0.0), 0.5 + 1.5);
+    // Empty ranges evaluate to the neutral element.
+    assert_eq!(evaluate("sum(i, 1, 0, x)", 7.0), 0.0);
+    assert_eq!(evaluate("prod(i, 1, 0, x)", 7.0), 1.0);
+    assert_eq!(evaluate("sum(i, 1, 3, sum(j, 1, i, j))", 0.0), 10.0);
+    assert_eq!(evaluate("sum(i, 1, 3, i > 1)", 0.0), 2.0);
+}
+
+#[test]
+fn for_loops() {
+    assert_eq!(evaluate("a = 0; for i in 0..x { a += i }; a", 4.0), 6.0);
+    assert_eq!(evaluate("for i in 0..x { i * 2 }", 4.0), 6.0);
+    assert_eq!(evaluate("for i in 0..x { i * 2 }", 0.0), 0.0);
+    // The loop variable keeps its last value.
+    assert_eq!(evaluate("i = 0; for i in 1..x { 0 }; i", 4.0), 3.0);
+    assert_eq!(
+        evaluate(
+            "a = 1; b = 1; for i in 2..x { c = a + b; a = b; b = c }; b",
+            10.0
+        ),
+        55.0
+    );
+    assert_eq!(evaluate("for i in 0..3 { i < 1 ? x : -x }", 2.0), -2.0);
+}
+
+#[test]
+fn parsing() {
+    let Ok(Expr::Loop { kind, variable, .. }) = parser::expression("for n in 0..10 { n }") else {
+        panic!("Parsing failed")
+    };
+    assert_eq!((kind, variable.as_str()), (LoopKind::For, "n"));
+    for expression in [
+        "for i in 0..x + 1 { a = i; a * 2 }",
+        "sum(i, 1, n, i ^ 2) + prod(j, 1, 3, j)",
+        "-sum(i, 1.5, 3, x)",
+    ] {
+        let parsed = parser::expression(expression).expect("Parsing failed");
+        let printed = parsed.to_string();
+        assert_eq!(parser::expression(&printed), Ok(parsed), "{printed}");
+    }
+    // `sum` and `prod` are still functions with other arguments.
+    assert!(matches!(
+        parser::expression("sum(1, 2, 3, 4)"),
+        Ok(Expr::Call { .. })
+    ));
+    assert!(parser::expression("for in 0..1 { 1 }").is_err());
+}
+
+#[test]
+fn uninitialized_variables() {
+    let uninitialized = |expression: &str| match JIT::<f64, DefaultFunctions>::default()
+        .compile_dynamic(expression, &["x"])
+    {
+        Ok(_) => Vec::new(),
+        Err(JITError::UseOfUninitializedVariables(variables)) => {
+            variables.iter().map(|(name, _)| name.clone()).collect()
+        }
+        Err(error) => panic!("{expression}: {error}"),
+    };
+    assert!(uninitialized("sum(i, 0, x, i)").is_empty());
+    assert_eq!(uninitialized("sum(i, i, x, 1)"), ["i"]);
+    // The body may not run, so neither its assignments nor the loop variable count after the loop.
+    assert_eq!(uninitialized("for i in 0..x { a = i }; a"), ["a"]);
+    assert_eq!(uninitialized("for i in 0..x { a = a + i }"), ["a"]);
+    assert_eq!(uninitialized("for i in 0..x { 1 }; i"), ["i"]);
+}
+
+#[test]
+fn max_iterations() {
+    let mut jit = JIT::<f64, DefaultFunctions>::default();
+    jit.set_max_iterations(10);
+    let function = jit
+        .compile_dynamic("sum(i, 1, x, 1) + for j in 0..x { 1 }", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[5.0]), Ok(6.0));
+    assert_eq!(function.call(&[1e300]), Ok(10.0));
+
+    // Without the limit adding one to `1e300` would never reach the end.
+    let function = JIT::<f64, DefaultFunctions>::default()
+        .compile_dynamic("sum(i, 1e300, 1e301, 1)", &[])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[]), Ok(1e6));
+
+    let mut interpreter = Interpreter::<f64, DefaultFunctions>::default();
+    let function = interpreter
+        .set_max_iterations(10)
+        .compile_dynamic("sum(i, 1, x, 1) + for j in 0..x { 1 }", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[5.0]), Ok(6.0));
+    assert_eq!(function.call(&[1e300]), Ok(10.0));
+}
+
+#[test]
+fn shared_iteration_budget() {
+    // Nested loops take their iterations from the same budget.
+    let nested = "sum(i, 0, 1e6, sum(j, 0, 1e6, sum(k, 0, 1e6, 1)))";
+    assert_eq!(evaluate(nested, 0.0), 999_998.0);
+    let mut jit = JIT::<f64, DefaultFunctions>::default();
+    jit.set_max_iterations(10);
+    let function = jit
+        .compile_dynamic("sum(i, 1, x, sum(j, 1, x, 1))", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[1e6]), Ok(9.0));
+    // The budget is per evaluation.
+    assert_eq!(function.call(&[2.0]), Ok(4.0));
+    assert_eq!(function.call(&[1e6]), Ok(9.0));
+
+    let function = Interpreter::<f64, DefaultFunctions>::default()
+        .set_max_iterations(10)
+        .compile_dynamic("sum(i, 1, x, sum(j, 1, x, 1))", &["x"])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[1e6]), Ok(9.0));
+    let function = Interpreter::<f64, DefaultFunctions>::default()
+        .compile_dynamic(nested, &[])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[]), Ok(999_998.0));
+
+    let function =
+        compile_expression!("sum(i, 0, n, sum(j, 0, n, sum(k, 0, n, 1)))", (n: i32) -> i32)
+            .expect("Compilation failed");
+    assert_eq!(function(1_000_000), 999_998);
+
+    // Every element of a batch has a budget of its own.
+    let function = compile_batch_expression!("sum(i, 0, x, sum(j, 0, x, 1))", (x) -> f32)
+        .expect("Compilation failed");
+    let x = vec![1e6; 5];
+    let mut output = vec![0.0; 5];
+    function.call(&[&x], &mut output).expect("Call failed");
+    assert_eq!(output, [999_999.0; 5]);
+}
+
+#[test]
+fn interpreter_matches_jit() {
+    for expression in [
+        "sum(i, 1, x, i ^ 2)",
+        "prod(i, x, x + 3, i)",
+        "a = 1; i = 0; for i in 0..x { a *= -2 }; a + i",
+        "sum(i, 1, 3, for j in 0..i { j })",
+    ] {
+        let interpreted = Interpreter::<f64, DefaultFunctions>::default()
+            .compile_dynamic(expression, &["x"])
+            .expect("Compilation failed");
+        for x in [-1.0, 0.0, 2.5, 4.0] {
+            assert_eq!(
+                interpreted.call(&[x]),
+                Ok(evaluate(expression, x)),
+                "{expression} for x = {x}"
+            );
+        }
+    }
+}
+
+#[test]
+fn batch() {
+    let function = compile_batch_expression!("a = 0; s = sum(i, 1, x, a = i); s + a", (x) -> f32)
+        .expect("Compilation failed");
+    let x: Vec<f32> = (0..11).map(|i| i as f32 - 2.0).collect();
+    let mut output = vec![0.0; 11];
+    function.call(&[&x], &mut output).expect("Call failed");
+    for (x, output) in x.iter().zip(output) {
+        let n = x.max(0.0);
+        assert_eq!(output, n * (n + 1.0) / 2.0 + n, "x = {x}");
+    }
+}
+
+#[test]
+fn typed() {
+    let function =
+        compile_expression!("sum(i, 1, n, i * i)", (n: i32) -> i32).expect("Compilation failed");
+    assert_eq!(function(4), 30);
+    let function = compile_expression!("prod(i, 1, n, x)", (n: u32, x: f64) -> f64)
+        .expect("Compilation failed");
+    assert_eq!(function(3, 1.5), 3.375);
+    let function = compile_expression!("sum(i, 0, 2, vec2(i, x))", (x: f32) -> Vec2)
+        .expect("Compilation failed");
+    assert_eq!(function(0.5), Vec2::new(3.0, 1.5));
+    let function = compile_expression!("for i in 0..n { i > 1 }", (n: i64) -> bool)
+        .expect("Compilation failed");
+    assert!(function(3));
+    assert!(!function(2));
+
+    let result = compile_expression!("v = vec2(x); sum(v, 0, 1, 1)", (x: f32) -> f32);
+    assert!(matches!(
+        result,
+        Err(JITError::TranslatorError(
+            TranslatorError::InvalidLoopVariable { .. }
+        ))
+    ));
+}
+
+#[test]
+fn complex_and_interval() {
+    let function = JIT::<Complex<f64>, ComplexFunctions<f64>>::default()
+        .compile_dynamic("prod(k, 1, 4, i)", &[])
+        .expect("Compilation failed");
+    assert_eq!(function.call(&[]), Ok(Complex::new(1.0, 0.0)));
+
+    let result = JIT::<Interval<f64>, IntervalFunctions<f64>>::default()
+        .compile_dynamic("sum(k, 1, x, k)", &["x"]);
+    let Err(JITError::TranslatorError(error @ TranslatorError::UnsupportedLoop { .. })) = result
+    else {
+        panic!("Loops compiled for intervals")
+    };
+    assert_eq!(error.span().map(|span| span.start), Some(4));
+}
+
+#[test]
+fn derivatives() {
+    for (expression, x) in [
+        ("sum(i, 1, 3, i * x ^ 2)", 1.5),
+        ("sum(i, x, x + 2, i ^ 2)", 0.5),
+        ("prod(i, 1, 3, x + i)", 0.5),
+        ("prod(i, 1, 3, x * i)", -2.0),
+        ("a = 1; for i in 0..3 { a = a * x + i }; a", 2.0),
+    ] {
+        assert_matches_difference(expression, x);
+    }
+}
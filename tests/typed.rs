@@ -0,0 +1,250 @@
+use anita::{
+    anita_macros::function_manager,
+    compile_expression,
+    default_functions::DefaultFunctions,
+    jit::{
+        frontend::parser,
+        types::{AnitaType, ValueType},
+        JITError, TranslatorError, JIT,
+    },
+};
+
+#[test]
+fn mixed_parameters() {
+    let function =
+        compile_expression!("x ^ n + n / 2", (n: i32, x: f64) -> f64).expect("Compilation failed");
+    // `n / 2` is an integer division, its result is converted for the addition.
+    assert_eq!(function(3, 2.0), 9.0);
+    assert_eq!(function(-3, 2.0), 0.125 - 1.0);
+
+    let function =
+        compile_expression!("x > 0.5 && x < 1", (x: f32) -> bool).expect("Compilation failed");
+    assert!(function(0.7));
+    assert!(!function(1.5));
+}
+
+#[test]
+fn promotion() {
+    let function =
+        compile_expression!("a + b", (a: i32, b: u32) -> i64).expect("Compilation failed");
+    assert_eq!(function(-1, u32::MAX), i64::from(u32::MAX) - 1);
+
+    let function =
+        compile_expression!("a * b", (a: i64, b: f32) -> f64).expect("Compilation failed");
+    assert_eq!(function(3, 0.5), 1.5);
+
+    let function =
+        compile_expression!("a - b", (a: f32, b: f64) -> f64).expect("Compilation failed");
+    assert_eq!(function(0.1, 0.1), f64::from(0.1f32) - 0.1);
+
+    let function =
+        compile_expression!("a % b", (a: u32, b: u64) -> u64).expect("Compilation failed");
+    assert_eq!(function(7, 4), 3);
+}
+
+#[test]
+fn literals_take_the_type_of_their_operand() {
+    let function = compile_expression!("n / 2", (n: i32) -> f64).expect("Compilation failed");
+    assert_eq!(function(7), 3.0);
+
+    let function = compile_expression!("n / 2.0", (n: i32) -> f64).expect("Compilation failed");
+    assert_eq!(function(7), 3.0);
+
+    let function = compile_expression!("n / 2.5", (n: i32) -> f64).expect("Compilation failed");
+    assert_eq!(function(5), 2.0);
+
+    let function = compile_expression!("1 / 4", (n: i32) -> f64).expect("Compilation failed");
+    assert_eq!(function(0), 0.25);
+
+    let function =
+        compile_expression!("y = 3; y / n", (n: u64) -> u64).expect("Compilation failed");
+    assert_eq!(function(2), 1);
+}
+
+#[test]
+fn casts() {
+    let function = compile_expression!("x as i32", (x: f64) -> i32).expect("Compilation failed");
+    assert_eq!(function(2.7), 2);
+    assert_eq!(function(-2.7), -2);
+    assert_eq!(function(1e20), i32::MAX);
+    assert_eq!(function(f64::NAN), 0);
+
+    let function = compile_expression!("x as u32", (x: i64) -> u64).expect("Compilation failed");
+    assert_eq!(function(-1), u64::from(u32::MAX));
+    let function = compile_expression!("x as i64", (x: i32) -> i64).expect("Compilation failed");
+    assert_eq!(function(-5), -5);
+    let function = compile_expression!("x as i64", (x: u32) -> i64).expect("Compilation failed");
+    assert_eq!(function(u32::MAX), i64::from(u32::MAX));
+    let function = compile_expression!("x as f32", (x: u64) -> f32).expect("Compilation failed");
+    assert_eq!(function(u64::MAX), u64::MAX as f32);
+    let function =
+        compile_expression!("(x as i32) / 2", (x: f64) -> f64).expect("Compilation failed");
+    assert_eq!(function(7.9), 3.0);
+    let function = compile_expression!("x as bool", (x: f32) -> i32).expect("Compilation failed");
+    assert_eq!(function(0.25), 1);
+    assert_eq!(function(0.0), 0);
+}
+
+#[test]
+fn booleans() {
+    let function =
+        compile_expression!("(x > 1) + (x > 2) * 2", (x: i32) -> i32).expect("Compilation failed");
+    assert_eq!(function(3), 3);
+
+    let function = compile_expression!("!b || x == 0 ? 1 : x", (b: bool, x: i64) -> i64)
+        .expect("Compilation failed");
+    assert_eq!(function(false, 5), 1);
+    assert_eq!(function(true, 5), 5);
+
+    let function = compile_expression!("if x { 2.5 } else { 1 }", (x: u32) -> f32)
+        .expect("Compilation failed");
+    assert_eq!(function(7), 2.5);
+    assert_eq!(function(0), 1.0);
+
+    let function =
+        compile_expression!("b == (x < 0)", (b: bool, x: f64) -> bool).expect("Compilation failed");
+    assert!(function(true, -1.0));
+    assert!(!function(false, -1.0));
+}
+
+#[test]
+fn variables_keep_their_first_type() {
+    let function =
+        compile_expression!("y = x; y = y * 2.5; y", (x: i32) -> f64).expect("Compilation failed");
+    // `y` is an `i32`, so `y * 2.5` is truncated on assignment.
+    assert_eq!(function(3), 7.0);
+
+    let function = compile_expression!("if x > 0 { y = x } else { y = 0.5 }; y", (x: f32) -> f64)
+        .expect("Compilation failed");
+    assert_eq!(function(-1.0), 0.5);
+}
+
+struct MixedFunctions;
+
+#[function_manager]
+impl MixedFunctions {
+    fn scale(n: i32, x: f64) -> f64 {
+        f64::from(n) * x
+    }
+
+    fn half(x: f32) -> f32 {
+        x / 2.0
+    }
+}
+
+#[test]
+fn function_arguments_are_converted() {
+    let function = compile_expression!("scale(n, half(n))", (n: u32) -> f64, MixedFunctions)
+        .expect("Compilation failed");
+    assert_eq!(function(3), 4.5);
+
+    let function = compile_expression!("scale(2.9, 1)", (n: u32) -> i64, MixedFunctions)
+        .expect("Compilation failed");
+    assert_eq!(function(0), 2);
+}
+
+#[test]
+fn incompatible_types() {
+    let Err(error) = compile_expression!("a + b", (a: u64, b: i32) -> u64) else {
+        panic!("Compilation succeeded")
+    };
+    let JITError::TranslatorError(TranslatorError::IncompatibleTypes { lhs, rhs, span }) = error
+    else {
+        panic!("unexpected error {error:?}")
+    };
+    assert_eq!((lhs, rhs), (ValueType::U64, ValueType::I32));
+    assert_eq!(span.map(|span| (span.start, span.end)), Some((0, 5)));
+    assert_eq!(
+        error.to_string(),
+        "`u64` and `i32` can not be combined without a cast"
+    );
+
+    let function =
+        compile_expression!("a + b as u64", (a: u64, b: i32) -> u64).expect("Compilation failed");
+    assert_eq!(function(1, -1), 0);
+}
+
+#[test]
+fn casts_in_untyped_expressions() {
+    let function = compile_expression!("x as f64 * 2", (x) -> f64).expect("Compilation failed");
+    assert_eq!(function(1.5), 3.0);
+
+    let Err(error) = compile_expression!("x as i32", (x) -> f64) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "casts to `i32` require typed parameters");
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((5, 8))
+    );
+}
+
+#[test]
+fn casts_round_trip_through_display() {
+    for expression in [
+        "-x as i32",
+        "-(x as i32)",
+        "a * b as u64",
+        "((a * b) as u64) ^ 2",
+        "x as bool as f32",
+    ] {
+        let parsed = parser::expression(expression).expect("Parsing failed");
+        assert_eq!(parsed.to_string(), expression);
+    }
+    assert!(parser::expression("x as i16").is_err());
+}
+
+#[test]
+fn compile_typed() {
+    let mut jit = JIT::<f64, DefaultFunctions>::default();
+    let code_ptr = jit
+        .compile_typed("x ^ 2", &[("x", ValueType::U64)], ValueType::U64)
+        .expect("Compilation failed");
+    let function = unsafe { std::mem::transmute::<*const u8, fn(u64) -> u64>(code_ptr) };
+    assert_eq!(function(1 << 20), 1 << 40);
+}
+
+#[test]
+fn optimization_matches_untyped_expressions() {
+    for optimize in [true, false] {
+        for expression in [
+            "x ^ 2",
+            "x ^ -3",
+            "x - x",
+            "x * 0",
+            "0 - x + -x",
+            "2 ^ 0.5 * x",
+        ] {
+            let mut jit = JIT::<f64, DefaultFunctions>::default();
+            jit.set_optimize(optimize);
+            let code_ptr = jit
+                .compile_typed(expression, &[("x", ValueType::F64)], ValueType::F64)
+                .expect("Compilation failed");
+            let typed = unsafe { std::mem::transmute::<*const u8, fn(f64) -> f64>(code_ptr) };
+            let mut jit = JIT::<f64, DefaultFunctions>::default();
+            jit.set_optimize(optimize);
+            let untyped = jit
+                .compile_dynamic(expression, &["x"])
+                .expect("Compilation failed");
+            for x in [1.1, -0.3, 0.0, f64::INFINITY, f64::NAN] {
+                let expected = untyped.call(&[x]).unwrap();
+                assert_eq!(
+                    typed(x).to_bits(),
+                    expected.to_bits(),
+                    "{expression} at {x} with optimize = {optimize}"
+                );
+            }
+        }
+    }
+    // `7 / 2` is `3` and `2 ^ 40` wraps to `0` as an `i32`, also when they are folded.
+    let mut jit = JIT::<f64, DefaultFunctions>::default();
+    let code_ptr = jit
+        .compile_typed(
+            "n + 7 / 2 + 2 ^ 40",
+            &[("n", ValueType::I32)],
+            ValueType::I64,
+        )
+        .expect("Compilation failed");
+    let function = unsafe { std::mem::transmute::<*const u8, fn(i32) -> i64>(code_ptr) };
+    assert_eq!(function(1), 4);
+}
@@ -0,0 +1,213 @@
+use anita::{
+    compile_expression,
+    default_functions::DefaultFunctions,
+    jit::{
+        frontend::{parser, Expr, Span},
+        types::{ValueType, Vec2, Vec3, Vec4},
+        JITError, TranslatorError,
+    },
+};
+
+#[test]
+fn constructors_and_swizzles() {
+    let function =
+        compile_expression!("vec3(x, 2, x * 3)", (x: f32) -> Vec3).expect("Compilation failed");
+    assert_eq!(function(1.5), Vec3::new(1.5, 2.0, 4.5));
+
+    let function = compile_expression!("v.zyx", (v: Vec3) -> Vec3).expect("Compilation failed");
+    assert_eq!(function(Vec3::new(1.0, 2.0, 3.0)), Vec3::new(3.0, 2.0, 1.0));
+
+    let function = compile_expression!("vec4(v.xx, n, 1)", (v: Vec2, n: i32) -> Vec4)
+        .expect("Compilation failed");
+    assert_eq!(
+        function(Vec2::new(1.0, 2.0), 7),
+        Vec4::new(1.0, 1.0, 7.0, 1.0)
+    );
+
+    let function = compile_expression!("v.y + v.w", (v: Vec4) -> f64).expect("Compilation failed");
+    assert_eq!(function(Vec4::new(1.0, 2.0, 3.0, 4.0)), 6.0);
+
+    // A single scalar is used for every component.
+    let function = compile_expression!("vec2(x)", (x: f64) -> Vec2).expect("Compilation failed");
+    assert_eq!(function(0.5), Vec2::splat(0.5));
+}
+
+#[test]
+fn broadcasting() {
+    let function =
+        compile_expression!("v * 2 + x", (v: Vec3, x: f64) -> Vec3).expect("Compilation failed");
+    assert_eq!(
+        function(Vec3::new(1.0, 2.0, 3.0), 0.5),
+        Vec3::new(2.5, 4.5, 6.5)
+    );
+
+    let function =
+        compile_expression!("1 - v / n", (v: Vec2, n: u32) -> Vec2).expect("Compilation failed");
+    assert_eq!(function(Vec2::new(2.0, -4.0), 2), Vec2::new(0.0, 3.0));
+
+    let function =
+        compile_expression!("v % 2 + v ^ 2 - -v", (v: Vec2) -> Vec2).expect("Compilation failed");
+    assert_eq!(function(Vec2::new(3.0, -1.0)), Vec2::new(13.0, -1.0));
+
+    // Results of the expression are broadcast too.
+    let function = compile_expression!("x + 1", (x: i32) -> Vec4).expect("Compilation failed");
+    assert_eq!(function(2), Vec4::splat(3.0));
+}
+
+#[test]
+fn inbuilt_functions() {
+    let a = Vec3::new(1.0, 2.0, 2.0);
+    let b = Vec3::new(0.0, 1.0, 0.0);
+
+    let function =
+        compile_expression!("dot(a, b)", (a: Vec3, b: Vec3) -> f32).expect("Compilation failed");
+    assert_eq!(function(a, b), 2.0);
+
+    let function =
+        compile_expression!("cross(a, b)", (a: Vec3, b: Vec3) -> Vec3).expect("Compilation failed");
+    assert_eq!(function(a, b), Vec3::new(-2.0, 0.0, 1.0));
+
+    let function = compile_expression!("length(a)", (a: Vec3) -> f32).expect("Compilation failed");
+    assert_eq!(function(a), 3.0);
+
+    let function =
+        compile_expression!("normalize(a) * 3", (a: Vec3) -> Vec3).expect("Compilation failed");
+    assert_eq!(function(a), a);
+
+    let function = compile_expression!("sqrt(dot(v, 1))", (v: Vec4) -> f32, DefaultFunctions)
+        .expect("Compilation failed");
+    assert_eq!(function(Vec4::new(1.0, 2.0, 3.0, 3.0)), 3.0);
+}
+
+#[test]
+fn variables_and_conditionals() {
+    let function = compile_expression!(
+        "n = normalize(v); d = dot(n, l); d > 0 ? n * d : vec3(0)",
+        (v: Vec3, l: Vec3) -> Vec3
+    )
+    .expect("Compilation failed");
+    let up = Vec3::new(0.0, 0.0, 2.0);
+    assert_eq!(
+        function(up, Vec3::new(1.0, 0.0, 0.5)),
+        Vec3::new(0.0, 0.0, 0.5)
+    );
+    assert_eq!(function(up, Vec3::new(0.0, 0.0, -1.0)), Vec3::splat(0.0));
+
+    let function = compile_expression!("a == b.yx && a != 0", (a: Vec2, b: Vec2) -> bool)
+        .expect("Compilation failed");
+    assert!(function(Vec2::new(1.0, 2.0), Vec2::new(2.0, 1.0)));
+    assert!(!function(Vec2::new(1.0, 2.0), Vec2::new(1.0, 2.0)));
+}
+
+#[test]
+fn type_errors() {
+    let Err(error) = compile_expression!("a + b", (a: Vec2, b: Vec3) -> Vec3) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "`vec3` can not be converted to `vec2`");
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((0, 5))
+    );
+
+    let Err(error) = compile_expression!("v", (v: Vec3) -> f32) else {
+        panic!("Compilation succeeded")
+    };
+    assert!(matches!(
+        error,
+        JITError::TranslatorError(TranslatorError::InvalidConversion {
+            from: ValueType::Vec3,
+            to: ValueType::F32,
+            span: Some(Span { start: 0, end: 1 })
+        })
+    ));
+
+    let Err(error) = compile_expression!("v as f32", (v: Vec3) -> f32) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((5, 8))
+    );
+
+    let Err(error) = compile_expression!("v < 1", (v: Vec2) -> bool) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(
+        error.to_string(),
+        "`vec2` can only be compared with `==` and `!=`"
+    );
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((0, 1))
+    );
+
+    let Err(error) = compile_expression!("v ? 1 : 0", (v: Vec2) -> f32) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "`vec2` can not be converted to `bool`");
+
+    let Err(error) = compile_expression!("v.xz", (v: Vec2) -> Vec2) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "`vec2` has no components `xz`");
+    assert_eq!(
+        error.span().map(|span| (span.start, span.end)),
+        Some((2, 4))
+    );
+
+    let Err(error) = compile_expression!("vec3(v, v)", (v: Vec2) -> Vec3) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(
+        error.to_string(),
+        "function `vec3` can not be called with `vec2`, `vec2`"
+    );
+
+    let Err(error) = compile_expression!("cross(v, v)", (v: Vec2) -> Vec2) else {
+        panic!("Compilation succeeded")
+    };
+    assert!(matches!(
+        error,
+        JITError::TranslatorError(TranslatorError::InvalidArguments { .. })
+    ));
+
+    let Err(error) = compile_expression!("sin(v)", (v: Vec2) -> Vec2, DefaultFunctions) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "`vec2` can not be converted to `f32`");
+
+    let Err(error) = compile_expression!("x.x", (x) -> f32) else {
+        panic!("Compilation succeeded")
+    };
+    assert_eq!(error.to_string(), "swizzles require typed parameters");
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(
+        parser::expression("v.xy").expect("Parsing failed"),
+        Expr::Swizzle {
+            value: Box::new(Expr::VariableRead {
+                identifier: "v".to_owned(),
+                span: Span::new(0, 1)
+            }),
+            components: "xy".to_owned(),
+            span: Span::new(2, 4)
+        }
+    );
+    for expression in [
+        "v.xyz.x",
+        "-v.x",
+        "(a + b).yx ^ 2",
+        "vec3(1, 2, 3).zzz",
+        "(1).x",
+        "(-v).x",
+    ] {
+        let parsed = parser::expression(expression).expect("Parsing failed");
+        assert_eq!(parsed.to_string(), expression);
+    }
+    for expression in ["v.xa", "v.xyzwx", "v.", "v.rgb"] {
+        assert!(parser::expression(expression).is_err(), "{expression}");
+    }
+}